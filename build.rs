@@ -18,6 +18,9 @@ use std::io::Write;
 use std::path::{Path, PathBuf};
 
 fn main() {
+    // The generated code derives serde traits under the `serde` feature,
+    // which is not defined by this crate.
+    println!("cargo:rustc-check-cfg=cfg(feature, values(\"serde\"))");
     generate_module(&Path::new("src/uci_packets.pdl").canonicalize().unwrap());
 }
 
@@ -40,8 +40,9 @@ class Host:
     async def _read_packet(self) -> bytes:
         """Read a single UCI packet from the socket.
 
-        The packet is automatically re-assembled if segmented on
-        the UCI transport."""
+        Control packets are automatically re-assembled if segmented on
+        the UCI transport. Data packets are returned unmodified, each
+        one carrying a segment of an application data message."""
 
         complete_packet_bytes = bytes()
 
@@ -69,6 +70,9 @@ class Host:
 
             complete_packet_bytes += payload_bytes
 
+            if common_header.mt == uci.MessageType.DATA:
+                return header_bytes + complete_packet_bytes
+
             # Check the Packet Boundary Flag.
             match common_header.pbf:
                 case uci.PacketBoundaryFlag.COMPLETE:
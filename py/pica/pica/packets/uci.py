@@ -195,6 +195,24 @@ class SessionControlOpcodeId(enum.IntEnum):
             raise exn
 
 
+class TestOpcodeId(enum.IntEnum):
+    CONFIG_SET = 0x0
+    CONFIG_GET = 0x1
+    PERIODIC_TX = 0x2
+    PER_RX = 0x3
+    RX = 0x5
+    LOOPBACK = 0x6
+    STOP_SESSION = 0x7
+    SS_TWR = 0x8
+
+    @staticmethod
+    def from_int(v: int) -> Union[int, 'TestOpcodeId']:
+        try:
+            return TestOpcodeId(v)
+        except ValueError as exn:
+            raise exn
+
+
 class AndroidOpcodeId(enum.IntEnum):
     GET_POWER_STATS = 0x0
     SET_COUNTRY_CODE = 0x1
@@ -1140,6 +1158,13 @@ class ControlPacket(Packet):
             return child, span
         except Exception as exn:
             pass
+        try:
+            child, remainder = TestPacket.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
         try:
             child, remainder = AndroidPacket.parse(fields.copy(), payload)
             if remainder:
@@ -1625,6 +1650,34 @@ class SessionConfigPacket(ControlPacket):
             return child, span
         except Exception as exn:
             pass
+        try:
+            child, remainder = SessionUpdateControllerMulticastListExtendedRsp.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = SessionUpdateControllerMulticastListExtendedNtf.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = SessionUpdateControllerMulticastListRsp_V1.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = SessionUpdateControllerMulticastListNtf_V1.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
         try:
             child, remainder = SessionQueryMaxDataSizeInRangingCmd.parse(fields.copy(), payload)
             if remainder:
@@ -1747,6 +1800,185 @@ class SessionControlPacket(ControlPacket):
     def size(self) -> int:
         return len(self.payload) + 3
 
+@dataclass
+class TestPacket(ControlPacket):
+    oid: TestOpcodeId = field(kw_only=True, default=TestOpcodeId.CONFIG_SET)
+
+    def __post_init__(self):
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestPacket', bytes]:
+        if fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 3:
+            raise Exception('Invalid packet size')
+        fields['oid'] = TestOpcodeId.from_int((span[0] >> 0) & 0x3f)
+        value_ = int.from_bytes(span[1:3], byteorder='little')
+        span = span[3:]
+        payload = span
+        span = bytes([])
+        fields['payload'] = payload
+        try:
+            child, remainder = TestConfigSetCmd.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestConfigSetRsp.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestConfigGetCmd.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestConfigGetRsp.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestPeriodicTxCmd.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestPeriodicTxRsp.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestPeriodicTxNtf.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestPerRxCmd.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestPerRxRsp.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestPerRxNtf.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestRxCmd.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestRxRsp.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestRxNtf.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestLoopbackCmd.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestLoopbackRsp.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestLoopbackNtf.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestStopSessionCmd.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestStopSessionRsp.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestSsTwrCmd.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestSsTwrRsp.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        try:
+            child, remainder = TestSsTwrNtf.parse(fields.copy(), payload)
+            if remainder:
+                raise Exception('Unexpected parsing remainder')
+            return child, span
+        except Exception as exn:
+            pass
+        return TestPacket(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.oid << 0))
+        _span.extend([0] * 2)
+        _span.extend(payload or self.payload or [])
+        return ControlPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return len(self.payload) + 3
+
 @dataclass
 class AndroidPacket(ControlPacket):
     oid: AndroidOpcodeId = field(kw_only=True, default=AndroidOpcodeId.GET_POWER_STATS)
@@ -2377,7 +2609,7 @@ class CoreQueryTimeStampCmd(CorePacket):
 @dataclass
 class CoreQueryTimeStampRsp(CorePacket):
     status: Status = field(kw_only=True, default=Status.OK)
-    timeStamp: int = field(kw_only=True, default=0)
+    timestamp: int = field(kw_only=True, default=0)
 
     def __post_init__(self):
         self.mt = MessageType.RESPONSE
@@ -2392,17 +2624,17 @@ class CoreQueryTimeStampRsp(CorePacket):
             raise Exception('Invalid packet size')
         fields['status'] = Status.from_int(span[0])
         value_ = int.from_bytes(span[1:9], byteorder='little')
-        fields['timeStamp'] = value_
+        fields['timestamp'] = value_
         span = span[9:]
         return CoreQueryTimeStampRsp(**fields), span
 
     def serialize(self, payload: bytes = None) -> bytes:
         _span = bytearray()
         _span.append((self.status << 0))
-        if self.timeStamp > 18446744073709551615:
-            print(f"Invalid value for field CoreQueryTimeStampRsp::timeStamp: {self.timeStamp} > 18446744073709551615; the value will be truncated")
-            self.timeStamp &= 18446744073709551615
-        _span.extend(int.to_bytes((self.timeStamp << 0), length=8, byteorder='little'))
+        if self.timestamp > 18446744073709551615:
+            print(f"Invalid value for field CoreQueryTimeStampRsp::timestamp: {self.timestamp} > 18446744073709551615; the value will be truncated")
+            self.timestamp &= 18446744073709551615
+        _span.extend(int.to_bytes((self.timestamp << 0), length=8, byteorder='little'))
         return CorePacket.serialize(self, payload = bytes(_span))
 
     @property
@@ -2968,9 +3200,53 @@ class SessionGetStateRsp(SessionConfigPacket):
     def size(self) -> int:
         return 2
 
+class DtAnchorActingRole(enum.IntEnum):
+    RESPONDER = 0x0
+    INITIATOR = 0x1
+
+    @staticmethod
+    def from_int(v: int) -> Union[int, 'DtAnchorActingRole']:
+        try:
+            return DtAnchorActingRole(v)
+        except ValueError as exn:
+            raise exn
+
+
+@dataclass
+class DtAnchorRangingRound(Packet):
+    ranging_round_index: int = field(kw_only=True, default=0)
+    acting_role: DtAnchorActingRole = field(kw_only=True, default=DtAnchorActingRole.RESPONDER)
+
+    def __post_init__(self):
+        pass
+
+    @staticmethod
+    def parse(span: bytes) -> Tuple['DtAnchorRangingRound', bytes]:
+        fields = {'payload': None}
+        if len(span) < 2:
+            raise Exception('Invalid packet size')
+        fields['ranging_round_index'] = span[0]
+        fields['acting_role'] = DtAnchorActingRole.from_int(span[1])
+        span = span[2:]
+        return DtAnchorRangingRound(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        if self.ranging_round_index > 255:
+            print(f"Invalid value for field DtAnchorRangingRound::ranging_round_index: {self.ranging_round_index} > 255; the value will be truncated")
+            self.ranging_round_index &= 255
+        _span.append((self.ranging_round_index << 0))
+        _span.append((self.acting_role << 0))
+        return bytes(_span)
+
+    @property
+    def size(self) -> int:
+        return 2
+
 @dataclass
 class SessionUpdateDtAnchorRangingRoundsCmd(SessionConfigPacket):
-    
+    session_token: int = field(kw_only=True, default=0)
+    ranging_rounds: List[DtAnchorRangingRound] = field(kw_only=True, default_factory=list)
 
     def __post_init__(self):
         self.mt = MessageType.COMMAND
@@ -2981,19 +3257,43 @@ class SessionUpdateDtAnchorRangingRoundsCmd(SessionConfigPacket):
     def parse(fields: dict, span: bytes) -> Tuple['SessionUpdateDtAnchorRangingRoundsCmd', bytes]:
         if fields['mt'] != MessageType.COMMAND or fields['oid'] != SessionConfigOpcodeId.UPDATE_DT_ANCHOR_RANGING_ROUNDS or fields['gid'] != GroupId.SESSION_CONFIG:
             raise Exception("Invalid constraint field values")
+        if len(span) < 5:
+            raise Exception('Invalid packet size')
+        value_ = int.from_bytes(span[0:4], byteorder='little')
+        fields['session_token'] = value_
+        ranging_rounds_count = span[4]
+        span = span[5:]
+        if len(span) < ranging_rounds_count * 2:
+            raise Exception('Invalid packet size')
+        ranging_rounds = []
+        for n in range(ranging_rounds_count):
+            ranging_rounds.append(DtAnchorRangingRound.parse_all(span[n * 2:(n + 1) * 2]))
+        fields['ranging_rounds'] = ranging_rounds
+        span = span[ranging_rounds_count * 2:]
         return SessionUpdateDtAnchorRangingRoundsCmd(**fields), span
 
     def serialize(self, payload: bytes = None) -> bytes:
         _span = bytearray()
+        if self.session_token > 4294967295:
+            print(f"Invalid value for field SessionUpdateDtAnchorRangingRoundsCmd::session_token: {self.session_token} > 4294967295; the value will be truncated")
+            self.session_token &= 4294967295
+        _span.extend(int.to_bytes((self.session_token << 0), length=4, byteorder='little'))
+        if len(self.ranging_rounds) > 255:
+            print(f"Invalid length for field SessionUpdateDtAnchorRangingRoundsCmd::ranging_rounds:  {len(self.ranging_rounds)} > 255; the array will be truncated")
+            del self.ranging_rounds[255:]
+        _span.append((len(self.ranging_rounds) << 0))
+        for _elt in self.ranging_rounds:
+            _span.extend(_elt.serialize())
         return SessionConfigPacket.serialize(self, payload = bytes(_span))
 
     @property
     def size(self) -> int:
-        return 0
+        return sum([elt.size for elt in self.ranging_rounds]) + 5
 
 @dataclass
 class SessionUpdateDtAnchorRangingRoundsRsp(SessionConfigPacket):
-    
+    status: Status = field(kw_only=True, default=Status.OK)
+    ranging_round_indexes: bytearray = field(kw_only=True, default_factory=bytearray)
 
     def __post_init__(self):
         self.mt = MessageType.RESPONSE
@@ -3004,15 +3304,30 @@ class SessionUpdateDtAnchorRangingRoundsRsp(SessionConfigPacket):
     def parse(fields: dict, span: bytes) -> Tuple['SessionUpdateDtAnchorRangingRoundsRsp', bytes]:
         if fields['mt'] != MessageType.RESPONSE or fields['oid'] != SessionConfigOpcodeId.UPDATE_DT_ANCHOR_RANGING_ROUNDS or fields['gid'] != GroupId.SESSION_CONFIG:
             raise Exception("Invalid constraint field values")
+        if len(span) < 2:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        ranging_round_indexes_count = span[1]
+        span = span[2:]
+        if len(span) < ranging_round_indexes_count:
+            raise Exception('Invalid packet size')
+        fields['ranging_round_indexes'] = list(span[:ranging_round_indexes_count])
+        span = span[ranging_round_indexes_count:]
         return SessionUpdateDtAnchorRangingRoundsRsp(**fields), span
 
     def serialize(self, payload: bytes = None) -> bytes:
         _span = bytearray()
+        _span.append((self.status << 0))
+        if len(self.ranging_round_indexes) > 255:
+            print(f"Invalid length for field SessionUpdateDtAnchorRangingRoundsRsp::ranging_round_indexes:  {len(self.ranging_round_indexes)} > 255; the array will be truncated")
+            del self.ranging_round_indexes[255:]
+        _span.append((len(self.ranging_round_indexes) << 0))
+        _span.extend(self.ranging_round_indexes)
         return SessionConfigPacket.serialize(self, payload = bytes(_span))
 
     @property
     def size(self) -> int:
-        return 0
+        return len(self.ranging_round_indexes) * 1 + 2
 
 @dataclass
 class SessionUpdateDtTagRangingRoundsCmd(SessionConfigPacket):
@@ -3213,11 +3528,128 @@ class Controlee_V2_0_32_Byte_Version(Packet):
     def size(self) -> int:
         return 38
 
-class UpdateMulticastListAction(enum.IntEnum):
-    ADD_CONTROLEE = 0x0
-    REMOVE_CONTROLEE = 0x1
-    ADD_CONTROLEE_WITH_SHORT_SUB_SESSION_KEY = 0x2
-    ADD_CONTROLEE_WITH_EXTENDED_SUB_SESSION_KEY = 0x3
+@dataclass
+class ExtendedControlee(Packet):
+    extended_address: bytearray = field(kw_only=True, default_factory=bytearray)
+    subsession_id: int = field(kw_only=True, default=0)
+
+    def __post_init__(self):
+        pass
+
+    @staticmethod
+    def parse(span: bytes) -> Tuple['ExtendedControlee', bytes]:
+        fields = {'payload': None}
+        if len(span) < 8:
+            raise Exception('Invalid packet size')
+        fields['extended_address'] = list(span[:8])
+        span = span[8:]
+        if len(span) < 4:
+            raise Exception('Invalid packet size')
+        value_ = int.from_bytes(span[0:4], byteorder='little')
+        fields['subsession_id'] = value_
+        span = span[4:]
+        return ExtendedControlee(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.extend(self.extended_address)
+        if self.subsession_id > 4294967295:
+            print(f"Invalid value for field ExtendedControlee::subsession_id: {self.subsession_id} > 4294967295; the value will be truncated")
+            self.subsession_id &= 4294967295
+        _span.extend(int.to_bytes((self.subsession_id << 0), length=4, byteorder='little'))
+        return bytes(_span)
+
+    @property
+    def size(self) -> int:
+        return 12
+
+@dataclass
+class ExtendedControlee_V2_0_16_Byte_Version(Packet):
+    extended_address: bytearray = field(kw_only=True, default_factory=bytearray)
+    subsession_id: int = field(kw_only=True, default=0)
+    subsession_key: bytearray = field(kw_only=True, default_factory=bytearray)
+
+    def __post_init__(self):
+        pass
+
+    @staticmethod
+    def parse(span: bytes) -> Tuple['ExtendedControlee_V2_0_16_Byte_Version', bytes]:
+        fields = {'payload': None}
+        if len(span) < 8:
+            raise Exception('Invalid packet size')
+        fields['extended_address'] = list(span[:8])
+        span = span[8:]
+        if len(span) < 4:
+            raise Exception('Invalid packet size')
+        value_ = int.from_bytes(span[0:4], byteorder='little')
+        fields['subsession_id'] = value_
+        span = span[4:]
+        if len(span) < 16:
+            raise Exception('Invalid packet size')
+        fields['subsession_key'] = list(span[:16])
+        span = span[16:]
+        return ExtendedControlee_V2_0_16_Byte_Version(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.extend(self.extended_address)
+        if self.subsession_id > 4294967295:
+            print(f"Invalid value for field ExtendedControlee_V2_0_16_Byte_Version::subsession_id: {self.subsession_id} > 4294967295; the value will be truncated")
+            self.subsession_id &= 4294967295
+        _span.extend(int.to_bytes((self.subsession_id << 0), length=4, byteorder='little'))
+        _span.extend(self.subsession_key)
+        return bytes(_span)
+
+    @property
+    def size(self) -> int:
+        return 28
+
+@dataclass
+class ExtendedControlee_V2_0_32_Byte_Version(Packet):
+    extended_address: bytearray = field(kw_only=True, default_factory=bytearray)
+    subsession_id: int = field(kw_only=True, default=0)
+    subsession_key: bytearray = field(kw_only=True, default_factory=bytearray)
+
+    def __post_init__(self):
+        pass
+
+    @staticmethod
+    def parse(span: bytes) -> Tuple['ExtendedControlee_V2_0_32_Byte_Version', bytes]:
+        fields = {'payload': None}
+        if len(span) < 8:
+            raise Exception('Invalid packet size')
+        fields['extended_address'] = list(span[:8])
+        span = span[8:]
+        if len(span) < 4:
+            raise Exception('Invalid packet size')
+        value_ = int.from_bytes(span[0:4], byteorder='little')
+        fields['subsession_id'] = value_
+        span = span[4:]
+        if len(span) < 32:
+            raise Exception('Invalid packet size')
+        fields['subsession_key'] = list(span[:32])
+        span = span[32:]
+        return ExtendedControlee_V2_0_32_Byte_Version(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.extend(self.extended_address)
+        if self.subsession_id > 4294967295:
+            print(f"Invalid value for field ExtendedControlee_V2_0_32_Byte_Version::subsession_id: {self.subsession_id} > 4294967295; the value will be truncated")
+            self.subsession_id &= 4294967295
+        _span.extend(int.to_bytes((self.subsession_id << 0), length=4, byteorder='little'))
+        _span.extend(self.subsession_key)
+        return bytes(_span)
+
+    @property
+    def size(self) -> int:
+        return 44
+
+class UpdateMulticastListAction(enum.IntEnum):
+    ADD_CONTROLEE = 0x0
+    REMOVE_CONTROLEE = 0x1
+    ADD_CONTROLEE_WITH_SHORT_SUB_SESSION_KEY = 0x2
+    ADD_CONTROLEE_WITH_EXTENDED_SUB_SESSION_KEY = 0x3
 
     @staticmethod
     def from_int(v: int) -> Union[int, 'UpdateMulticastListAction']:
@@ -3378,32 +3810,115 @@ class SessionUpdateControllerMulticastListCmd_2_0_32_Byte_Payload(Packet):
         return sum([elt.size for elt in self.controlees]) + 1
 
 @dataclass
-class SessionUpdateControllerMulticastListRsp(SessionConfigPacket):
-    status: Status = field(kw_only=True, default=Status.OK)
+class SessionUpdateControllerMulticastListCmdExtendedPayload(Packet):
+    controlees: List[ExtendedControlee] = field(kw_only=True, default_factory=list)
 
     def __post_init__(self):
-        self.mt = MessageType.RESPONSE
-        self.oid = SessionConfigOpcodeId.UPDATE_CONTROLLER_MULTICAST_LIST
-        self.gid = GroupId.SESSION_CONFIG
+        pass
 
     @staticmethod
-    def parse(fields: dict, span: bytes) -> Tuple['SessionUpdateControllerMulticastListRsp', bytes]:
-        if fields['mt'] != MessageType.RESPONSE or fields['oid'] != SessionConfigOpcodeId.UPDATE_CONTROLLER_MULTICAST_LIST or fields['gid'] != GroupId.SESSION_CONFIG:
-            raise Exception("Invalid constraint field values")
+    def parse(span: bytes) -> Tuple['SessionUpdateControllerMulticastListCmdExtendedPayload', bytes]:
+        fields = {'payload': None}
         if len(span) < 1:
             raise Exception('Invalid packet size')
-        fields['status'] = Status.from_int(span[0])
+        controlees_count = span[0]
         span = span[1:]
-        return SessionUpdateControllerMulticastListRsp(**fields), span
+        if len(span) < controlees_count * 12:
+            raise Exception('Invalid packet size')
+        controlees = []
+        for n in range(controlees_count):
+            controlees.append(ExtendedControlee.parse_all(span[n * 12:(n + 1) * 12]))
+        fields['controlees'] = controlees
+        span = span[controlees_count * 12:]
+        return SessionUpdateControllerMulticastListCmdExtendedPayload(**fields), span
 
     def serialize(self, payload: bytes = None) -> bytes:
         _span = bytearray()
-        _span.append((self.status << 0))
-        return SessionConfigPacket.serialize(self, payload = bytes(_span))
+        if len(self.controlees) > 255:
+            print(f"Invalid length for field SessionUpdateControllerMulticastListCmdExtendedPayload::controlees:  {len(self.controlees)} > 255; the array will be truncated")
+            del self.controlees[255:]
+        _span.append((len(self.controlees) << 0))
+        for _elt in self.controlees:
+            _span.extend(_elt.serialize())
+        return bytes(_span)
 
     @property
     def size(self) -> int:
-        return 1
+        return sum([elt.size for elt in self.controlees]) + 1
+
+@dataclass
+class SessionUpdateControllerMulticastListCmd_2_0_16_Byte_ExtendedPayload(Packet):
+    controlees: List[ExtendedControlee_V2_0_16_Byte_Version] = field(kw_only=True, default_factory=list)
+
+    def __post_init__(self):
+        pass
+
+    @staticmethod
+    def parse(span: bytes) -> Tuple['SessionUpdateControllerMulticastListCmd_2_0_16_Byte_ExtendedPayload', bytes]:
+        fields = {'payload': None}
+        if len(span) < 1:
+            raise Exception('Invalid packet size')
+        controlees_count = span[0]
+        span = span[1:]
+        if len(span) < controlees_count * 28:
+            raise Exception('Invalid packet size')
+        controlees = []
+        for n in range(controlees_count):
+            controlees.append(ExtendedControlee_V2_0_16_Byte_Version.parse_all(span[n * 28:(n + 1) * 28]))
+        fields['controlees'] = controlees
+        span = span[controlees_count * 28:]
+        return SessionUpdateControllerMulticastListCmd_2_0_16_Byte_ExtendedPayload(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        if len(self.controlees) > 255:
+            print(f"Invalid length for field SessionUpdateControllerMulticastListCmd_2_0_16_Byte_ExtendedPayload::controlees:  {len(self.controlees)} > 255; the array will be truncated")
+            del self.controlees[255:]
+        _span.append((len(self.controlees) << 0))
+        for _elt in self.controlees:
+            _span.extend(_elt.serialize())
+        return bytes(_span)
+
+    @property
+    def size(self) -> int:
+        return sum([elt.size for elt in self.controlees]) + 1
+
+@dataclass
+class SessionUpdateControllerMulticastListCmd_2_0_32_Byte_ExtendedPayload(Packet):
+    controlees: List[ExtendedControlee_V2_0_32_Byte_Version] = field(kw_only=True, default_factory=list)
+
+    def __post_init__(self):
+        pass
+
+    @staticmethod
+    def parse(span: bytes) -> Tuple['SessionUpdateControllerMulticastListCmd_2_0_32_Byte_ExtendedPayload', bytes]:
+        fields = {'payload': None}
+        if len(span) < 1:
+            raise Exception('Invalid packet size')
+        controlees_count = span[0]
+        span = span[1:]
+        if len(span) < controlees_count * 44:
+            raise Exception('Invalid packet size')
+        controlees = []
+        for n in range(controlees_count):
+            controlees.append(ExtendedControlee_V2_0_32_Byte_Version.parse_all(span[n * 44:(n + 1) * 44]))
+        fields['controlees'] = controlees
+        span = span[controlees_count * 44:]
+        return SessionUpdateControllerMulticastListCmd_2_0_32_Byte_ExtendedPayload(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        if len(self.controlees) > 255:
+            print(f"Invalid length for field SessionUpdateControllerMulticastListCmd_2_0_32_Byte_ExtendedPayload::controlees:  {len(self.controlees)} > 255; the array will be truncated")
+            del self.controlees[255:]
+        _span.append((len(self.controlees) << 0))
+        for _elt in self.controlees:
+            _span.extend(_elt.serialize())
+        return bytes(_span)
+
+    @property
+    def size(self) -> int:
+        return sum([elt.size for elt in self.controlees]) + 1
 
 @dataclass
 class ControleeStatus(Packet):
@@ -3436,6 +3951,49 @@ class ControleeStatus(Packet):
     def size(self) -> int:
         return 3
 
+@dataclass
+class SessionUpdateControllerMulticastListRsp(SessionConfigPacket):
+    status: Status = field(kw_only=True, default=Status.OK)
+    controlee_status: List[ControleeStatus] = field(kw_only=True, default_factory=list)
+
+    def __post_init__(self):
+        self.mt = MessageType.RESPONSE
+        self.oid = SessionConfigOpcodeId.UPDATE_CONTROLLER_MULTICAST_LIST
+        self.gid = GroupId.SESSION_CONFIG
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['SessionUpdateControllerMulticastListRsp', bytes]:
+        if fields['mt'] != MessageType.RESPONSE or fields['oid'] != SessionConfigOpcodeId.UPDATE_CONTROLLER_MULTICAST_LIST or fields['gid'] != GroupId.SESSION_CONFIG:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 2:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        controlee_status_count = span[1]
+        span = span[2:]
+        if len(span) < controlee_status_count * 3:
+            raise Exception('Invalid packet size')
+        controlee_status = []
+        for n in range(controlee_status_count):
+            controlee_status.append(ControleeStatus.parse_all(span[n * 3:(n + 1) * 3]))
+        fields['controlee_status'] = controlee_status
+        span = span[controlee_status_count * 3:]
+        return SessionUpdateControllerMulticastListRsp(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.status << 0))
+        if len(self.controlee_status) > 255:
+            print(f"Invalid length for field SessionUpdateControllerMulticastListRsp::controlee_status:  {len(self.controlee_status)} > 255; the array will be truncated")
+            del self.controlee_status[255:]
+        _span.append((len(self.controlee_status) << 0))
+        for _elt in self.controlee_status:
+            _span.extend(_elt.serialize())
+        return SessionConfigPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return sum([elt.size for elt in self.controlee_status]) + 2
+
 @dataclass
 class SessionUpdateControllerMulticastListNtf(SessionConfigPacket):
     session_token: int = field(kw_only=True, default=0)
@@ -3484,42 +4042,282 @@ class SessionUpdateControllerMulticastListNtf(SessionConfigPacket):
         return sum([elt.size for elt in self.controlee_status]) + 5
 
 @dataclass
-class SessionDataCreditNtf(SessionControlPacket):
-    session_token: int = field(kw_only=True, default=0)
-    credit_availability: CreditAvailability = field(kw_only=True, default=CreditAvailability.CREDIT_NOT_AVAILABLE)
+class ExtendedControleeStatus(Packet):
+    mac_address: bytearray = field(kw_only=True, default_factory=bytearray)
+    status: MulticastUpdateStatus = field(kw_only=True, default=MulticastUpdateStatus.OK_MULTICAST_LIST_UPDATE)
 
     def __post_init__(self):
-        self.mt = MessageType.NOTIFICATION
-        self.oid = SessionControlOpcodeId.DATA_CREDIT
-        self.gid = GroupId.SESSION_CONTROL
+        pass
 
     @staticmethod
-    def parse(fields: dict, span: bytes) -> Tuple['SessionDataCreditNtf', bytes]:
-        if fields['mt'] != MessageType.NOTIFICATION or fields['oid'] != SessionControlOpcodeId.DATA_CREDIT or fields['gid'] != GroupId.SESSION_CONTROL:
-            raise Exception("Invalid constraint field values")
-        if len(span) < 5:
+    def parse(span: bytes) -> Tuple['ExtendedControleeStatus', bytes]:
+        fields = {'payload': None}
+        if len(span) < 8:
             raise Exception('Invalid packet size')
-        value_ = int.from_bytes(span[0:4], byteorder='little')
-        fields['session_token'] = value_
-        fields['credit_availability'] = CreditAvailability.from_int(span[4])
-        span = span[5:]
-        return SessionDataCreditNtf(**fields), span
+        fields['mac_address'] = list(span[:8])
+        span = span[8:]
+        if len(span) < 1:
+            raise Exception('Invalid packet size')
+        fields['status'] = MulticastUpdateStatus.from_int(span[0])
+        span = span[1:]
+        return ExtendedControleeStatus(**fields), span
 
     def serialize(self, payload: bytes = None) -> bytes:
         _span = bytearray()
-        if self.session_token > 4294967295:
-            print(f"Invalid value for field SessionDataCreditNtf::session_token: {self.session_token} > 4294967295; the value will be truncated")
-            self.session_token &= 4294967295
-        _span.extend(int.to_bytes((self.session_token << 0), length=4, byteorder='little'))
-        _span.append((self.credit_availability << 0))
-        return SessionControlPacket.serialize(self, payload = bytes(_span))
+        _span.extend(self.mac_address)
+        _span.append((self.status << 0))
+        return bytes(_span)
 
     @property
     def size(self) -> int:
-        return 5
+        return 9
 
 @dataclass
-class SessionDataTransferStatusNtf(SessionControlPacket):
+class SessionUpdateControllerMulticastListExtendedRsp(SessionConfigPacket):
+    status: Status = field(kw_only=True, default=Status.OK)
+    controlee_status: List[ExtendedControleeStatus] = field(kw_only=True, default_factory=list)
+
+    def __post_init__(self):
+        self.mt = MessageType.RESPONSE
+        self.oid = SessionConfigOpcodeId.UPDATE_CONTROLLER_MULTICAST_LIST
+        self.gid = GroupId.SESSION_CONFIG
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['SessionUpdateControllerMulticastListExtendedRsp', bytes]:
+        if fields['mt'] != MessageType.RESPONSE or fields['oid'] != SessionConfigOpcodeId.UPDATE_CONTROLLER_MULTICAST_LIST or fields['gid'] != GroupId.SESSION_CONFIG:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 2:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        controlee_status_count = span[1]
+        span = span[2:]
+        if len(span) < controlee_status_count * 9:
+            raise Exception('Invalid packet size')
+        controlee_status = []
+        for n in range(controlee_status_count):
+            controlee_status.append(ExtendedControleeStatus.parse_all(span[n * 9:(n + 1) * 9]))
+        fields['controlee_status'] = controlee_status
+        span = span[controlee_status_count * 9:]
+        return SessionUpdateControllerMulticastListExtendedRsp(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.status << 0))
+        if len(self.controlee_status) > 255:
+            print(f"Invalid length for field SessionUpdateControllerMulticastListExtendedRsp::controlee_status:  {len(self.controlee_status)} > 255; the array will be truncated")
+            del self.controlee_status[255:]
+        _span.append((len(self.controlee_status) << 0))
+        for _elt in self.controlee_status:
+            _span.extend(_elt.serialize())
+        return SessionConfigPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return sum([elt.size for elt in self.controlee_status]) + 2
+
+@dataclass
+class SessionUpdateControllerMulticastListExtendedNtf(SessionConfigPacket):
+    session_token: int = field(kw_only=True, default=0)
+    controlee_status: List[ExtendedControleeStatus] = field(kw_only=True, default_factory=list)
+
+    def __post_init__(self):
+        self.mt = MessageType.NOTIFICATION
+        self.oid = SessionConfigOpcodeId.UPDATE_CONTROLLER_MULTICAST_LIST
+        self.gid = GroupId.SESSION_CONFIG
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['SessionUpdateControllerMulticastListExtendedNtf', bytes]:
+        if fields['mt'] != MessageType.NOTIFICATION or fields['oid'] != SessionConfigOpcodeId.UPDATE_CONTROLLER_MULTICAST_LIST or fields['gid'] != GroupId.SESSION_CONFIG:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 5:
+            raise Exception('Invalid packet size')
+        value_ = int.from_bytes(span[0:4], byteorder='little')
+        fields['session_token'] = value_
+        controlee_status_count = span[4]
+        span = span[5:]
+        if len(span) < controlee_status_count * 9:
+            raise Exception('Invalid packet size')
+        controlee_status = []
+        for n in range(controlee_status_count):
+            controlee_status.append(ExtendedControleeStatus.parse_all(span[n * 9:(n + 1) * 9]))
+        fields['controlee_status'] = controlee_status
+        span = span[controlee_status_count * 9:]
+        return SessionUpdateControllerMulticastListExtendedNtf(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        if self.session_token > 4294967295:
+            print(f"Invalid value for field SessionUpdateControllerMulticastListExtendedNtf::session_token: {self.session_token} > 4294967295; the value will be truncated")
+            self.session_token &= 4294967295
+        _span.extend(int.to_bytes((self.session_token << 0), length=4, byteorder='little'))
+        if len(self.controlee_status) > 255:
+            print(f"Invalid length for field SessionUpdateControllerMulticastListExtendedNtf::controlee_status:  {len(self.controlee_status)} > 255; the array will be truncated")
+            del self.controlee_status[255:]
+        _span.append((len(self.controlee_status) << 0))
+        for _elt in self.controlee_status:
+            _span.extend(_elt.serialize())
+        return SessionConfigPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return sum([elt.size for elt in self.controlee_status]) + 5
+
+@dataclass
+class SessionUpdateControllerMulticastListRsp_V1(SessionConfigPacket):
+    status: Status = field(kw_only=True, default=Status.OK)
+
+    def __post_init__(self):
+        self.mt = MessageType.RESPONSE
+        self.oid = SessionConfigOpcodeId.UPDATE_CONTROLLER_MULTICAST_LIST
+        self.gid = GroupId.SESSION_CONFIG
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['SessionUpdateControllerMulticastListRsp_V1', bytes]:
+        if fields['mt'] != MessageType.RESPONSE or fields['oid'] != SessionConfigOpcodeId.UPDATE_CONTROLLER_MULTICAST_LIST or fields['gid'] != GroupId.SESSION_CONFIG:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 1:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        span = span[1:]
+        return SessionUpdateControllerMulticastListRsp_V1(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.status << 0))
+        return SessionConfigPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return 1
+
+@dataclass
+class ControleeStatus_V1(Packet):
+    mac_address: bytearray = field(kw_only=True, default_factory=bytearray)
+    subsession_id: int = field(kw_only=True, default=0)
+    status: MulticastUpdateStatus = field(kw_only=True, default=MulticastUpdateStatus.OK_MULTICAST_LIST_UPDATE)
+
+    def __post_init__(self):
+        pass
+
+    @staticmethod
+    def parse(span: bytes) -> Tuple['ControleeStatus_V1', bytes]:
+        fields = {'payload': None}
+        if len(span) < 2:
+            raise Exception('Invalid packet size')
+        fields['mac_address'] = list(span[:2])
+        span = span[2:]
+        if len(span) < 5:
+            raise Exception('Invalid packet size')
+        value_ = int.from_bytes(span[0:4], byteorder='little')
+        fields['subsession_id'] = value_
+        fields['status'] = MulticastUpdateStatus.from_int(span[4])
+        span = span[5:]
+        return ControleeStatus_V1(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.extend(self.mac_address)
+        if self.subsession_id > 4294967295:
+            print(f"Invalid value for field ControleeStatus_V1::subsession_id: {self.subsession_id} > 4294967295; the value will be truncated")
+            self.subsession_id &= 4294967295
+        _span.extend(int.to_bytes((self.subsession_id << 0), length=4, byteorder='little'))
+        _span.append((self.status << 0))
+        return bytes(_span)
+
+    @property
+    def size(self) -> int:
+        return 7
+
+@dataclass
+class SessionUpdateControllerMulticastListNtf_V1(SessionConfigPacket):
+    session_token: int = field(kw_only=True, default=0)
+    remaining_multicast_list_size: int = field(kw_only=True, default=0)
+    controlee_status: List[ControleeStatus_V1] = field(kw_only=True, default_factory=list)
+
+    def __post_init__(self):
+        self.mt = MessageType.NOTIFICATION
+        self.oid = SessionConfigOpcodeId.UPDATE_CONTROLLER_MULTICAST_LIST
+        self.gid = GroupId.SESSION_CONFIG
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['SessionUpdateControllerMulticastListNtf_V1', bytes]:
+        if fields['mt'] != MessageType.NOTIFICATION or fields['oid'] != SessionConfigOpcodeId.UPDATE_CONTROLLER_MULTICAST_LIST or fields['gid'] != GroupId.SESSION_CONFIG:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 6:
+            raise Exception('Invalid packet size')
+        value_ = int.from_bytes(span[0:4], byteorder='little')
+        fields['session_token'] = value_
+        fields['remaining_multicast_list_size'] = span[4]
+        controlee_status_count = span[5]
+        span = span[6:]
+        if len(span) < controlee_status_count * 7:
+            raise Exception('Invalid packet size')
+        controlee_status = []
+        for n in range(controlee_status_count):
+            controlee_status.append(ControleeStatus_V1.parse_all(span[n * 7:(n + 1) * 7]))
+        fields['controlee_status'] = controlee_status
+        span = span[controlee_status_count * 7:]
+        return SessionUpdateControllerMulticastListNtf_V1(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        if self.session_token > 4294967295:
+            print(f"Invalid value for field SessionUpdateControllerMulticastListNtf_V1::session_token: {self.session_token} > 4294967295; the value will be truncated")
+            self.session_token &= 4294967295
+        _span.extend(int.to_bytes((self.session_token << 0), length=4, byteorder='little'))
+        if self.remaining_multicast_list_size > 255:
+            print(f"Invalid value for field SessionUpdateControllerMulticastListNtf_V1::remaining_multicast_list_size: {self.remaining_multicast_list_size} > 255; the value will be truncated")
+            self.remaining_multicast_list_size &= 255
+        _span.append((self.remaining_multicast_list_size << 0))
+        if len(self.controlee_status) > 255:
+            print(f"Invalid length for field SessionUpdateControllerMulticastListNtf_V1::controlee_status:  {len(self.controlee_status)} > 255; the array will be truncated")
+            del self.controlee_status[255:]
+        _span.append((len(self.controlee_status) << 0))
+        for _elt in self.controlee_status:
+            _span.extend(_elt.serialize())
+        return SessionConfigPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return sum([elt.size for elt in self.controlee_status]) + 6
+
+@dataclass
+class SessionDataCreditNtf(SessionControlPacket):
+    session_token: int = field(kw_only=True, default=0)
+    credit_availability: CreditAvailability = field(kw_only=True, default=CreditAvailability.CREDIT_NOT_AVAILABLE)
+
+    def __post_init__(self):
+        self.mt = MessageType.NOTIFICATION
+        self.oid = SessionControlOpcodeId.DATA_CREDIT
+        self.gid = GroupId.SESSION_CONTROL
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['SessionDataCreditNtf', bytes]:
+        if fields['mt'] != MessageType.NOTIFICATION or fields['oid'] != SessionControlOpcodeId.DATA_CREDIT or fields['gid'] != GroupId.SESSION_CONTROL:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 5:
+            raise Exception('Invalid packet size')
+        value_ = int.from_bytes(span[0:4], byteorder='little')
+        fields['session_token'] = value_
+        fields['credit_availability'] = CreditAvailability.from_int(span[4])
+        span = span[5:]
+        return SessionDataCreditNtf(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        if self.session_token > 4294967295:
+            print(f"Invalid value for field SessionDataCreditNtf::session_token: {self.session_token} > 4294967295; the value will be truncated")
+            self.session_token &= 4294967295
+        _span.extend(int.to_bytes((self.session_token << 0), length=4, byteorder='little'))
+        _span.append((self.credit_availability << 0))
+        return SessionControlPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return 5
+
+@dataclass
+class SessionDataTransferStatusNtf(SessionControlPacket):
     session_token: int = field(kw_only=True, default=0)
     uci_sequence_number: int = field(kw_only=True, default=0)
     status: DataTransferNtfStatusCode = field(kw_only=True, default=DataTransferNtfStatusCode.UCI_DATA_TRANSFER_STATUS_REPETITION_OK)
@@ -3534,14 +4332,15 @@ class SessionDataTransferStatusNtf(SessionControlPacket):
     def parse(fields: dict, span: bytes) -> Tuple['SessionDataTransferStatusNtf', bytes]:
         if fields['mt'] != MessageType.NOTIFICATION or fields['oid'] != SessionControlOpcodeId.DATA_TRANSFER_STATUS or fields['gid'] != GroupId.SESSION_CONTROL:
             raise Exception("Invalid constraint field values")
-        if len(span) < 7:
+        if len(span) < 8:
             raise Exception('Invalid packet size')
         value_ = int.from_bytes(span[0:4], byteorder='little')
         fields['session_token'] = value_
-        fields['uci_sequence_number'] = span[4]
-        fields['status'] = DataTransferNtfStatusCode.from_int(span[5])
-        fields['tx_count'] = span[6]
-        span = span[7:]
+        value_ = int.from_bytes(span[4:6], byteorder='little')
+        fields['uci_sequence_number'] = value_
+        fields['status'] = DataTransferNtfStatusCode.from_int(span[6])
+        fields['tx_count'] = span[7]
+        span = span[8:]
         return SessionDataTransferStatusNtf(**fields), span
 
     def serialize(self, payload: bytes = None) -> bytes:
@@ -3550,10 +4349,10 @@ class SessionDataTransferStatusNtf(SessionControlPacket):
             print(f"Invalid value for field SessionDataTransferStatusNtf::session_token: {self.session_token} > 4294967295; the value will be truncated")
             self.session_token &= 4294967295
         _span.extend(int.to_bytes((self.session_token << 0), length=4, byteorder='little'))
-        if self.uci_sequence_number > 255:
-            print(f"Invalid value for field SessionDataTransferStatusNtf::uci_sequence_number: {self.uci_sequence_number} > 255; the value will be truncated")
-            self.uci_sequence_number &= 255
-        _span.append((self.uci_sequence_number << 0))
+        if self.uci_sequence_number > 65535:
+            print(f"Invalid value for field SessionDataTransferStatusNtf::uci_sequence_number: {self.uci_sequence_number} > 65535; the value will be truncated")
+            self.uci_sequence_number &= 65535
+        _span.extend(int.to_bytes((self.uci_sequence_number << 0), length=2, byteorder='little'))
         _span.append((self.status << 0))
         if self.tx_count > 255:
             print(f"Invalid value for field SessionDataTransferStatusNtf::tx_count: {self.tx_count} > 255; the value will be truncated")
@@ -3563,7 +4362,7 @@ class SessionDataTransferStatusNtf(SessionControlPacket):
 
     @property
     def size(self) -> int:
-        return 7
+        return 8
 
 @dataclass
 class SessionQueryMaxDataSizeInRangingCmd(SessionConfigPacket):
@@ -4957,6 +5756,949 @@ class SessionGetRangingCountRsp(SessionControlPacket):
     def size(self) -> int:
         return 5
 
+class TestConfigTlvType(enum.IntEnum):
+    NUM_PACKETS = 0x0
+    T_GAP = 0x1
+    T_START = 0x2
+    T_WIN = 0x3
+    RANDOMIZE_PSDU = 0x4
+    PHR_RANGING_BIT = 0x5
+    RMARKER_TX_START = 0x6
+    RMARKER_RX_START = 0x7
+    STS_INDEX_AUTO_INCR = 0x8
+    STS_DETECT_BITMAP = 0x9
+
+    @staticmethod
+    def from_int(v: int) -> Union[int, 'TestConfigTlvType']:
+        try:
+            return TestConfigTlvType(v)
+        except ValueError as exn:
+            raise exn
+
+
+@dataclass
+class TestConfigTlv(Packet):
+    cfg_id: TestConfigTlvType = field(kw_only=True, default=TestConfigTlvType.NUM_PACKETS)
+    v: bytearray = field(kw_only=True, default_factory=bytearray)
+
+    def __post_init__(self):
+        pass
+
+    @staticmethod
+    def parse(span: bytes) -> Tuple['TestConfigTlv', bytes]:
+        fields = {'payload': None}
+        if len(span) < 2:
+            raise Exception('Invalid packet size')
+        fields['cfg_id'] = TestConfigTlvType.from_int(span[0])
+        v_count = span[1]
+        span = span[2:]
+        if len(span) < v_count:
+            raise Exception('Invalid packet size')
+        fields['v'] = list(span[:v_count])
+        span = span[v_count:]
+        return TestConfigTlv(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.cfg_id << 0))
+        if len(self.v) > 255:
+            print(f"Invalid length for field TestConfigTlv::v:  {len(self.v)} > 255; the array will be truncated")
+            del self.v[255:]
+        _span.append((len(self.v) << 0))
+        _span.extend(self.v)
+        return bytes(_span)
+
+    @property
+    def size(self) -> int:
+        return len(self.v) * 1 + 2
+
+@dataclass
+class TestConfigStatus(Packet):
+    cfg_id: TestConfigTlvType = field(kw_only=True, default=TestConfigTlvType.NUM_PACKETS)
+    status: Status = field(kw_only=True, default=Status.OK)
+
+    def __post_init__(self):
+        pass
+
+    @staticmethod
+    def parse(span: bytes) -> Tuple['TestConfigStatus', bytes]:
+        fields = {'payload': None}
+        if len(span) < 2:
+            raise Exception('Invalid packet size')
+        fields['cfg_id'] = TestConfigTlvType.from_int(span[0])
+        fields['status'] = Status.from_int(span[1])
+        span = span[2:]
+        return TestConfigStatus(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.cfg_id << 0))
+        _span.append((self.status << 0))
+        return bytes(_span)
+
+    @property
+    def size(self) -> int:
+        return 2
+
+@dataclass
+class TestConfigSetCmd(TestPacket):
+    session_token: int = field(kw_only=True, default=0)
+    tlvs: List[TestConfigTlv] = field(kw_only=True, default_factory=list)
+
+    def __post_init__(self):
+        self.mt = MessageType.COMMAND
+        self.oid = TestOpcodeId.CONFIG_SET
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestConfigSetCmd', bytes]:
+        if fields['mt'] != MessageType.COMMAND or fields['oid'] != TestOpcodeId.CONFIG_SET or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 5:
+            raise Exception('Invalid packet size')
+        value_ = int.from_bytes(span[0:4], byteorder='little')
+        fields['session_token'] = value_
+        tlvs_count = span[4]
+        span = span[5:]
+        tlvs = []
+        for n in range(tlvs_count):
+            element, span = TestConfigTlv.parse(span)
+            tlvs.append(element)
+        fields['tlvs'] = tlvs
+        return TestConfigSetCmd(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        if self.session_token > 4294967295:
+            print(f"Invalid value for field TestConfigSetCmd::session_token: {self.session_token} > 4294967295; the value will be truncated")
+            self.session_token &= 4294967295
+        _span.extend(int.to_bytes((self.session_token << 0), length=4, byteorder='little'))
+        if len(self.tlvs) > 255:
+            print(f"Invalid length for field TestConfigSetCmd::tlvs:  {len(self.tlvs)} > 255; the array will be truncated")
+            del self.tlvs[255:]
+        _span.append((len(self.tlvs) << 0))
+        for _elt in self.tlvs:
+            _span.extend(_elt.serialize())
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return sum([elt.size for elt in self.tlvs]) + 5
+
+@dataclass
+class TestConfigSetRsp(TestPacket):
+    status: Status = field(kw_only=True, default=Status.OK)
+    cfg_status: List[TestConfigStatus] = field(kw_only=True, default_factory=list)
+
+    def __post_init__(self):
+        self.mt = MessageType.RESPONSE
+        self.oid = TestOpcodeId.CONFIG_SET
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestConfigSetRsp', bytes]:
+        if fields['mt'] != MessageType.RESPONSE or fields['oid'] != TestOpcodeId.CONFIG_SET or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 2:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        cfg_status_count = span[1]
+        span = span[2:]
+        if len(span) < cfg_status_count * 2:
+            raise Exception('Invalid packet size')
+        cfg_status = []
+        for n in range(cfg_status_count):
+            cfg_status.append(TestConfigStatus.parse_all(span[n * 2:(n + 1) * 2]))
+        fields['cfg_status'] = cfg_status
+        span = span[cfg_status_count * 2:]
+        return TestConfigSetRsp(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.status << 0))
+        if len(self.cfg_status) > 255:
+            print(f"Invalid length for field TestConfigSetRsp::cfg_status:  {len(self.cfg_status)} > 255; the array will be truncated")
+            del self.cfg_status[255:]
+        _span.append((len(self.cfg_status) << 0))
+        for _elt in self.cfg_status:
+            _span.extend(_elt.serialize())
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return sum([elt.size for elt in self.cfg_status]) + 2
+
+@dataclass
+class TestConfigGetCmd(TestPacket):
+    session_token: int = field(kw_only=True, default=0)
+    test_config_ids: List[TestConfigTlvType] = field(kw_only=True, default_factory=list)
+
+    def __post_init__(self):
+        self.mt = MessageType.COMMAND
+        self.oid = TestOpcodeId.CONFIG_GET
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestConfigGetCmd', bytes]:
+        if fields['mt'] != MessageType.COMMAND or fields['oid'] != TestOpcodeId.CONFIG_GET or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 5:
+            raise Exception('Invalid packet size')
+        value_ = int.from_bytes(span[0:4], byteorder='little')
+        fields['session_token'] = value_
+        test_config_ids_count = span[4]
+        span = span[5:]
+        if len(span) < test_config_ids_count:
+            raise Exception('Invalid packet size')
+        test_config_ids = []
+        for n in range(test_config_ids_count):
+            test_config_ids.append(TestConfigTlvType(int.from_bytes(span[n:n + 1], byteorder='little')))
+        fields['test_config_ids'] = test_config_ids
+        span = span[test_config_ids_count:]
+        return TestConfigGetCmd(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        if self.session_token > 4294967295:
+            print(f"Invalid value for field TestConfigGetCmd::session_token: {self.session_token} > 4294967295; the value will be truncated")
+            self.session_token &= 4294967295
+        _span.extend(int.to_bytes((self.session_token << 0), length=4, byteorder='little'))
+        if len(self.test_config_ids) > 255:
+            print(f"Invalid length for field TestConfigGetCmd::test_config_ids:  {len(self.test_config_ids)} > 255; the array will be truncated")
+            del self.test_config_ids[255:]
+        _span.append((len(self.test_config_ids) << 0))
+        for _elt in self.test_config_ids:
+            _span.append(_elt)
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return len(self.test_config_ids) * 8 + 5
+
+@dataclass
+class TestConfigGetRsp(TestPacket):
+    status: Status = field(kw_only=True, default=Status.OK)
+    tlvs: List[TestConfigTlv] = field(kw_only=True, default_factory=list)
+
+    def __post_init__(self):
+        self.mt = MessageType.RESPONSE
+        self.oid = TestOpcodeId.CONFIG_GET
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestConfigGetRsp', bytes]:
+        if fields['mt'] != MessageType.RESPONSE or fields['oid'] != TestOpcodeId.CONFIG_GET or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 2:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        tlvs_count = span[1]
+        span = span[2:]
+        tlvs = []
+        for n in range(tlvs_count):
+            element, span = TestConfigTlv.parse(span)
+            tlvs.append(element)
+        fields['tlvs'] = tlvs
+        return TestConfigGetRsp(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.status << 0))
+        if len(self.tlvs) > 255:
+            print(f"Invalid length for field TestConfigGetRsp::tlvs:  {len(self.tlvs)} > 255; the array will be truncated")
+            del self.tlvs[255:]
+        _span.append((len(self.tlvs) << 0))
+        for _elt in self.tlvs:
+            _span.extend(_elt.serialize())
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return sum([elt.size for elt in self.tlvs]) + 2
+
+@dataclass
+class TestPeriodicTxCmd(TestPacket):
+    psdu_data: bytearray = field(kw_only=True, default_factory=bytearray)
+
+    def __post_init__(self):
+        self.mt = MessageType.COMMAND
+        self.oid = TestOpcodeId.PERIODIC_TX
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestPeriodicTxCmd', bytes]:
+        if fields['mt'] != MessageType.COMMAND or fields['oid'] != TestOpcodeId.PERIODIC_TX or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        fields['psdu_data'] = list(span)
+        span = bytes()
+        return TestPeriodicTxCmd(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.extend(self.psdu_data)
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return len(self.psdu_data) * 1
+
+@dataclass
+class TestPeriodicTxRsp(TestPacket):
+    status: Status = field(kw_only=True, default=Status.OK)
+
+    def __post_init__(self):
+        self.mt = MessageType.RESPONSE
+        self.oid = TestOpcodeId.PERIODIC_TX
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestPeriodicTxRsp', bytes]:
+        if fields['mt'] != MessageType.RESPONSE or fields['oid'] != TestOpcodeId.PERIODIC_TX or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 1:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        span = span[1:]
+        return TestPeriodicTxRsp(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.status << 0))
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return 1
+
+@dataclass
+class TestPeriodicTxNtf(TestPacket):
+    status: Status = field(kw_only=True, default=Status.OK)
+    vendor_data: bytearray = field(kw_only=True, default_factory=bytearray)
+
+    def __post_init__(self):
+        self.mt = MessageType.NOTIFICATION
+        self.oid = TestOpcodeId.PERIODIC_TX
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestPeriodicTxNtf', bytes]:
+        if fields['mt'] != MessageType.NOTIFICATION or fields['oid'] != TestOpcodeId.PERIODIC_TX or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 1:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        span = span[1:]
+        fields['vendor_data'] = list(span)
+        span = bytes()
+        return TestPeriodicTxNtf(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.status << 0))
+        _span.extend(self.vendor_data)
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return len(self.vendor_data) * 1 + 1
+
+@dataclass
+class TestPerRxCmd(TestPacket):
+    psdu_data: bytearray = field(kw_only=True, default_factory=bytearray)
+
+    def __post_init__(self):
+        self.mt = MessageType.COMMAND
+        self.oid = TestOpcodeId.PER_RX
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestPerRxCmd', bytes]:
+        if fields['mt'] != MessageType.COMMAND or fields['oid'] != TestOpcodeId.PER_RX or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        fields['psdu_data'] = list(span)
+        span = bytes()
+        return TestPerRxCmd(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.extend(self.psdu_data)
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return len(self.psdu_data) * 1
+
+@dataclass
+class TestPerRxRsp(TestPacket):
+    status: Status = field(kw_only=True, default=Status.OK)
+
+    def __post_init__(self):
+        self.mt = MessageType.RESPONSE
+        self.oid = TestOpcodeId.PER_RX
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestPerRxRsp', bytes]:
+        if fields['mt'] != MessageType.RESPONSE or fields['oid'] != TestOpcodeId.PER_RX or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 1:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        span = span[1:]
+        return TestPerRxRsp(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.status << 0))
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return 1
+
+@dataclass
+class TestPerRxNtf(TestPacket):
+    status: Status = field(kw_only=True, default=Status.OK)
+    attempts: int = field(kw_only=True, default=0)
+    acq_detect: int = field(kw_only=True, default=0)
+    acq_reject: int = field(kw_only=True, default=0)
+    rx_fail: int = field(kw_only=True, default=0)
+    sync_cir_ready: int = field(kw_only=True, default=0)
+    sfd_fail: int = field(kw_only=True, default=0)
+    sfd_found: int = field(kw_only=True, default=0)
+    phr_dec_error: int = field(kw_only=True, default=0)
+    phr_bit_error: int = field(kw_only=True, default=0)
+    psdu_dec_error: int = field(kw_only=True, default=0)
+    psdu_bit_error: int = field(kw_only=True, default=0)
+    sts_found: int = field(kw_only=True, default=0)
+    eof: int = field(kw_only=True, default=0)
+    vendor_data: bytearray = field(kw_only=True, default_factory=bytearray)
+
+    def __post_init__(self):
+        self.mt = MessageType.NOTIFICATION
+        self.oid = TestOpcodeId.PER_RX
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestPerRxNtf', bytes]:
+        if fields['mt'] != MessageType.NOTIFICATION or fields['oid'] != TestOpcodeId.PER_RX or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 53:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        value_ = int.from_bytes(span[1:5], byteorder='little')
+        fields['attempts'] = value_
+        value_ = int.from_bytes(span[5:9], byteorder='little')
+        fields['acq_detect'] = value_
+        value_ = int.from_bytes(span[9:13], byteorder='little')
+        fields['acq_reject'] = value_
+        value_ = int.from_bytes(span[13:17], byteorder='little')
+        fields['rx_fail'] = value_
+        value_ = int.from_bytes(span[17:21], byteorder='little')
+        fields['sync_cir_ready'] = value_
+        value_ = int.from_bytes(span[21:25], byteorder='little')
+        fields['sfd_fail'] = value_
+        value_ = int.from_bytes(span[25:29], byteorder='little')
+        fields['sfd_found'] = value_
+        value_ = int.from_bytes(span[29:33], byteorder='little')
+        fields['phr_dec_error'] = value_
+        value_ = int.from_bytes(span[33:37], byteorder='little')
+        fields['phr_bit_error'] = value_
+        value_ = int.from_bytes(span[37:41], byteorder='little')
+        fields['psdu_dec_error'] = value_
+        value_ = int.from_bytes(span[41:45], byteorder='little')
+        fields['psdu_bit_error'] = value_
+        value_ = int.from_bytes(span[45:49], byteorder='little')
+        fields['sts_found'] = value_
+        value_ = int.from_bytes(span[49:53], byteorder='little')
+        fields['eof'] = value_
+        span = span[53:]
+        fields['vendor_data'] = list(span)
+        span = bytes()
+        return TestPerRxNtf(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.status << 0))
+        if self.attempts > 4294967295:
+            print(f"Invalid value for field TestPerRxNtf::attempts: {self.attempts} > 4294967295; the value will be truncated")
+            self.attempts &= 4294967295
+        _span.extend(int.to_bytes((self.attempts << 0), length=4, byteorder='little'))
+        if self.acq_detect > 4294967295:
+            print(f"Invalid value for field TestPerRxNtf::acq_detect: {self.acq_detect} > 4294967295; the value will be truncated")
+            self.acq_detect &= 4294967295
+        _span.extend(int.to_bytes((self.acq_detect << 0), length=4, byteorder='little'))
+        if self.acq_reject > 4294967295:
+            print(f"Invalid value for field TestPerRxNtf::acq_reject: {self.acq_reject} > 4294967295; the value will be truncated")
+            self.acq_reject &= 4294967295
+        _span.extend(int.to_bytes((self.acq_reject << 0), length=4, byteorder='little'))
+        if self.rx_fail > 4294967295:
+            print(f"Invalid value for field TestPerRxNtf::rx_fail: {self.rx_fail} > 4294967295; the value will be truncated")
+            self.rx_fail &= 4294967295
+        _span.extend(int.to_bytes((self.rx_fail << 0), length=4, byteorder='little'))
+        if self.sync_cir_ready > 4294967295:
+            print(f"Invalid value for field TestPerRxNtf::sync_cir_ready: {self.sync_cir_ready} > 4294967295; the value will be truncated")
+            self.sync_cir_ready &= 4294967295
+        _span.extend(int.to_bytes((self.sync_cir_ready << 0), length=4, byteorder='little'))
+        if self.sfd_fail > 4294967295:
+            print(f"Invalid value for field TestPerRxNtf::sfd_fail: {self.sfd_fail} > 4294967295; the value will be truncated")
+            self.sfd_fail &= 4294967295
+        _span.extend(int.to_bytes((self.sfd_fail << 0), length=4, byteorder='little'))
+        if self.sfd_found > 4294967295:
+            print(f"Invalid value for field TestPerRxNtf::sfd_found: {self.sfd_found} > 4294967295; the value will be truncated")
+            self.sfd_found &= 4294967295
+        _span.extend(int.to_bytes((self.sfd_found << 0), length=4, byteorder='little'))
+        if self.phr_dec_error > 4294967295:
+            print(f"Invalid value for field TestPerRxNtf::phr_dec_error: {self.phr_dec_error} > 4294967295; the value will be truncated")
+            self.phr_dec_error &= 4294967295
+        _span.extend(int.to_bytes((self.phr_dec_error << 0), length=4, byteorder='little'))
+        if self.phr_bit_error > 4294967295:
+            print(f"Invalid value for field TestPerRxNtf::phr_bit_error: {self.phr_bit_error} > 4294967295; the value will be truncated")
+            self.phr_bit_error &= 4294967295
+        _span.extend(int.to_bytes((self.phr_bit_error << 0), length=4, byteorder='little'))
+        if self.psdu_dec_error > 4294967295:
+            print(f"Invalid value for field TestPerRxNtf::psdu_dec_error: {self.psdu_dec_error} > 4294967295; the value will be truncated")
+            self.psdu_dec_error &= 4294967295
+        _span.extend(int.to_bytes((self.psdu_dec_error << 0), length=4, byteorder='little'))
+        if self.psdu_bit_error > 4294967295:
+            print(f"Invalid value for field TestPerRxNtf::psdu_bit_error: {self.psdu_bit_error} > 4294967295; the value will be truncated")
+            self.psdu_bit_error &= 4294967295
+        _span.extend(int.to_bytes((self.psdu_bit_error << 0), length=4, byteorder='little'))
+        if self.sts_found > 4294967295:
+            print(f"Invalid value for field TestPerRxNtf::sts_found: {self.sts_found} > 4294967295; the value will be truncated")
+            self.sts_found &= 4294967295
+        _span.extend(int.to_bytes((self.sts_found << 0), length=4, byteorder='little'))
+        if self.eof > 4294967295:
+            print(f"Invalid value for field TestPerRxNtf::eof: {self.eof} > 4294967295; the value will be truncated")
+            self.eof &= 4294967295
+        _span.extend(int.to_bytes((self.eof << 0), length=4, byteorder='little'))
+        _span.extend(self.vendor_data)
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return len(self.vendor_data) * 1 + 53
+
+@dataclass
+class TestRxCmd(TestPacket):
+    
+
+    def __post_init__(self):
+        self.mt = MessageType.COMMAND
+        self.oid = TestOpcodeId.RX
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestRxCmd', bytes]:
+        if fields['mt'] != MessageType.COMMAND or fields['oid'] != TestOpcodeId.RX or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        return TestRxCmd(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return 0
+
+@dataclass
+class TestRxRsp(TestPacket):
+    status: Status = field(kw_only=True, default=Status.OK)
+
+    def __post_init__(self):
+        self.mt = MessageType.RESPONSE
+        self.oid = TestOpcodeId.RX
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestRxRsp', bytes]:
+        if fields['mt'] != MessageType.RESPONSE or fields['oid'] != TestOpcodeId.RX or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 1:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        span = span[1:]
+        return TestRxRsp(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.status << 0))
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return 1
+
+@dataclass
+class TestRxNtf(TestPacket):
+    status: Status = field(kw_only=True, default=Status.OK)
+    rx_done_ts_int: int = field(kw_only=True, default=0)
+    rx_done_ts_frac: int = field(kw_only=True, default=0)
+    aoa_azimuth: int = field(kw_only=True, default=0)
+    aoa_elevation: int = field(kw_only=True, default=0)
+    toa_gap: int = field(kw_only=True, default=0)
+    phr: int = field(kw_only=True, default=0)
+    psdu_data: bytearray = field(kw_only=True, default_factory=bytearray)
+    vendor_data: bytearray = field(kw_only=True, default_factory=bytearray)
+
+    def __post_init__(self):
+        self.mt = MessageType.NOTIFICATION
+        self.oid = TestOpcodeId.RX
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestRxNtf', bytes]:
+        if fields['mt'] != MessageType.NOTIFICATION or fields['oid'] != TestOpcodeId.RX or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 16:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        value_ = int.from_bytes(span[1:5], byteorder='little')
+        fields['rx_done_ts_int'] = value_
+        value_ = int.from_bytes(span[5:7], byteorder='little')
+        fields['rx_done_ts_frac'] = value_
+        value_ = int.from_bytes(span[7:9], byteorder='little')
+        fields['aoa_azimuth'] = value_
+        value_ = int.from_bytes(span[9:11], byteorder='little')
+        fields['aoa_elevation'] = value_
+        fields['toa_gap'] = span[11]
+        value_ = int.from_bytes(span[12:14], byteorder='little')
+        fields['phr'] = value_
+        value_ = int.from_bytes(span[14:16], byteorder='little')
+        psdu_data_size = value_
+        span = span[16:]
+        if len(span) < psdu_data_size:
+            raise Exception('Invalid packet size')
+        fields['psdu_data'] = list(span[:psdu_data_size])
+        span = span[psdu_data_size:]
+        fields['vendor_data'] = list(span)
+        span = bytes()
+        return TestRxNtf(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.status << 0))
+        if self.rx_done_ts_int > 4294967295:
+            print(f"Invalid value for field TestRxNtf::rx_done_ts_int: {self.rx_done_ts_int} > 4294967295; the value will be truncated")
+            self.rx_done_ts_int &= 4294967295
+        _span.extend(int.to_bytes((self.rx_done_ts_int << 0), length=4, byteorder='little'))
+        if self.rx_done_ts_frac > 65535:
+            print(f"Invalid value for field TestRxNtf::rx_done_ts_frac: {self.rx_done_ts_frac} > 65535; the value will be truncated")
+            self.rx_done_ts_frac &= 65535
+        _span.extend(int.to_bytes((self.rx_done_ts_frac << 0), length=2, byteorder='little'))
+        if self.aoa_azimuth > 65535:
+            print(f"Invalid value for field TestRxNtf::aoa_azimuth: {self.aoa_azimuth} > 65535; the value will be truncated")
+            self.aoa_azimuth &= 65535
+        _span.extend(int.to_bytes((self.aoa_azimuth << 0), length=2, byteorder='little'))
+        if self.aoa_elevation > 65535:
+            print(f"Invalid value for field TestRxNtf::aoa_elevation: {self.aoa_elevation} > 65535; the value will be truncated")
+            self.aoa_elevation &= 65535
+        _span.extend(int.to_bytes((self.aoa_elevation << 0), length=2, byteorder='little'))
+        if self.toa_gap > 255:
+            print(f"Invalid value for field TestRxNtf::toa_gap: {self.toa_gap} > 255; the value will be truncated")
+            self.toa_gap &= 255
+        _span.append((self.toa_gap << 0))
+        if self.phr > 65535:
+            print(f"Invalid value for field TestRxNtf::phr: {self.phr} > 65535; the value will be truncated")
+            self.phr &= 65535
+        _span.extend(int.to_bytes((self.phr << 0), length=2, byteorder='little'))
+        _span.extend(int.to_bytes(((len(self.psdu_data) * 1) << 0), length=2, byteorder='little'))
+        _span.extend(self.psdu_data)
+        _span.extend(self.vendor_data)
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return 16 + (
+        len(self.psdu_data) * 1 +
+            len(self.vendor_data) * 1
+        )
+
+@dataclass
+class TestLoopbackCmd(TestPacket):
+    psdu_data: bytearray = field(kw_only=True, default_factory=bytearray)
+
+    def __post_init__(self):
+        self.mt = MessageType.COMMAND
+        self.oid = TestOpcodeId.LOOPBACK
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestLoopbackCmd', bytes]:
+        if fields['mt'] != MessageType.COMMAND or fields['oid'] != TestOpcodeId.LOOPBACK or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        fields['psdu_data'] = list(span)
+        span = bytes()
+        return TestLoopbackCmd(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.extend(self.psdu_data)
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return len(self.psdu_data) * 1
+
+@dataclass
+class TestLoopbackRsp(TestPacket):
+    status: Status = field(kw_only=True, default=Status.OK)
+
+    def __post_init__(self):
+        self.mt = MessageType.RESPONSE
+        self.oid = TestOpcodeId.LOOPBACK
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestLoopbackRsp', bytes]:
+        if fields['mt'] != MessageType.RESPONSE or fields['oid'] != TestOpcodeId.LOOPBACK or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 1:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        span = span[1:]
+        return TestLoopbackRsp(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.status << 0))
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return 1
+
+@dataclass
+class TestLoopbackNtf(TestPacket):
+    status: Status = field(kw_only=True, default=Status.OK)
+    tx_ts_int: int = field(kw_only=True, default=0)
+    tx_ts_frac: int = field(kw_only=True, default=0)
+    rx_ts_int: int = field(kw_only=True, default=0)
+    rx_ts_frac: int = field(kw_only=True, default=0)
+    aoa_azimuth: int = field(kw_only=True, default=0)
+    aoa_elevation: int = field(kw_only=True, default=0)
+    phr: int = field(kw_only=True, default=0)
+    psdu_data: bytearray = field(kw_only=True, default_factory=bytearray)
+    vendor_data: bytearray = field(kw_only=True, default_factory=bytearray)
+
+    def __post_init__(self):
+        self.mt = MessageType.NOTIFICATION
+        self.oid = TestOpcodeId.LOOPBACK
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestLoopbackNtf', bytes]:
+        if fields['mt'] != MessageType.NOTIFICATION or fields['oid'] != TestOpcodeId.LOOPBACK or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 21:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        value_ = int.from_bytes(span[1:5], byteorder='little')
+        fields['tx_ts_int'] = value_
+        value_ = int.from_bytes(span[5:7], byteorder='little')
+        fields['tx_ts_frac'] = value_
+        value_ = int.from_bytes(span[7:11], byteorder='little')
+        fields['rx_ts_int'] = value_
+        value_ = int.from_bytes(span[11:13], byteorder='little')
+        fields['rx_ts_frac'] = value_
+        value_ = int.from_bytes(span[13:15], byteorder='little')
+        fields['aoa_azimuth'] = value_
+        value_ = int.from_bytes(span[15:17], byteorder='little')
+        fields['aoa_elevation'] = value_
+        value_ = int.from_bytes(span[17:19], byteorder='little')
+        fields['phr'] = value_
+        value_ = int.from_bytes(span[19:21], byteorder='little')
+        psdu_data_size = value_
+        span = span[21:]
+        if len(span) < psdu_data_size:
+            raise Exception('Invalid packet size')
+        fields['psdu_data'] = list(span[:psdu_data_size])
+        span = span[psdu_data_size:]
+        fields['vendor_data'] = list(span)
+        span = bytes()
+        return TestLoopbackNtf(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.status << 0))
+        if self.tx_ts_int > 4294967295:
+            print(f"Invalid value for field TestLoopbackNtf::tx_ts_int: {self.tx_ts_int} > 4294967295; the value will be truncated")
+            self.tx_ts_int &= 4294967295
+        _span.extend(int.to_bytes((self.tx_ts_int << 0), length=4, byteorder='little'))
+        if self.tx_ts_frac > 65535:
+            print(f"Invalid value for field TestLoopbackNtf::tx_ts_frac: {self.tx_ts_frac} > 65535; the value will be truncated")
+            self.tx_ts_frac &= 65535
+        _span.extend(int.to_bytes((self.tx_ts_frac << 0), length=2, byteorder='little'))
+        if self.rx_ts_int > 4294967295:
+            print(f"Invalid value for field TestLoopbackNtf::rx_ts_int: {self.rx_ts_int} > 4294967295; the value will be truncated")
+            self.rx_ts_int &= 4294967295
+        _span.extend(int.to_bytes((self.rx_ts_int << 0), length=4, byteorder='little'))
+        if self.rx_ts_frac > 65535:
+            print(f"Invalid value for field TestLoopbackNtf::rx_ts_frac: {self.rx_ts_frac} > 65535; the value will be truncated")
+            self.rx_ts_frac &= 65535
+        _span.extend(int.to_bytes((self.rx_ts_frac << 0), length=2, byteorder='little'))
+        if self.aoa_azimuth > 65535:
+            print(f"Invalid value for field TestLoopbackNtf::aoa_azimuth: {self.aoa_azimuth} > 65535; the value will be truncated")
+            self.aoa_azimuth &= 65535
+        _span.extend(int.to_bytes((self.aoa_azimuth << 0), length=2, byteorder='little'))
+        if self.aoa_elevation > 65535:
+            print(f"Invalid value for field TestLoopbackNtf::aoa_elevation: {self.aoa_elevation} > 65535; the value will be truncated")
+            self.aoa_elevation &= 65535
+        _span.extend(int.to_bytes((self.aoa_elevation << 0), length=2, byteorder='little'))
+        if self.phr > 65535:
+            print(f"Invalid value for field TestLoopbackNtf::phr: {self.phr} > 65535; the value will be truncated")
+            self.phr &= 65535
+        _span.extend(int.to_bytes((self.phr << 0), length=2, byteorder='little'))
+        _span.extend(int.to_bytes(((len(self.psdu_data) * 1) << 0), length=2, byteorder='little'))
+        _span.extend(self.psdu_data)
+        _span.extend(self.vendor_data)
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return 21 + (
+        len(self.psdu_data) * 1 +
+            len(self.vendor_data) * 1
+        )
+
+@dataclass
+class TestStopSessionCmd(TestPacket):
+    
+
+    def __post_init__(self):
+        self.mt = MessageType.COMMAND
+        self.oid = TestOpcodeId.STOP_SESSION
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestStopSessionCmd', bytes]:
+        if fields['mt'] != MessageType.COMMAND or fields['oid'] != TestOpcodeId.STOP_SESSION or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        return TestStopSessionCmd(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return 0
+
+@dataclass
+class TestStopSessionRsp(TestPacket):
+    status: Status = field(kw_only=True, default=Status.OK)
+
+    def __post_init__(self):
+        self.mt = MessageType.RESPONSE
+        self.oid = TestOpcodeId.STOP_SESSION
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestStopSessionRsp', bytes]:
+        if fields['mt'] != MessageType.RESPONSE or fields['oid'] != TestOpcodeId.STOP_SESSION or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 1:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        span = span[1:]
+        return TestStopSessionRsp(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.status << 0))
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return 1
+
+@dataclass
+class TestSsTwrCmd(TestPacket):
+    
+
+    def __post_init__(self):
+        self.mt = MessageType.COMMAND
+        self.oid = TestOpcodeId.SS_TWR
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestSsTwrCmd', bytes]:
+        if fields['mt'] != MessageType.COMMAND or fields['oid'] != TestOpcodeId.SS_TWR or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        return TestSsTwrCmd(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return 0
+
+@dataclass
+class TestSsTwrRsp(TestPacket):
+    status: Status = field(kw_only=True, default=Status.OK)
+
+    def __post_init__(self):
+        self.mt = MessageType.RESPONSE
+        self.oid = TestOpcodeId.SS_TWR
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestSsTwrRsp', bytes]:
+        if fields['mt'] != MessageType.RESPONSE or fields['oid'] != TestOpcodeId.SS_TWR or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 1:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        span = span[1:]
+        return TestSsTwrRsp(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.status << 0))
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return 1
+
+@dataclass
+class TestSsTwrNtf(TestPacket):
+    status: Status = field(kw_only=True, default=Status.OK)
+    measurement: int = field(kw_only=True, default=0)
+
+    def __post_init__(self):
+        self.mt = MessageType.NOTIFICATION
+        self.oid = TestOpcodeId.SS_TWR
+        self.gid = GroupId.TEST
+
+    @staticmethod
+    def parse(fields: dict, span: bytes) -> Tuple['TestSsTwrNtf', bytes]:
+        if fields['mt'] != MessageType.NOTIFICATION or fields['oid'] != TestOpcodeId.SS_TWR or fields['gid'] != GroupId.TEST:
+            raise Exception("Invalid constraint field values")
+        if len(span) < 5:
+            raise Exception('Invalid packet size')
+        fields['status'] = Status.from_int(span[0])
+        value_ = int.from_bytes(span[1:5], byteorder='little')
+        fields['measurement'] = value_
+        span = span[5:]
+        return TestSsTwrNtf(**fields), span
+
+    def serialize(self, payload: bytes = None) -> bytes:
+        _span = bytearray()
+        _span.append((self.status << 0))
+        if self.measurement > 4294967295:
+            print(f"Invalid value for field TestSsTwrNtf::measurement: {self.measurement} > 4294967295; the value will be truncated")
+            self.measurement &= 4294967295
+        _span.extend(int.to_bytes((self.measurement << 0), length=4, byteorder='little'))
+        return TestPacket.serialize(self, payload = bytes(_span))
+
+    @property
+    def size(self) -> int:
+        return 5
+
 @dataclass
 class AndroidGetPowerStatsCmd(AndroidPacket):
     
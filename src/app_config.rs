@@ -1,6 +1,14 @@
 use crate::packets::uci;
 use crate::MacAddress;
 
+/// Maximum PSDU size of BPRF and HPRF frames, in octets.
+const BPRF_MAX_PSDU_SIZE: u64 = 127;
+const HPRF_MAX_PSDU_SIZE: u64 = 4095;
+
+/// Approximate size of the MAC header, data message header and FCS
+/// of the frames carrying application data, in octets.
+const DATA_FRAME_OVERHEAD: u64 = 16;
+
 /// [UCI] 8.3 Application Configuration Parameters.
 /// Sub-session Key provided for Provisioned STS for Responder specific Key mode
 /// (STS_CONFIG equal to 0x04).
@@ -25,7 +33,7 @@ pub struct AppConfig {
     pub ranging_round_usage: Option<uci::RangingRoundUsage>,
     pub sts_config: uci::StsConfig,
     pub multi_node_mode: Option<uci::MultiNodeMode>,
-    channel_number: uci::ChannelNumber,
+    pub channel_number: uci::ChannelNumber,
     /// Number of Controlees(N) 1<=N<=8 (Default is 1)
     pub number_of_controlees: u8,
     /// MAC Address of the UWBS itself participating in UWB session.
@@ -39,28 +47,28 @@ pub struct AppConfig {
     /// - equal to 1 when MULTI_NODE_MODE is set 0x00 (O2O).
     /// - ranging from 1 to 8 when MULTI_NODE_MODE is set to 0x01 (O2M).
     pub dst_mac_address: Vec<MacAddress>,
-    slot_duration: u16,
+    pub slot_duration: u16,
     pub ranging_duration: u32,
-    sts_index: u32,
+    pub sts_index: u32,
     mac_fcs_type: uci::MacFcsType,
     ranging_round_control: u8,
-    aoa_result_req: uci::AoaResultReq,
+    pub aoa_result_req: uci::AoaResultReq,
     pub session_info_ntf_config: uci::SessionInfoNtfConfig,
     near_proximity_config: u16,
     far_proximity_config: u16,
     pub device_role: Option<uci::DeviceRole>,
     rframe_config: uci::RframeConfig,
     rssi_reporting: uci::RssiReporting,
-    preamble_code_index: u8,
+    pub preamble_code_index: u8,
     sfd_id: u8,
-    psdu_data_rate: uci::PsduDataRate,
-    preamble_duration: uci::PreambleDuration,
+    pub psdu_data_rate: uci::PsduDataRate,
+    pub preamble_duration: uci::PreambleDuration,
     link_layer_mode: uci::LinkLayerMode,
     data_repetition_count: u8,
     ranging_time_struct: uci::RangingTimeStruct,
-    slots_per_rr: u8,
+    pub slots_per_rr: u8,
     aoa_bound_config: [u16; 4],
-    prf_mode: uci::PrfMode,
+    pub prf_mode: uci::PrfMode,
     cap_size_range: [u8; 2],
     tx_jitter_window_size: u8,
     pub schedule_mode: Option<uci::ScheduleMode>,
@@ -71,8 +79,9 @@ pub struct AppConfig {
     vendor_id: u16,
     static_sts_iv: [u8; 6],
     number_of_sts_segments: u8,
-    max_rr_retry: u16,
-    uwb_initiation_time: u64,
+    pub max_rr_retry: u16,
+    /// Start time of the first ranging round, cf. [UCI] UWB_INITIATION_TIME.
+    pub uwb_initiation_time: u64,
     hopping_mode: uci::HoppingMode,
     block_stride_length: u8,
     result_report_config: u8,
@@ -82,13 +91,44 @@ pub struct AppConfig {
     max_number_of_measurements: u16,
     sts_length: uci::StsLength,
     min_frames_per_rr: u8,
-    mtu_size: u16,
+    pub mtu_size: u16,
     inter_frame_interval: u8,
+    dl_tdoa_ranging_method: uci::DlTdoaRangingMethod,
+    /// Bit 0 selects whether the TX timestamp is included in the
+    /// DL-TDoA messages, bit 1 selects the 64-bit timestamp format.
+    pub dl_tdoa_tx_timestamp_conf: u8,
+    dl_tdoa_hop_count: u8,
+    dl_tdoa_anchor_cfo: uci::DlTdoaAnchorCfo,
+    /// Location of the DT-Anchor, prefixed by the location type:
+    /// 0x00 for WGS-84 coordinates, 0x01 for relative coordinates.
+    pub dl_tdoa_anchor_location: Vec<u8>,
+    pub dl_tdoa_tx_active_ranging_rounds: u8,
+    dl_tdoa_block_skipping: u8,
+    dl_tdoa_time_reference_anchor: u8,
     session_key: Vec<u8>,
     sub_session_key: SubSessionKey,
     pub session_data_transfer_status_ntf_config: uci::SessionDataTransferStatusNtfConfig,
-    session_time_base: [u8; 9],
+    /// Time base configuration (octet 0), reference session handle
+    /// (octets 1-4) and session offset time in microseconds (octets 5-8),
+    /// cf. [UCI] SESSION_TIME_BASE.
+    pub session_time_base: [u8; 9],
+    dl_tdoa_responder_tof: u8,
     application_data_endpoint: u8,
+    /// Number of ranging rounds between two OWR AoA measurement
+    /// notifications. A notification is generated for every ranging
+    /// round when set to 0.
+    pub owr_aoa_measurement_ntf_period: u8,
+    /// Key seeding the ranging round hopping sequence of CCC sessions.
+    pub ccc_hop_mode_key: u32,
+    /// UWBS time of the start of the first ranging block of CCC sessions,
+    /// in microseconds.
+    pub ccc_uwb_time0: u64,
+    ccc_ranging_protocol_ver: u16,
+    ccc_uwb_config_id: u16,
+    ccc_pulseshape_combo: u8,
+    ccc_ursk_ttl: u16,
+    /// STS index used in the last ranging block of CCC sessions.
+    pub ccc_last_index_used: u32,
 }
 
 impl Default for AppConfig {
@@ -150,12 +190,29 @@ impl Default for AppConfig {
             min_frames_per_rr: 4,
             mtu_size: 0, // XX
             inter_frame_interval: 1,
+            dl_tdoa_ranging_method: uci::DlTdoaRangingMethod::DsTwr,
+            dl_tdoa_tx_timestamp_conf: 0,
+            dl_tdoa_hop_count: 0,
+            dl_tdoa_anchor_cfo: uci::DlTdoaAnchorCfo::AnchorCfoIncluded,
+            dl_tdoa_anchor_location: vec![],
+            dl_tdoa_tx_active_ranging_rounds: 0,
+            dl_tdoa_block_skipping: 0,
+            dl_tdoa_time_reference_anchor: 0,
             session_key: vec![],
             sub_session_key: SubSessionKey::None,
             session_data_transfer_status_ntf_config:
                 uci::SessionDataTransferStatusNtfConfig::Disable,
             session_time_base: [0; 9],
+            dl_tdoa_responder_tof: 0,
             application_data_endpoint: 0,
+            owr_aoa_measurement_ntf_period: 0,
+            ccc_hop_mode_key: 0,
+            ccc_uwb_time0: 0,
+            ccc_ranging_protocol_ver: 0,
+            ccc_uwb_config_id: 0,
+            ccc_pulseshape_combo: 0,
+            ccc_ursk_ttl: 0,
+            ccc_last_index_used: 0,
         }
     }
 }
@@ -199,15 +256,15 @@ impl AppConfig {
             uci::AppConfigTlvType::DeviceMacAddress => {
                 self.device_mac_address = Some(match self.mac_address_mode {
                     uci::MacAddressMode::Mode0 => MacAddress::Short(value.try_into()?),
-                    uci::MacAddressMode::Mode1 => unimplemented!(),
-                    uci::MacAddressMode::Mode2 => MacAddress::Extended(value.try_into()?),
+                    uci::MacAddressMode::Mode1 | uci::MacAddressMode::Mode2 => {
+                        MacAddress::Extended(value.try_into()?)
+                    }
                 })
             }
             uci::AppConfigTlvType::DstMacAddress => {
                 let mac_address_size = match self.mac_address_mode {
                     uci::MacAddressMode::Mode0 => 2,
-                    uci::MacAddressMode::Mode1 => unimplemented!(),
-                    uci::MacAddressMode::Mode2 => 8,
+                    uci::MacAddressMode::Mode1 | uci::MacAddressMode::Mode2 => 8,
                 };
                 if value.len() != self.number_of_controlees as usize * mac_address_size {
                     log::error!(
@@ -222,8 +279,7 @@ impl AppConfig {
                     .chunks(mac_address_size)
                     .map(|value| match self.mac_address_mode {
                         uci::MacAddressMode::Mode0 => MacAddress::Short(value.try_into().unwrap()),
-                        uci::MacAddressMode::Mode1 => unimplemented!(),
-                        uci::MacAddressMode::Mode2 => {
+                        uci::MacAddressMode::Mode1 | uci::MacAddressMode::Mode2 => {
                             MacAddress::Extended(value.try_into().unwrap())
                         }
                     })
@@ -323,6 +379,30 @@ impl AppConfig {
             uci::AppConfigTlvType::InterFrameInterval => {
                 self.inter_frame_interval = try_parse_u8(value)?
             }
+            uci::AppConfigTlvType::DlTdoaRangingMethod => {
+                self.dl_tdoa_ranging_method = try_parse(value)?
+            }
+            uci::AppConfigTlvType::DlTdoaTxTimestampConf => {
+                self.dl_tdoa_tx_timestamp_conf = try_parse_u8(value)?
+            }
+            uci::AppConfigTlvType::DlTdoaHopCount => self.dl_tdoa_hop_count = try_parse_u8(value)?,
+            uci::AppConfigTlvType::DlTdoaAnchorCfo => self.dl_tdoa_anchor_cfo = try_parse(value)?,
+            uci::AppConfigTlvType::DlTdoaAnchorLocation => {
+                match (value.first(), value.len()) {
+                    (Some(0x00), 13) | (Some(0x01), 11) => (),
+                    _ => anyhow::bail!("invalid dl_tdoa_anchor_location {:?}", value),
+                }
+                self.dl_tdoa_anchor_location = value.to_vec()
+            }
+            uci::AppConfigTlvType::DlTdoaTxActiveRangingRounds => {
+                self.dl_tdoa_tx_active_ranging_rounds = try_parse_u8(value)?
+            }
+            uci::AppConfigTlvType::DlTdoaBlockSkipping => {
+                self.dl_tdoa_block_skipping = try_parse_u8(value)?
+            }
+            uci::AppConfigTlvType::DlTdoaTimeReferenceAnchor => {
+                self.dl_tdoa_time_reference_anchor = try_parse_u8(value)?
+            }
             uci::AppConfigTlvType::SessionKey => self.session_key = value.to_vec(),
             uci::AppConfigTlvType::SubSessionKey => {
                 self.sub_session_key = match value.len() {
@@ -335,18 +415,30 @@ impl AppConfig {
                 self.session_data_transfer_status_ntf_config = try_parse(value)?
             }
             uci::AppConfigTlvType::SessionTimeBase => self.session_time_base = value.try_into()?,
+            uci::AppConfigTlvType::DlTdoaResponderTof => {
+                self.dl_tdoa_responder_tof = try_parse_u8(value)?
+            }
             uci::AppConfigTlvType::ApplicationDataEndpoint => {
                 self.application_data_endpoint = try_parse_u8(value)?
             }
+            uci::AppConfigTlvType::OwrAoaMeasurementNtfPeriod => {
+                self.owr_aoa_measurement_ntf_period = try_parse_u8(value)?
+            }
+            uci::AppConfigTlvType::CccHopModeKey => self.ccc_hop_mode_key = try_parse_u32(value)?,
+            uci::AppConfigTlvType::CccUwbTime0 => self.ccc_uwb_time0 = try_parse_u64(value)?,
+            uci::AppConfigTlvType::CccRangingProtocolVer => {
+                self.ccc_ranging_protocol_ver = try_parse_u16(value)?
+            }
+            uci::AppConfigTlvType::CccUwbConfigId => self.ccc_uwb_config_id = try_parse_u16(value)?,
+            uci::AppConfigTlvType::CccPulseshapeCombo => {
+                self.ccc_pulseshape_combo = try_parse_u8(value)?
+            }
+            uci::AppConfigTlvType::CccUrskTtl => self.ccc_ursk_ttl = try_parse_u16(value)?,
+            uci::AppConfigTlvType::CccLastIndexUsed => {
+                self.ccc_last_index_used = try_parse_u32(value)?
+            }
 
-            uci::AppConfigTlvType::CccHopModeKey
-            | uci::AppConfigTlvType::CccUwbTime0
-            | uci::AppConfigTlvType::CccRangingProtocolVer
-            | uci::AppConfigTlvType::CccUwbConfigId
-            | uci::AppConfigTlvType::CccPulseshapeCombo
-            | uci::AppConfigTlvType::CccUrskTtl
-            | uci::AppConfigTlvType::CccLastIndexUsed
-            | uci::AppConfigTlvType::NbOfRangeMeasurements
+            uci::AppConfigTlvType::NbOfRangeMeasurements
             | uci::AppConfigTlvType::NbOfAzimuthMeasurements
             | uci::AppConfigTlvType::NbOfElevationMeasurements
             | uci::AppConfigTlvType::EnableDiagnostics
@@ -460,6 +552,22 @@ impl AppConfig {
             uci::AppConfigTlvType::MinFramesPerRr => Ok(vec![self.min_frames_per_rr]),
             uci::AppConfigTlvType::MtuSize => Ok(self.mtu_size.to_le_bytes().to_vec()),
             uci::AppConfigTlvType::InterFrameInterval => Ok(vec![self.inter_frame_interval]),
+            uci::AppConfigTlvType::DlTdoaRangingMethod => {
+                Ok(vec![self.dl_tdoa_ranging_method.into()])
+            }
+            uci::AppConfigTlvType::DlTdoaTxTimestampConf => {
+                Ok(vec![self.dl_tdoa_tx_timestamp_conf])
+            }
+            uci::AppConfigTlvType::DlTdoaHopCount => Ok(vec![self.dl_tdoa_hop_count]),
+            uci::AppConfigTlvType::DlTdoaAnchorCfo => Ok(vec![self.dl_tdoa_anchor_cfo.into()]),
+            uci::AppConfigTlvType::DlTdoaAnchorLocation => Ok(self.dl_tdoa_anchor_location.clone()),
+            uci::AppConfigTlvType::DlTdoaTxActiveRangingRounds => {
+                Ok(vec![self.dl_tdoa_tx_active_ranging_rounds])
+            }
+            uci::AppConfigTlvType::DlTdoaBlockSkipping => Ok(vec![self.dl_tdoa_block_skipping]),
+            uci::AppConfigTlvType::DlTdoaTimeReferenceAnchor => {
+                Ok(vec![self.dl_tdoa_time_reference_anchor])
+            }
             uci::AppConfigTlvType::SessionKey => Ok(self.session_key.clone()),
             uci::AppConfigTlvType::SubSessionKey => Ok(match self.sub_session_key {
                 SubSessionKey::None => vec![],
@@ -470,18 +578,30 @@ impl AppConfig {
                 Ok(vec![self.session_data_transfer_status_ntf_config.into()])
             }
             uci::AppConfigTlvType::SessionTimeBase => Ok(self.session_time_base.to_vec()),
+            uci::AppConfigTlvType::DlTdoaResponderTof => Ok(vec![self.dl_tdoa_responder_tof]),
             uci::AppConfigTlvType::ApplicationDataEndpoint => {
                 Ok(vec![self.application_data_endpoint])
             }
+            uci::AppConfigTlvType::OwrAoaMeasurementNtfPeriod => {
+                Ok(vec![self.owr_aoa_measurement_ntf_period])
+            }
+            uci::AppConfigTlvType::CccHopModeKey => {
+                Ok(self.ccc_hop_mode_key.to_le_bytes().to_vec())
+            }
+            uci::AppConfigTlvType::CccUwbTime0 => Ok(self.ccc_uwb_time0.to_le_bytes().to_vec()),
+            uci::AppConfigTlvType::CccRangingProtocolVer => {
+                Ok(self.ccc_ranging_protocol_ver.to_le_bytes().to_vec())
+            }
+            uci::AppConfigTlvType::CccUwbConfigId => {
+                Ok(self.ccc_uwb_config_id.to_le_bytes().to_vec())
+            }
+            uci::AppConfigTlvType::CccPulseshapeCombo => Ok(vec![self.ccc_pulseshape_combo]),
+            uci::AppConfigTlvType::CccUrskTtl => Ok(self.ccc_ursk_ttl.to_le_bytes().to_vec()),
+            uci::AppConfigTlvType::CccLastIndexUsed => {
+                Ok(self.ccc_last_index_used.to_le_bytes().to_vec())
+            }
 
-            uci::AppConfigTlvType::CccHopModeKey
-            | uci::AppConfigTlvType::CccUwbTime0
-            | uci::AppConfigTlvType::CccRangingProtocolVer
-            | uci::AppConfigTlvType::CccUwbConfigId
-            | uci::AppConfigTlvType::CccPulseshapeCombo
-            | uci::AppConfigTlvType::CccUrskTtl
-            | uci::AppConfigTlvType::CccLastIndexUsed
-            | uci::AppConfigTlvType::NbOfRangeMeasurements
+            uci::AppConfigTlvType::NbOfRangeMeasurements
             | uci::AppConfigTlvType::NbOfAzimuthMeasurements
             | uci::AppConfigTlvType::NbOfElevationMeasurements
             | uci::AppConfigTlvType::EnableDiagnostics
@@ -496,8 +616,137 @@ impl AppConfig {
         }
     }
 
+    /// Check the value of the selected parameter against the capabilities
+    /// advertised by the device. Parameters without a matching capability,
+    /// or whose capability is not advertised, are not restricted.
+    /// Return the reason code reported when the value is not supported.
+    pub fn check_capabilities(
+        &self,
+        id: uci::AppConfigTlvType,
+        session_type: uci::SessionType,
+        caps: &[uci::CapTlv],
+    ) -> Result<(), uci::ReasonCode> {
+        // Capability values are little endian bitmaps.
+        let bitmap = |t: uci::CapTlvType| {
+            caps.iter().find(|cap| cap.t == t).map(|cap| {
+                cap.v
+                    .iter()
+                    .take(8)
+                    .rev()
+                    .fold(0u64, |bitmap, byte| (bitmap << 8) | *byte as u64)
+            })
+        };
+        let supports = |t: uci::CapTlvType, bit: u8| {
+            bitmap(t)
+                .iter()
+                .all(|bitmap| bit < 64 && bitmap & (1 << bit) != 0)
+        };
+        let check = |supported: bool, reason_code: uci::ReasonCode| {
+            if supported {
+                Ok(())
+            } else {
+                Err(reason_code)
+            }
+        };
+
+        match id {
+            uci::AppConfigTlvType::ChannelNumber if session_type == uci::SessionType::Ccc => {
+                // [CCC] Only the channels 5 and 9 are defined.
+                let bit = match self.channel_number {
+                    uci::ChannelNumber::ChannelNumber5 => Some(0),
+                    uci::ChannelNumber::ChannelNumber9 => Some(1),
+                    _ => None,
+                };
+                check(
+                    bit.is_some_and(|bit| supports(uci::CapTlvType::CccSupportedChannels, bit)),
+                    uci::ReasonCode::ErrorChannelNotSupported,
+                )
+            }
+            uci::AppConfigTlvType::ChannelNumber => {
+                let bit = match self.channel_number {
+                    uci::ChannelNumber::ChannelNumber5 => 0,
+                    uci::ChannelNumber::ChannelNumber6 => 1,
+                    uci::ChannelNumber::ChannelNumber8 => 2,
+                    uci::ChannelNumber::ChannelNumber9 => 3,
+                    uci::ChannelNumber::ChannelNumber10 => 4,
+                    uci::ChannelNumber::ChannelNumber12 => 5,
+                    uci::ChannelNumber::ChannelNumber13 => 6,
+                    uci::ChannelNumber::ChannelNumber14 => 7,
+                };
+                check(
+                    supports(uci::CapTlvType::SupportedChannels, bit),
+                    uci::ReasonCode::ErrorChannelNotSupported,
+                )
+            }
+            uci::AppConfigTlvType::DeviceRole => check(
+                self.device_role.iter().all(|device_role| {
+                    supports(uci::CapTlvType::SupportedDeviceRoles, (*device_role).into())
+                }),
+                uci::ReasonCode::ErrorDeviceRoleNotSupported,
+            ),
+            uci::AppConfigTlvType::RangingRoundUsage => check(
+                self.ranging_round_usage.iter().all(|ranging_round_usage| {
+                    supports(
+                        uci::CapTlvType::SupportedRangingMethod,
+                        (*ranging_round_usage).into(),
+                    )
+                }),
+                uci::ReasonCode::ErrorInvalidRangingRoundUsage,
+            ),
+            uci::AppConfigTlvType::MultiNodeMode => check(
+                self.multi_node_mode.iter().all(|multi_node_mode| {
+                    supports(
+                        uci::CapTlvType::SupportedMultiNodeModes,
+                        (*multi_node_mode).into(),
+                    )
+                }),
+                uci::ReasonCode::ErrorInvalidMultiNodeMode,
+            ),
+            uci::AppConfigTlvType::StsConfig => check(
+                supports(uci::CapTlvType::SupportedStsConfig, self.sts_config.into()),
+                uci::ReasonCode::ErrorInvalidStsConfig,
+            ),
+            uci::AppConfigTlvType::RframeConfig => check(
+                supports(
+                    uci::CapTlvType::SupportedRframeConfig,
+                    self.rframe_config.into(),
+                ),
+                uci::ReasonCode::ErrorInvalidRframeConfig,
+            ),
+            uci::AppConfigTlvType::AoaResultReq => {
+                // SUPPORTED_AOA: b0 azimuth 90°, b1 azimuth 180°,
+                // b2 elevation, b3 figure of merit.
+                let aoa = bitmap(uci::CapTlvType::SupportedAoa);
+                let azimuth = aoa.iter().all(|aoa| aoa & 0x3 != 0);
+                let elevation = aoa.iter().all(|aoa| aoa & 0x4 != 0);
+                check(
+                    match self.aoa_result_req {
+                        uci::AoaResultReq::AoaDisabled => true,
+                        uci::AoaResultReq::AoaEnabled => azimuth || elevation,
+                        uci::AoaResultReq::AoaEnabledAzimuthOnly => azimuth,
+                        uci::AoaResultReq::AoaEnabledElevationOnly => elevation,
+                    },
+                    uci::ReasonCode::ErrorInvalidChannelWithAoa,
+                )
+            }
+            uci::AppConfigTlvType::MacAddressMode => check(
+                self.mac_address_mode == uci::MacAddressMode::Mode0
+                    || supports(uci::CapTlvType::SupportedExtendedMacAddress, 0),
+                uci::ReasonCode::ErrorMacAddressModeNotSupported,
+            ),
+            uci::AppConfigTlvType::HoppingMode => check(
+                self.hopping_mode == uci::HoppingMode::Disable
+                    || supports(uci::CapTlvType::SupportedHoppingMode, 0),
+                uci::ReasonCode::ErrorHoppingModeNotSupported,
+            ),
+            _ => Ok(()),
+        }
+    }
+
     pub fn is_compatible_for_ranging(&self, peer_config: &Self) -> bool {
-        self.device_role != peer_config.device_role
+        self.is_two_way_ranging()
+            && peer_config.is_two_way_ranging()
+            && self.device_role != peer_config.device_role
             && self.device_type != peer_config.device_type
             && peer_config
                 .dst_mac_address
@@ -507,6 +756,94 @@ impl AppConfig {
                 .contains(&peer_config.device_mac_address.unwrap())
     }
 
+    /// Return true if the configuration is compatible with the
+    /// configuration of a peer CCC session. CCC sessions pair
+    /// a controller with a controlee of the same session.
+    pub fn is_compatible_for_ccc_ranging(&self, peer_config: &Self) -> bool {
+        self.device_type.is_some()
+            && peer_config.device_type.is_some()
+            && self.device_type != peer_config.device_type
+    }
+
+    /// Return the index of the ranging round used in the selected ranging
+    /// block of a CCC session. The ranging round hops pseudo-randomly
+    /// within the block when HOPPING_MODE is enabled, with a sequence
+    /// derived from CCC_HOP_MODE_KEY; the first ranging round is always
+    /// used otherwise.
+    pub fn ccc_ranging_round_index(&self, block_index: u32) -> u8 {
+        if self.hopping_mode == uci::HoppingMode::Disable {
+            return 0;
+        }
+
+        // The ranging block duration is expressed in milliseconds,
+        // the ranging round duration in RSTU (1 ms = 1200 RSTU).
+        let round_duration = self.slot_duration as u64 * self.slots_per_rr as u64;
+        let rounds_per_block = (self.ranging_duration as u64 * 1200)
+            .checked_div(round_duration)
+            .unwrap_or(1)
+            .clamp(1, 256);
+
+        // Splitmix64 finalizer applied to the hop key and block index:
+        // both ends of the session compute the same hopping sequence.
+        let mut z = ((self.ccc_hop_mode_key as u64) << 32) | block_index as u64;
+        z = z.wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        (z % rounds_per_block) as u8
+    }
+
+    /// Return true if the device role is valid for two way ranging.
+    fn is_two_way_ranging(&self) -> bool {
+        matches!(
+            self.device_role,
+            Some(uci::DeviceRole::Initiator | uci::DeviceRole::Responder)
+        )
+    }
+
+    /// Return true if the configuration is compatible with the
+    /// configuration of an OWR AoA advertiser.
+    pub fn can_observe(&self, advertiser_config: &Self) -> bool {
+        self.device_role == Some(uci::DeviceRole::Observer)
+            && advertiser_config.device_role == Some(uci::DeviceRole::Advertiser)
+            && (self.dst_mac_address.is_empty()
+                || advertiser_config
+                    .device_mac_address
+                    .is_some_and(|mac_address| self.dst_mac_address.contains(&mac_address)))
+    }
+
+    /// Return the maximum size of the application data transmitted
+    /// in one ranging round, cf. [UCI] SESSION_QUERY_DATA_SIZE_IN_RANGING.
+    /// The slots of the ranging round not used for the ranging control,
+    /// poll, response and final messages carry one data frame each.
+    /// The frame payload is limited by MTU_SIZE, the maximum PSDU size
+    /// of the PRF mode, and the number of octets transmitted in one
+    /// slot at PSDU_DATA_RATE.
+    pub fn max_data_size_in_ranging(&self) -> u16 {
+        let ranging_slots = 3 + self.number_of_controlees as u64;
+        let data_slots = (self.slots_per_rr as u64).saturating_sub(ranging_slots);
+
+        let max_psdu_size = match self.prf_mode {
+            uci::PrfMode::BprfMode => BPRF_MAX_PSDU_SIZE,
+            _ => HPRF_MAX_PSDU_SIZE,
+        };
+        let mtu_size = match self.mtu_size {
+            0 => max_psdu_size,
+            mtu_size => (mtu_size as u64).min(max_psdu_size),
+        };
+        // The slot duration is expressed in RSTU (1 ms = 1200 RSTU).
+        let data_rate_kbps = match self.psdu_data_rate {
+            uci::PsduDataRate::DataRate6m81 => 6810,
+            uci::PsduDataRate::DataRate7m80 => 7800,
+            uci::PsduDataRate::DataRate27m2 => 27200,
+            uci::PsduDataRate::DataRate31m2 => 31200,
+        };
+        let slot_size = self.slot_duration as u64 * data_rate_kbps / 1200 / 8;
+        let frame_payload_size = mtu_size.min(slot_size).saturating_sub(DATA_FRAME_OVERHEAD);
+
+        (data_slots * frame_payload_size).min(u16::MAX as u64) as u16
+    }
+
     pub fn can_start_data_transfer(&self) -> bool {
         self.device_role == Some(uci::DeviceRole::Initiator)
     }
@@ -515,3 +852,84 @@ impl AppConfig {
         self.device_role == Some(uci::DeviceRole::Responder)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_capabilities() {
+        let caps = vec![
+            uci::CapTlv {
+                t: uci::CapTlvType::SupportedStsConfig,
+                v: vec![0x01],
+            },
+            uci::CapTlv {
+                t: uci::CapTlvType::SupportedDeviceRoles,
+                v: vec![0x00, 0x01],
+            },
+        ];
+        let check = |id: uci::AppConfigTlvType, value: &[u8]| {
+            let mut app_config = AppConfig::default();
+            app_config.set(id, value).unwrap();
+            app_config.check_capabilities(id, uci::SessionType::FiraRangingSession, &caps)
+        };
+
+        assert_eq!(check(uci::AppConfigTlvType::StsConfig, &[0x00]), Ok(()));
+        assert_eq!(
+            check(uci::AppConfigTlvType::StsConfig, &[0x01]),
+            Err(uci::ReasonCode::ErrorInvalidStsConfig)
+        );
+        assert_eq!(check(uci::AppConfigTlvType::DeviceRole, &[0x08]), Ok(()));
+        assert_eq!(
+            check(uci::AppConfigTlvType::DeviceRole, &[0x01]),
+            Err(uci::ReasonCode::ErrorDeviceRoleNotSupported)
+        );
+        // Capabilities not advertised do not restrict the parameters.
+        assert_eq!(check(uci::AppConfigTlvType::ChannelNumber, &[0x05]), Ok(()));
+    }
+
+    #[test]
+    fn test_ccc_ranging_round_index() {
+        let mut app_config = AppConfig {
+            ccc_hop_mode_key: 0x1234,
+            ..Default::default()
+        };
+        // The first ranging round is used when hopping is disabled.
+        assert!((0..16).all(|block| app_config.ccc_ranging_round_index(block) == 0));
+
+        // 4 ranging rounds of 25 slots of 2400 RSTU per ranging block of 200ms.
+        app_config.hopping_mode = uci::HoppingMode::Enable;
+        let sequence: Vec<u8> = (0..16)
+            .map(|block| app_config.ccc_ranging_round_index(block))
+            .collect();
+        assert!(sequence.iter().all(|round| *round < 4));
+        assert!(sequence.iter().any(|round| *round != sequence[0]));
+
+        // The sequence is derived from the hop mode key.
+        app_config.ccc_hop_mode_key = 0x4321;
+        assert!((0..16)
+            .any(|block| app_config.ccc_ranging_round_index(block) != sequence[block as usize]));
+    }
+
+    #[test]
+    fn test_max_data_size_in_ranging() {
+        let mut app_config = AppConfig::default();
+        // 21 data slots of 127 octets.
+        assert_eq!(app_config.max_data_size_in_ranging(), 21 * 111);
+
+        app_config.number_of_controlees = 2;
+        app_config.mtu_size = 64;
+        assert_eq!(app_config.max_data_size_in_ranging(), 20 * 48);
+
+        // The frame payload is limited by the slot duration.
+        app_config.prf_mode = uci::PrfMode::HprfMode124m8;
+        app_config.psdu_data_rate = uci::PsduDataRate::DataRate27m2;
+        app_config.mtu_size = 0;
+        app_config.slot_duration = 600;
+        assert_eq!(app_config.max_data_size_in_ranging(), 20 * (1700 - 16));
+
+        app_config.slots_per_rr = 4;
+        assert_eq!(app_config.max_data_size_in_ranging(), 0);
+    }
+}
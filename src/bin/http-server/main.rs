@@ -18,22 +18,35 @@ use hyper::service::{make_service_fn, service_fn};
 use hyper::{body, Body, Request, Response, Server, StatusCode as HttpStatusCode};
 use serde::{Deserialize, Serialize};
 use serde_json::error::Category as SerdeErrorCategory;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::convert::Infallible;
 use std::net::{Ipv4Addr, SocketAddrV4};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
 use tokio::net::TcpListener;
 use tokio::sync::{broadcast, mpsc, oneshot};
 use tokio::try_join;
 use tokio_stream::{wrappers::BroadcastStream, StreamExt};
 
-use pica::{Category, MacAddress, Pica, PicaCommand, PicaCommandError, PicaEvent};
+use pica::{
+    Category, ClockMode, ConnectionHint, DeviceDeclaration, Fault, FaultNotification, MacAddress,
+    Pica, PicaCommand, PicaCommandError, PicaEvent, UwbsClock,
+};
 
 mod position;
 use position::Position;
 
+mod scenario;
+use scenario::Scenario;
+
+mod trajectory;
+use trajectory::{Motion, Trajectory};
+
+mod obstacle;
+use obstacle::Obstacle;
+
 const DEFAULT_UCI_PORT: u16 = 7000;
 const DEFAULT_WEB_PORT: u16 = 3000;
 
@@ -106,11 +119,35 @@ pub enum Event {
     },
 }
 
+type AnchorResponse = oneshot::Receiver<Result<pica::Handle, PicaCommandError>>;
+
+/// Anchor creation or removal executed by pica, awaiting the response.
+enum AnchorUpdate {
+    Create(MacAddress, Position, AnchorResponse),
+    Destroy(MacAddress, AnchorResponse),
+}
+
 /// Record the position of active devices for reference by the
 /// ranging estimator.
 #[derive(Clone)]
 struct Context {
     devices: Arc<Mutex<HashMap<pica::Handle, DeviceInformation>>>,
+    /// Initial position of the UCI devices, applied when the device
+    /// with the matching MAC address connects.
+    initial_positions: Arc<Mutex<HashMap<MacAddress, Position>>>,
+    /// Pairs of devices which cannot range with each other.
+    /// Links are blocked in both directions.
+    blocked_links: Arc<Mutex<HashSet<(MacAddress, MacAddress)>>>,
+    /// Trajectories followed by the devices, applied at every ranging
+    /// round once the device with the matching MAC address is present.
+    motions: Arc<Mutex<HashMap<MacAddress, Motion>>>,
+    /// Walls and obstacles of the scene, identified by name.
+    obstacles: Arc<Mutex<HashMap<String, Obstacle>>>,
+    /// Anchor creations and removals returned to pica by scenario
+    /// actions, applied in order once pica has responded.
+    anchor_updates: Arc<Mutex<VecDeque<AnchorUpdate>>>,
+    /// UCI ports accepting connections.
+    uci_ports: Arc<Mutex<HashSet<u16>>>,
     events: broadcast::Sender<Event>,
 }
 
@@ -119,10 +156,103 @@ impl Context {
         let (events, _) = broadcast::channel(1024);
         Context {
             devices: Arc::new(Mutex::new(HashMap::new())),
+            initial_positions: Default::default(),
+            blocked_links: Default::default(),
+            motions: Default::default(),
+            obstacles: Default::default(),
+            anchor_updates: Default::default(),
+            uci_ports: Default::default(),
             events,
         }
     }
 
+    /// Publish an event to the web interface. The event is dropped
+    /// if no client is listening.
+    fn send_event(&self, event: Event) {
+        let _ = self.events.send(event);
+    }
+
+    fn set_initial_position(&self, mac_address: MacAddress, position: Position) {
+        let mut initial_positions = self.initial_positions.lock().unwrap();
+        initial_positions.insert(mac_address, position);
+    }
+
+    fn set_trajectory(&self, mac_address: MacAddress, trajectory: Option<Trajectory>) {
+        log::info!("set-trajectory({}, {:?})", mac_address, trajectory);
+
+        let mut motions = self.motions.lock().unwrap();
+        match trajectory {
+            Some(trajectory) => motions.insert(mac_address, Motion::new(trajectory)),
+            None => motions.remove(&mac_address),
+        };
+    }
+
+    /// Move the devices following a trajectory to their position
+    /// at the selected time.
+    fn update_motions(&self, time: Duration) {
+        let updates = {
+            let devices = self.devices.lock().unwrap();
+            let mut motions = self.motions.lock().unwrap();
+            devices
+                .values()
+                .filter_map(|device| {
+                    let motion = motions.get_mut(&device.mac_address)?;
+                    let position = motion.update(device.position, time);
+                    (position.point() != device.position.point())
+                        .then_some((device.mac_address, position))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        for (mac_address, position) in updates {
+            self.update_position(mac_address, position);
+        }
+    }
+
+    fn create_obstacle(&self, name: &str, obstacle: Obstacle) -> HttpStatusCode {
+        log::info!("create-obstacle({}, {:?})", name, obstacle);
+
+        let mut obstacles = self.obstacles.lock().unwrap();
+        if obstacles.contains_key(name) {
+            return HttpStatusCode::CONFLICT;
+        }
+        obstacles.insert(name.to_owned(), obstacle);
+        HttpStatusCode::OK
+    }
+
+    fn destroy_obstacle(&self, name: &str) -> HttpStatusCode {
+        log::info!("destroy-obstacle({})", name);
+
+        let mut obstacles = self.obstacles.lock().unwrap();
+        match obstacles.remove(name) {
+            Some(_) => HttpStatusCode::OK,
+            None => HttpStatusCode::NOT_FOUND,
+        }
+    }
+
+    fn set_link_blocked(&self, left: MacAddress, right: MacAddress, blocked: bool) {
+        log::info!(
+            "set-link-blocked({}, {}, {})",
+            left,
+            right,
+            if blocked { "blocked" } else { "unblocked" }
+        );
+
+        let mut blocked_links = self.blocked_links.lock().unwrap();
+        if blocked {
+            blocked_links.insert((left, right));
+            blocked_links.insert((right, left));
+        } else {
+            blocked_links.remove(&(left, right));
+            blocked_links.remove(&(right, left));
+        }
+    }
+
+    fn is_link_blocked(&self, left: MacAddress, right: MacAddress) -> bool {
+        let blocked_links = self.blocked_links.lock().unwrap();
+        blocked_links.contains(&(left, right))
+    }
+
     async fn handle_connection_events(
         self,
         mut events: broadcast::Receiver<PicaEvent>,
@@ -133,22 +263,27 @@ impl Context {
                     mac_address,
                     handle,
                 }) => {
+                    let position = self
+                        .initial_positions
+                        .lock()
+                        .unwrap()
+                        .get(&mac_address)
+                        .copied()
+                        .unwrap_or_default();
                     let mut devices = self.devices.lock().unwrap();
                     devices.insert(
                         handle,
                         DeviceInformation {
                             category: Category::Uci,
                             mac_address,
-                            position: Default::default(),
+                            position,
                         },
                     );
-                    self.events
-                        .send(Event::DeviceAdded {
-                            category: Category::Uci,
-                            mac_address,
-                            position: Default::default(),
-                        })
-                        .unwrap();
+                    self.send_event(Event::DeviceAdded {
+                        category: Category::Uci,
+                        mac_address,
+                        position,
+                    });
                 }
                 Ok(PicaEvent::Disconnected {
                     mac_address,
@@ -156,12 +291,10 @@ impl Context {
                 }) => {
                     let mut devices = self.devices.lock().unwrap();
                     devices.remove(&handle);
-                    self.events
-                        .send(Event::DeviceRemoved {
-                            category: Category::Uci,
-                            mac_address,
-                        })
-                        .unwrap();
+                    self.send_event(Event::DeviceRemoved {
+                        category: Category::Uci,
+                        mac_address,
+                    });
                 }
                 Err(err) => anyhow::bail!(err),
             }
@@ -185,8 +318,26 @@ impl Context {
     }
 
     fn http_set_position(&self, mac_address: MacAddress, position: Position) -> Response<Body> {
+        let status = if self.set_position(mac_address, position) {
+            HttpStatusCode::OK
+        } else {
+            HttpStatusCode::NOT_FOUND
+        };
+
+        Response::builder().status(status).body("".into()).unwrap()
+    }
+
+    /// Update the position of the device with the selected MAC address.
+    /// Returns false if the device is not found.
+    fn set_position(&self, mac_address: MacAddress, position: Position) -> bool {
         log::info!("set-position({}, {})", mac_address, position);
+        self.update_position(mac_address, position)
+    }
 
+    /// Update the position of the device, and publish the device and
+    /// neighbor update events.
+    fn update_position(&self, mac_address: MacAddress, position: Position) -> bool {
+        self.apply_anchor_updates();
         let mut devices = self.devices.lock().unwrap();
         let mut found_device = None;
         for (_, device) in devices.iter_mut() {
@@ -198,19 +349,14 @@ impl Context {
         }
 
         let Some(device) = found_device else {
-            return Response::builder()
-                .status(HttpStatusCode::NOT_FOUND)
-                .body("".into())
-                .unwrap();
+            return false;
         };
 
-        self.events
-            .send(Event::DeviceUpdated {
-                category: device.category,
-                mac_address,
-                position,
-            })
-            .unwrap();
+        self.send_event(Event::DeviceUpdated {
+            category: device.category,
+            mac_address,
+            position,
+        });
 
         for other in devices.values() {
             if other.mac_address != device.mac_address {
@@ -223,37 +369,29 @@ impl Context {
 
                 assert!(local.0 == remote.0);
 
-                self.events
-                    .send(Event::NeighborUpdated {
-                        source_category: device.category,
-                        source_mac_address: device.mac_address,
-                        destination_category: other.category,
-                        destination_mac_address: other.mac_address,
-                        distance: local.0,
-                        azimuth: local.1,
-                        elevation: local.2,
-                    })
-                    .unwrap();
-
-                let _ = self
-                    .events
-                    .send(Event::NeighborUpdated {
-                        source_category: other.category,
-                        source_mac_address: other.mac_address,
-                        destination_category: device.category,
-                        destination_mac_address: device.mac_address,
-                        distance: remote.0,
-                        azimuth: remote.1,
-                        elevation: remote.2,
-                    })
-                    .unwrap();
+                self.send_event(Event::NeighborUpdated {
+                    source_category: device.category,
+                    source_mac_address: device.mac_address,
+                    destination_category: other.category,
+                    destination_mac_address: other.mac_address,
+                    distance: local.0,
+                    azimuth: local.1,
+                    elevation: local.2,
+                });
+
+                self.send_event(Event::NeighborUpdated {
+                    source_category: other.category,
+                    source_mac_address: other.mac_address,
+                    destination_category: device.category,
+                    destination_mac_address: device.mac_address,
+                    distance: remote.0,
+                    azimuth: remote.1,
+                    elevation: remote.2,
+                });
             }
         }
 
-        Response::builder()
-            .status(HttpStatusCode::OK)
-            .body("".into())
-            .unwrap()
+        true
     }
 
     async fn http_create_anchor(
@@ -262,6 +400,16 @@ impl Context {
         position: Position,
         cmd_tx: mpsc::Sender<PicaCommand>,
     ) -> Response<Body> {
+        let status = self.create_anchor(mac_address, position, cmd_tx).await;
+        Response::builder().status(status).body("".into()).unwrap()
+    }
+
+    async fn create_anchor(
+        &self,
+        mac_address: MacAddress,
+        position: Position,
+        cmd_tx: mpsc::Sender<PicaCommand>,
+    ) -> HttpStatusCode {
         log::info!("create-anchor({}, {})", mac_address, position);
 
         let (rsp_tx, rsp_rx) = oneshot::channel::<Result<pica::Handle, PicaCommandError>>();
@@ -270,32 +418,30 @@ impl Context {
             .await
             .unwrap();
 
-        let status = match rsp_rx.await {
+        match rsp_rx.await {
             Ok(Ok(handle)) => {
-                let mut devices = self.devices.lock().unwrap();
-                devices.insert(
-                    handle,
-                    DeviceInformation {
-                        position,
-                        mac_address,
-                        category: Category::Anchor,
-                    },
-                );
-                self.events
-                    .send(Event::DeviceAdded {
-                        category: Category::Anchor,
-                        mac_address,
-                        position,
-                    })
-                    .unwrap();
+                self.add_anchor(handle, mac_address, position);
                 HttpStatusCode::OK
             }
-            Ok(Err(PicaCommandError::DeviceAlreadyExists(_))) => HttpStatusCode::CONFLICT,
-            Ok(Err(PicaCommandError::DeviceNotFound(_))) => HttpStatusCode::NOT_FOUND,
-            Err(_) => HttpStatusCode::INTERNAL_SERVER_ERROR,
-        };
+            result => command_status(result),
+        }
+    }
 
-        Response::builder().status(status).body("".into()).unwrap()
+    fn add_anchor(&self, handle: pica::Handle, mac_address: MacAddress, position: Position) {
+        let mut devices = self.devices.lock().unwrap();
+        devices.insert(
+            handle,
+            DeviceInformation {
+                position,
+                mac_address,
+                category: Category::Anchor,
+            },
+        );
+        self.send_event(Event::DeviceAdded {
+            category: Category::Anchor,
+            mac_address,
+            position,
+        });
     }
 
     async fn http_destroy_anchor(
@@ -303,6 +449,15 @@ impl Context {
         mac_address: MacAddress,
         cmd_tx: mpsc::Sender<PicaCommand>,
     ) -> Response<Body> {
+        let status = self.destroy_anchor(mac_address, cmd_tx).await;
+        Response::builder().status(status).body("".into()).unwrap()
+    }
+
+    async fn destroy_anchor(
+        &self,
+        mac_address: MacAddress,
+        cmd_tx: mpsc::Sender<PicaCommand>,
+    ) -> HttpStatusCode {
         log::info!("destroy-anchor({})", mac_address);
 
         let (rsp_tx, rsp_rx) = oneshot::channel::<Result<pica::Handle, PicaCommandError>>();
@@ -311,28 +466,270 @@ impl Context {
             .await
             .unwrap();
 
-        let status = match rsp_rx.await {
+        match rsp_rx.await {
             Ok(Ok(handle)) => {
-                let mut devices = self.devices.lock().unwrap();
-                devices.remove(&handle);
-                self.events
-                    .send(Event::DeviceRemoved {
-                        category: Category::Anchor,
-                        mac_address,
-                    })
-                    .unwrap();
+                self.remove_anchor(handle, mac_address);
                 HttpStatusCode::OK
             }
-            Ok(Err(PicaCommandError::DeviceAlreadyExists(_))) => HttpStatusCode::CONFLICT,
-            Ok(Err(PicaCommandError::DeviceNotFound(_))) => HttpStatusCode::NOT_FOUND,
-            Err(_) => HttpStatusCode::INTERNAL_SERVER_ERROR,
+            result => command_status(result),
+        }
+    }
+
+    fn remove_anchor(&self, handle: pica::Handle, mac_address: MacAddress) {
+        let mut devices = self.devices.lock().unwrap();
+        devices.remove(&handle);
+        self.send_event(Event::DeviceRemoved {
+            category: Category::Anchor,
+            mac_address,
+        });
+    }
+
+    /// Return the command creating an anchor, for execution by pica
+    /// from a timer action. The anchor is added to the context once
+    /// created, before the next ranging estimation.
+    fn create_anchor_command(&self, mac_address: MacAddress, position: Position) -> PicaCommand {
+        log::info!("create-anchor({}, {})", mac_address, position);
+
+        let (rsp_tx, rsp_rx) = oneshot::channel();
+        self.anchor_updates
+            .lock()
+            .unwrap()
+            .push_back(AnchorUpdate::Create(mac_address, position, rsp_rx));
+        PicaCommand::CreateAnchor(mac_address, rsp_tx)
+    }
+
+    /// Return the command destroying an anchor, for execution by pica
+    /// from a timer action. The anchor is removed from the context once
+    /// destroyed, before the next ranging estimation.
+    fn destroy_anchor_command(&self, mac_address: MacAddress) -> PicaCommand {
+        log::info!("destroy-anchor({})", mac_address);
+
+        let (rsp_tx, rsp_rx) = oneshot::channel();
+        self.anchor_updates
+            .lock()
+            .unwrap()
+            .push_back(AnchorUpdate::Destroy(mac_address, rsp_rx));
+        PicaCommand::DestroyAnchor(mac_address, rsp_tx)
+    }
+
+    /// Apply the anchor creations and removals already executed by pica.
+    fn apply_anchor_updates(&self) {
+        let mut anchor_updates = self.anchor_updates.lock().unwrap();
+        while let Some(update) = anchor_updates.front_mut() {
+            let (AnchorUpdate::Create(mac_address, _, rsp_rx)
+            | AnchorUpdate::Destroy(mac_address, rsp_rx)) = update;
+            let mac_address = *mac_address;
+            let result = match rsp_rx.try_recv() {
+                Err(oneshot::error::TryRecvError::Empty) => break,
+                result => result,
+            };
+            match (anchor_updates.pop_front().unwrap(), result) {
+                (AnchorUpdate::Create(_, position, _), Ok(Ok(handle))) => {
+                    self.add_anchor(handle, mac_address, position)
+                }
+                (AnchorUpdate::Destroy(..), Ok(Ok(handle))) => {
+                    self.remove_anchor(handle, mac_address)
+                }
+                (_, Ok(Err(err))) => log::error!("Failed to update anchor: {}", err),
+                (_, Err(err)) => log::error!("Failed to update anchor {}: {}", mac_address, err),
+            }
+        }
+    }
+
+    async fn http_step(&self, cmd_tx: mpsc::Sender<PicaCommand>) -> Response<Body> {
+        log::info!("step()");
+
+        #[derive(Serialize)]
+        struct StepResponse {
+            time_us: u128,
+        }
+
+        let (rsp_tx, rsp_rx) = oneshot::channel();
+        cmd_tx.send(PicaCommand::Step(rsp_tx)).await.unwrap();
+
+        match rsp_rx.await {
+            Ok(time) => Response::builder()
+                .status(HttpStatusCode::OK)
+                .body(
+                    serde_json::to_string(&StepResponse {
+                        time_us: time.as_micros(),
+                    })
+                    .unwrap()
+                    .into(),
+                )
+                .unwrap(),
+            Err(_) => Response::builder()
+                .status(HttpStatusCode::INTERNAL_SERVER_ERROR)
+                .body("".into())
+                .unwrap(),
+        }
+    }
+
+    async fn http_set_faults(
+        &self,
+        mac_address: MacAddress,
+        faults: Vec<Fault>,
+        cmd_tx: mpsc::Sender<PicaCommand>,
+    ) -> Response<Body> {
+        log::info!("set-faults({}, {:?})", mac_address, faults);
+
+        let (rsp_tx, rsp_rx) = oneshot::channel::<Result<pica::Handle, PicaCommandError>>();
+        cmd_tx
+            .send(PicaCommand::SetFaults(mac_address, faults, rsp_tx))
+            .await
+            .unwrap();
+
+        let status = command_status(rsp_rx.await);
+        Response::builder().status(status).body("".into()).unwrap()
+    }
+
+    async fn http_set_uwbs_clock(
+        &self,
+        mac_address: MacAddress,
+        uwbs_clock: UwbsClock,
+        cmd_tx: mpsc::Sender<PicaCommand>,
+    ) -> Response<Body> {
+        log::info!("set-uwbs-clock({}, {:?})", mac_address, uwbs_clock);
+
+        let (rsp_tx, rsp_rx) = oneshot::channel::<Result<pica::Handle, PicaCommandError>>();
+        cmd_tx
+            .send(PicaCommand::SetUwbsClock(mac_address, uwbs_clock, rsp_tx))
+            .await
+            .unwrap();
+
+        let status = command_status(rsp_rx.await);
+        Response::builder().status(status).body("".into()).unwrap()
+    }
+
+    async fn http_declare_device(
+        &self,
+        mac_address: MacAddress,
+        body: DeclareDeviceBody,
+        cmd_tx: mpsc::Sender<PicaCommand>,
+    ) -> Response<Body> {
+        log::info!("declare-device({}, {:?})", mac_address, body);
+
+        let declaration = match body.into_declaration(mac_address) {
+            Ok(declaration) => declaration,
+            Err(reason) => {
+                log::error!("{}", reason);
+                return Response::builder().status(406).body(reason.into()).unwrap();
+            }
         };
+        let hint = declaration.hint.clone();
+        let (rsp_tx, rsp_rx) = oneshot::channel::<Result<(), PicaCommandError>>();
+        cmd_tx
+            .send(PicaCommand::DeclareDevice(declaration, rsp_tx))
+            .await
+            .unwrap();
 
+        let status = command_status(rsp_rx.await);
+        if let (HttpStatusCode::OK, Some(ConnectionHint::Port(port))) = (status, hint) {
+            self.spawn_listener(port, cmd_tx);
+        }
+        Response::builder().status(status).body("".into()).unwrap()
+    }
+
+    /// Accept UCI connections on a new port, for devices declared
+    /// after startup.
+    fn spawn_listener(&self, port: u16, cmd_tx: mpsc::Sender<PicaCommand>) {
+        if !self.uci_ports.lock().unwrap().insert(port) {
+            return;
+        }
+        let uci_ports = self.uci_ports.clone();
+        tokio::spawn(async move {
+            if let Err(err) = listen(cmd_tx, port).await {
+                log::error!("Failed to listen on UCI port {}: {}", port, err);
+                uci_ports.lock().unwrap().remove(&port);
+            }
+        });
+    }
+
+    async fn http_get_device_bindings(&self, cmd_tx: mpsc::Sender<PicaCommand>) -> Response<Body> {
+        log::info!("get-device-bindings()");
+
+        let (rsp_tx, rsp_rx) = oneshot::channel();
+        cmd_tx
+            .send(PicaCommand::GetDeviceBindings(rsp_tx))
+            .await
+            .unwrap();
+
+        match rsp_rx.await {
+            Ok(bindings) => Response::builder()
+                .status(HttpStatusCode::OK)
+                .body(serde_json::to_string(&bindings).unwrap().into())
+                .unwrap(),
+            Err(_) => Response::builder()
+                .status(HttpStatusCode::INTERNAL_SERVER_ERROR)
+                .body("".into())
+                .unwrap(),
+        }
+    }
+
+    async fn http_set_device_error(
+        &self,
+        mac_address: MacAddress,
+        cmd_tx: mpsc::Sender<PicaCommand>,
+    ) -> Response<Body> {
+        log::info!("set-device-error({})", mac_address);
+
+        let (rsp_tx, rsp_rx) = oneshot::channel::<Result<pica::Handle, PicaCommandError>>();
+        cmd_tx
+            .send(PicaCommand::SetDeviceError(mac_address, rsp_tx))
+            .await
+            .unwrap();
+
+        let status = command_status(rsp_rx.await);
+        Response::builder().status(status).body("".into()).unwrap()
+    }
+
+    async fn http_inject_notification(
+        &self,
+        mac_address: MacAddress,
+        notification: FaultNotification,
+        cmd_tx: mpsc::Sender<PicaCommand>,
+    ) -> Response<Body> {
+        log::info!("inject-notification({}, {:?})", mac_address, notification);
+
+        let (rsp_tx, rsp_rx) = oneshot::channel::<Result<pica::Handle, PicaCommandError>>();
+        cmd_tx
+            .send(PicaCommand::InjectNotification(
+                mac_address,
+                notification,
+                rsp_tx,
+            ))
+            .await
+            .unwrap();
+
+        let status = command_status(rsp_rx.await);
+        Response::builder().status(status).body("".into()).unwrap()
+    }
+
+    fn http_set_trajectory(
+        &self,
+        mac_address: MacAddress,
+        trajectory: Option<Trajectory>,
+    ) -> Response<Body> {
+        self.set_trajectory(mac_address, trajectory);
+        Response::builder()
+            .status(HttpStatusCode::OK)
+            .body("".into())
+            .unwrap()
+    }
+
+    fn http_create_obstacle(&self, name: &str, obstacle: Obstacle) -> Response<Body> {
+        let status = self.create_obstacle(name, obstacle);
+        Response::builder().status(status).body("".into()).unwrap()
+    }
+
+    fn http_destroy_obstacle(&self, name: &str) -> Response<Body> {
+        let status = self.destroy_obstacle(name);
         Response::builder().status(status).body("".into()).unwrap()
     }
 
     fn http_get_state(&self) -> Response<Body> {
         log::info!("get-state()");
+        self.apply_anchor_updates();
 
         #[derive(Serialize)]
         struct GetStateResponse {
@@ -356,20 +753,106 @@ impl pica::RangingEstimator for Context {
         &self,
         left: &pica::Handle,
         right: &pica::Handle,
+        context: &pica::RangingContext,
     ) -> Option<pica::RangingMeasurement> {
+        self.apply_anchor_updates();
+        self.update_motions(context.time);
         let devices = self.devices.lock().ok()?;
-        let left_pos = devices.get(left)?.position;
-        let right_pos = devices.get(right)?.position;
-        let (range, azimuth, elevation) = left_pos.compute_range_azimuth_elevation(&right_pos);
+        let left = devices.get(left)?;
+        let right = devices.get(right)?;
+        if self.is_link_blocked(left.mac_address, right.mac_address) {
+            return None;
+        }
+        let (range, azimuth, elevation) = left
+            .position
+            .compute_range_azimuth_elevation(&right.position);
+        let path_loss = obstacle::path_loss(
+            self.obstacles.lock().ok()?.values(),
+            left.position.point(),
+            right.position.point(),
+        );
+        if path_loss.is_blocked() {
+            return None;
+        }
         Some(pica::RangingMeasurement {
-            range,
+            range: range.saturating_add(path_loss.range_bias.round() as u16),
             azimuth,
             elevation,
+            nlos: path_loss.nlos,
+            azimuth_fom: path_loss.figure_of_merit(),
+            elevation_fom: path_loss.figure_of_merit(),
+            rssi: path_loss.rssi(range),
+            ..Default::default()
         })
     }
+
+    fn location(&self, device: &pica::Handle) -> Option<pica::Location> {
+        self.apply_anchor_updates();
+        let devices = self.devices.lock().ok()?;
+        Some(devices.get(device)?.position.location())
+    }
+}
+
+/// Map the result of a pica command to the HTTP status code.
+fn command_status<T>(
+    result: Result<Result<T, PicaCommandError>, oneshot::error::RecvError>,
+) -> HttpStatusCode {
+    match result {
+        Ok(Ok(_)) => HttpStatusCode::OK,
+        Ok(Err(PicaCommandError::DeviceAlreadyExists(_))) => HttpStatusCode::CONFLICT,
+        Ok(Err(PicaCommandError::DeviceNotFound(_))) => HttpStatusCode::NOT_FOUND,
+        Ok(Err(PicaCommandError::ProfileNotFound(_))) => HttpStatusCode::NOT_ACCEPTABLE,
+        Err(_) => HttpStatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Body of the declare-device request: the optional connection hint,
+/// either a port or a tag, and the optional name of the device profile.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DeclareDeviceBody {
+    port: Option<u16>,
+    tag: Option<String>,
+    profile: Option<String>,
 }
 
-#[derive(Deserialize)]
+impl DeclareDeviceBody {
+    fn into_declaration(self, mac_address: MacAddress) -> Result<DeviceDeclaration, String> {
+        let hint = match (self.port, self.tag) {
+            (Some(_), Some(_)) => return Err("port and tag are mutually exclusive".to_owned()),
+            (Some(port), None) => Some(ConnectionHint::Port(port)),
+            (None, Some(tag)) => Some(ConnectionHint::Tag(tag)),
+            (None, None) => None,
+        };
+        Ok(DeviceDeclaration {
+            mac_address,
+            hint,
+            profile: self.profile,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum NotificationBody {
+    GenericError { status: u8 },
+    DeviceStateError,
+}
+
+impl TryFrom<NotificationBody> for FaultNotification {
+    type Error = u8;
+
+    fn try_from(body: NotificationBody) -> Result<Self, Self::Error> {
+        Ok(match body {
+            NotificationBody::GenericError { status } => {
+                FaultNotification::GenericError(pica::packets::uci::Status::try_from(status)?)
+            }
+            NotificationBody::DeviceStateError => FaultNotification::DeviceStateError,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize)]
 struct PositionBody {
     x: i16,
     y: i16,
@@ -379,13 +862,19 @@ struct PositionBody {
     roll: i16,
 }
 
+impl From<PositionBody> for Position {
+    fn from(body: PositionBody) -> Self {
+        Position::new(body.x, body.y, body.z, body.yaw, body.pitch, body.roll)
+    }
+}
+
 macro_rules! position {
     ($body: ident) => {
         position!($body, false)
     };
     ($body: ident, $mandatory: ident) => {
         match serde_json::from_slice::<PositionBody>(&$body) {
-            Ok(body) => Position::new(body.x, body.y, body.z, body.yaw, body.pitch, body.roll),
+            Ok(body) => Position::from(body),
             Err(err) => {
                 if !$mandatory && err.classify() == SerdeErrorCategory::Eof {
                     Position::default()
@@ -399,6 +888,92 @@ macro_rules! position {
     };
 }
 
+macro_rules! trajectory {
+    ($body: ident) => {
+        match serde_json::from_slice::<Trajectory>(&$body) {
+            Ok(trajectory) => Some(trajectory),
+            Err(err) if err.classify() == SerdeErrorCategory::Eof => None,
+            Err(err) => {
+                let reason = format!("Error while deserializing trajectory: {}", err);
+                log::error!("{}", reason);
+                return Ok(Response::builder().status(406).body(reason.into()).unwrap());
+            }
+        }
+    };
+}
+
+macro_rules! obstacle {
+    ($body: ident) => {
+        match serde_json::from_slice::<Obstacle>(&$body) {
+            Ok(obstacle) => obstacle,
+            Err(err) => {
+                let reason = format!("Error while deserializing obstacle: {}", err);
+                log::error!("{}", reason);
+                return Ok(Response::builder().status(406).body(reason.into()).unwrap());
+            }
+        }
+    };
+}
+
+macro_rules! declaration {
+    ($body: ident) => {
+        match serde_json::from_slice::<DeclareDeviceBody>(&$body) {
+            Ok(body) => body,
+            Err(err) if err.classify() == SerdeErrorCategory::Eof => Default::default(),
+            Err(err) => {
+                let reason = format!("Error while deserializing device declaration: {}", err);
+                log::error!("{}", reason);
+                return Ok(Response::builder().status(406).body(reason.into()).unwrap());
+            }
+        }
+    };
+}
+
+macro_rules! faults {
+    ($body: ident) => {
+        match serde_json::from_slice::<Vec<Fault>>(&$body) {
+            Ok(faults) => faults,
+            Err(err) if err.classify() == SerdeErrorCategory::Eof => vec![],
+            Err(err) => {
+                let reason = format!("Error while deserializing faults: {}", err);
+                log::error!("{}", reason);
+                return Ok(Response::builder().status(406).body(reason.into()).unwrap());
+            }
+        }
+    };
+}
+
+macro_rules! uwbs_clock {
+    ($body: ident) => {
+        match serde_json::from_slice::<UwbsClock>(&$body) {
+            Ok(uwbs_clock) => uwbs_clock,
+            Err(err) => {
+                let reason = format!("Error while deserializing UWBS clock: {}", err);
+                log::error!("{}", reason);
+                return Ok(Response::builder().status(406).body(reason.into()).unwrap());
+            }
+        }
+    };
+}
+
+macro_rules! notification {
+    ($body: ident) => {
+        match serde_json::from_slice::<NotificationBody>(&$body)
+            .map_err(|err| err.to_string())
+            .and_then(|body| {
+                FaultNotification::try_from(body)
+                    .map_err(|status| format!("invalid status 0x{:x}", status))
+            }) {
+            Ok(notification) => notification,
+            Err(err) => {
+                let reason = format!("Error while deserializing notification: {}", err);
+                log::error!("{}", reason);
+                return Ok(Response::builder().status(406).body(reason.into()).unwrap());
+            }
+        }
+    };
+}
+
 macro_rules! mac_address {
     ($mac_address: ident) => {
         match MacAddress::new($mac_address.to_string()) {
@@ -454,6 +1029,9 @@ async fn http_request(
         ["set-position", mac_address] => {
             context.http_set_position(mac_address!(mac_address), position!(body))
         }
+        ["set-trajectory", mac_address] => {
+            context.http_set_trajectory(mac_address!(mac_address), trajectory!(body))
+        }
         ["create-anchor", mac_address] => {
             context
                 .http_create_anchor(mac_address!(mac_address), position!(body), cmd_tx)
@@ -464,7 +1042,36 @@ async fn http_request(
                 .http_destroy_anchor(mac_address!(mac_address), cmd_tx)
                 .await
         }
+        ["create-obstacle", name] => context.http_create_obstacle(name, obstacle!(body)),
+        ["destroy-obstacle", name] => context.http_destroy_obstacle(name),
+        ["set-faults", mac_address] => {
+            context
+                .http_set_faults(mac_address!(mac_address), faults!(body), cmd_tx)
+                .await
+        }
+        ["set-uwbs-clock", mac_address] => {
+            context
+                .http_set_uwbs_clock(mac_address!(mac_address), uwbs_clock!(body), cmd_tx)
+                .await
+        }
+        ["declare-device", mac_address] => {
+            context
+                .http_declare_device(mac_address!(mac_address), declaration!(body), cmd_tx)
+                .await
+        }
+        ["get-device-bindings"] => context.http_get_device_bindings(cmd_tx).await,
+        ["set-device-error", mac_address] => {
+            context
+                .http_set_device_error(mac_address!(mac_address), cmd_tx)
+                .await
+        }
+        ["inject-notification", mac_address] => {
+            context
+                .http_inject_notification(mac_address!(mac_address), notification!(body), cmd_tx)
+                .await
+        }
         ["get-state"] => context.http_get_state(),
+        ["step"] => context.http_step(cmd_tx).await,
 
         _ => Response::builder()
             .status(HttpStatusCode::NOT_FOUND)
@@ -495,6 +1102,8 @@ async fn serve(context: Context, tx: mpsc::Sender<PicaCommand>, web_port: u16) -
     Ok(())
 }
 
+/// Accept the UCI connections on the selected port. The connections
+/// are assigned the MAC address of the devices declared on this port.
 async fn listen(tx: mpsc::Sender<PicaCommand>, uci_port: u16) -> Result<()> {
     let uci_socket = SocketAddrV4::new(Ipv4Addr::LOCALHOST, uci_port);
     let uci_listener = TcpListener::bind(uci_socket).await?;
@@ -508,9 +1117,13 @@ async fn listen(tx: mpsc::Sender<PicaCommand>, uci_port: u16) -> Result<()> {
         let stream = Box::pin(futures::stream::unfold(read_half, pica::packets::uci::read));
         let sink = Box::pin(futures::sink::unfold(write_half, pica::packets::uci::write));
 
-        tx.send(PicaCommand::Connect(stream, sink))
-            .await
-            .map_err(|_| anyhow::anyhow!("pica command stream closed"))?
+        tx.send(PicaCommand::ConnectWithHint(
+            stream,
+            sink,
+            ConnectionHint::Port(uci_port),
+        ))
+        .await
+        .map_err(|_| anyhow::anyhow!("pica command stream closed"))?
     }
 }
 
@@ -528,6 +1141,42 @@ struct Args {
     /// Configure the HTTP port for the web interface.
     #[arg(short, long, value_name = "PORT", default_value_t = DEFAULT_WEB_PORT)]
     web_port: u16,
+    /// Add noise to the ranging measurements, with the default noise model.
+    /// The random sequence is seeded with the provided value.
+    #[arg(long, value_name = "SEED")]
+    noise_seed: Option<u64>,
+    /// Select how time elapses in the emulation. The virtual clock
+    /// is stepped with the `/step` HTTP command in `step` mode.
+    #[arg(long, value_enum, default_value_t = ClockMode::RealTime)]
+    clock: ClockMode,
+    /// Limit the range at which devices can measure each other with
+    /// the link budget defined in a JSON file.
+    #[arg(long, value_name = "FILE")]
+    link_budget: Option<PathBuf>,
+    /// Load the named capability profiles of the UCI devices from
+    /// a JSON file.
+    #[arg(long, value_name = "FILE")]
+    device_profiles: Option<PathBuf>,
+    /// Load and play a scenario file describing the anchors, the initial
+    /// position of the UCI devices, and timed events.
+    #[arg(long, value_name = "FILE")]
+    scenario: Option<PathBuf>,
+    /// Declare a UCI device with a fixed MAC address, optionally bound
+    /// to a dedicated UCI port and assigned a capability profile.
+    /// Can be repeated.
+    #[arg(long = "device", value_name = "MAC[@PORT][=PROFILE]")]
+    devices: Vec<DeviceDeclaration>,
+    /// Limit the number of connected UCI devices.
+    /// The number of devices is not limited by default.
+    #[arg(long, value_name = "COUNT")]
+    max_device: Option<usize>,
+    /// Maximum number of sessions per UCI device.
+    #[arg(long, value_name = "COUNT", default_value_t = 255)]
+    max_session: usize,
+    /// Assign extended MAC addresses to the undeclared UCI devices,
+    /// instead of short MAC addresses.
+    #[arg(long)]
+    extended_mac_address: bool,
 }
 
 #[tokio::main]
@@ -538,18 +1187,112 @@ async fn main() -> Result<()> {
         "UCI port and WEB port must be different."
     );
 
+    let scenario = args.scenario.as_deref().map(Scenario::load).transpose()?;
     let context = Context::new();
 
-    let pica = Pica::new(Box::new(context.clone()), args.pcapng_dir);
+    let ranging_estimator: Box<dyn pica::RangingEstimator> = match args.noise_seed {
+        Some(seed) => Box::new(pica::StochasticRangingEstimator::new(
+            Box::new(context.clone()),
+            pica::NoiseModel::default(),
+            seed,
+        )),
+        None => Box::new(context.clone()),
+    };
+    let mut uci_ports = vec![args.uci_port];
+    for declaration in &args.devices {
+        if let Some(ConnectionHint::Port(port)) = declaration.hint {
+            if !uci_ports.contains(&port) {
+                uci_ports.push(port)
+            }
+        }
+    }
+    context.uci_ports.lock().unwrap().extend(&uci_ports);
+
+    let device_profiles = args
+        .device_profiles
+        .as_deref()
+        .map(pica::DeviceProfile::load)
+        .transpose()?
+        .unwrap_or_default();
+    let mut pica = Pica::new(ranging_estimator, args.pcapng_dir)
+        .with_clock_mode(args.clock)
+        .with_max_session(args.max_session)
+        .with_device_profiles(device_profiles)
+        .with_devices(args.devices)?;
+    if let Some(max_device) = args.max_device {
+        pica = pica.with_max_device(max_device);
+    }
+    if args.extended_mac_address {
+        pica = pica.with_extended_mac_addresses();
+    }
+    if let Some(path) = args.link_budget {
+        pica = pica.with_link_budget(pica::LinkBudget::load(&path)?);
+    }
     let cmd_tx = pica.commands();
     let events_rx = pica.events();
 
     try_join!(
         pica.run(),
-        listen(cmd_tx.clone(), args.uci_port),
+        futures::future::try_join_all(
+            uci_ports
+                .into_iter()
+                .map(|uci_port| listen(cmd_tx.clone(), uci_port))
+        ),
         serve(context.clone(), cmd_tx.clone(), args.web_port),
-        context.handle_connection_events(events_rx),
+        context.clone().handle_connection_events(events_rx),
+        async {
+            match scenario {
+                Some(scenario) => scenario.play(context, cmd_tx.clone()).await,
+                None => Ok(()),
+            }
+        },
     )?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pica::RangingEstimator;
+
+    #[test]
+    fn test_estimate_obstacle() {
+        let context = Context::new();
+        context.add_anchor(
+            0,
+            MacAddress::Short([0, 0]),
+            Position::new(0, 0, 0, 0, 0, 0),
+        );
+        context.add_anchor(
+            1,
+            MacAddress::Short([0, 1]),
+            Position::new(1000, 0, 0, 0, 0, 0),
+        );
+        let app_config = pica::AppConfig::default();
+        let ranging_context = pica::RangingContext {
+            session_id: 0,
+            session_type: pica::packets::uci::SessionType::FiraRangingSession,
+            app_config: &app_config,
+            round_index: 0,
+            time: Duration::ZERO,
+        };
+
+        let measurement = context.estimate(&0, &1, &ranging_context).unwrap();
+        assert_eq!(measurement.range, 1000);
+        assert_eq!(measurement.rssi, 150);
+
+        let wall = serde_json::from_value(serde_json::json!({
+            "min": { "x": 400, "y": -100, "z": -100 },
+            "max": { "x": 410, "y": 100, "z": 100 },
+            "material": "brick",
+        }))
+        .unwrap();
+        assert_eq!(context.create_obstacle("wall", wall), HttpStatusCode::OK);
+
+        let measurement = context.estimate(&0, &1, &ranging_context).unwrap();
+        assert!(measurement.nlos);
+        assert_eq!(measurement.range, 1020);
+        assert_eq!(measurement.rssi, 170);
+    }
+}
@@ -0,0 +1,227 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use glam::Vec3;
+use serde::Deserialize;
+
+use crate::trajectory::Point;
+
+/// Total attenuation, in dB, above which two devices cannot range
+/// with each other.
+pub const MAX_LINK_ATTENUATION: f32 = 35.0;
+
+/// Received signal strength at 1 meter in free space, in dBm.
+/// Matches the default noise model of the stochastic estimator.
+pub const RSSI_AT_ONE_METER: f32 = -55.0;
+
+/// Material of an obstacle, defining the default attenuation and
+/// range bias of the signals going through it.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Material {
+    Drywall,
+    Glass,
+    Wood,
+    Brick,
+    Concrete,
+    Metal,
+}
+
+impl Material {
+    /// Attenuation of the signal going through the material, in dB.
+    fn attenuation(&self) -> f32 {
+        match self {
+            Material::Drywall => 3.0,
+            Material::Glass => 4.0,
+            Material::Wood => 5.0,
+            Material::Brick => 10.0,
+            Material::Concrete => 20.0,
+            Material::Metal => 40.0,
+        }
+    }
+
+    /// Delay of the signal going through the material, expressed
+    /// as a range bias in centimeters.
+    fn range_bias(&self) -> f32 {
+        match self {
+            Material::Drywall => 5.0,
+            Material::Glass => 5.0,
+            Material::Wood => 10.0,
+            Material::Brick => 20.0,
+            Material::Concrete => 30.0,
+            Material::Metal => 0.0,
+        }
+    }
+}
+
+/// Wall or obstacle, modelled as an axis aligned box.
+/// The attenuation and range bias of the material can be overridden.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct Obstacle {
+    /// Corner of the box with the lowest coordinates.
+    min: Point,
+    /// Corner of the box with the highest coordinates.
+    max: Point,
+    material: Material,
+    /// Attenuation in dB.
+    attenuation: Option<f32>,
+    /// Range bias in centimeters.
+    range_bias: Option<f32>,
+}
+
+/// Effect of the obstacles crossed by the line of sight
+/// between two devices.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PathLoss {
+    /// True if the line of sight is obstructed.
+    pub nlos: bool,
+    /// Total attenuation, in dB.
+    pub attenuation: f32,
+    /// Total range bias, in centimeters.
+    pub range_bias: f32,
+}
+
+impl Obstacle {
+    /// Returns true if the segment between the two points goes
+    /// through the obstacle.
+    fn intersects(&self, from: Vec3, to: Vec3) -> bool {
+        let min = Vec3::from(self.min);
+        let max = Vec3::from(self.max);
+        let direction = to - from;
+        let (mut t_min, mut t_max) = (0.0f32, 1.0f32);
+
+        for axis in 0..3 {
+            let (origin, delta) = (from[axis], direction[axis]);
+            let (lower, upper) = (min[axis].min(max[axis]), min[axis].max(max[axis]));
+            if delta.abs() < f32::EPSILON {
+                if origin < lower || origin > upper {
+                    return false;
+                }
+            } else {
+                let t1 = (lower - origin) / delta;
+                let t2 = (upper - origin) / delta;
+                t_min = t_min.max(t1.min(t2));
+                t_max = t_max.min(t1.max(t2));
+                if t_min > t_max {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Evaluate the path loss between two points, accumulated over
+/// all the obstacles crossed by the line of sight.
+pub fn path_loss<'a>(
+    obstacles: impl Iterator<Item = &'a Obstacle>,
+    from: Vec3,
+    to: Vec3,
+) -> PathLoss {
+    obstacles
+        .filter(|obstacle| obstacle.intersects(from, to))
+        .fold(PathLoss::default(), |path_loss, obstacle| PathLoss {
+            nlos: true,
+            attenuation: path_loss.attenuation
+                + obstacle
+                    .attenuation
+                    .unwrap_or_else(|| obstacle.material.attenuation()),
+            range_bias: path_loss.range_bias
+                + obstacle
+                    .range_bias
+                    .unwrap_or_else(|| obstacle.material.range_bias()),
+        })
+}
+
+impl PathLoss {
+    /// Returns true if the signal is too attenuated for the devices
+    /// to range with each other.
+    pub fn is_blocked(&self) -> bool {
+        self.attenuation > MAX_LINK_ATTENUATION
+    }
+
+    /// Figure of merit (0-100) of the angle measurements,
+    /// degraded with the attenuation.
+    pub fn figure_of_merit(&self) -> u8 {
+        (100.0 * (1.0 - self.attenuation / MAX_LINK_ATTENUATION))
+            .round()
+            .clamp(0.0, 100.0) as u8
+    }
+
+    /// Received signal strength (-dBm Q7.1) at the selected range in
+    /// centimeters: free-space path loss, plus the attenuation.
+    pub fn rssi(&self, range: u16) -> u8 {
+        let distance_m = (range as f32 / 100.0).max(0.1);
+        let rssi = RSSI_AT_ONE_METER - 20.0 * distance_m.log10() - self.attenuation;
+        // [UCI] The RSSI is reported in -dBm, Q7.1 format.
+        (-2.0 * rssi).round().clamp(0.0, u8::MAX as f32) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wall(material: &str) -> Obstacle {
+        serde_json::from_value(serde_json::json!({
+            "min": { "x": -100, "y": 0, "z": 100 },
+            "max": { "x": 100, "y": 300, "z": 110 },
+            "material": material,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_path_loss() {
+        let brick = wall("brick");
+        let from = Vec3::new(0.0, 100.0, 0.0);
+
+        let loss = path_loss([&brick].into_iter(), from, Vec3::new(0.0, 100.0, 50.0));
+        assert_eq!(loss, PathLoss::default());
+        assert_eq!(loss.figure_of_merit(), 100);
+
+        let loss = path_loss([&brick].into_iter(), from, Vec3::new(50.0, 100.0, 200.0));
+        assert!(loss.nlos);
+        assert_eq!(loss.attenuation, 10.0);
+        assert_eq!(loss.range_bias, 20.0);
+        assert!(!loss.is_blocked());
+
+        // Passing above the wall.
+        let loss = path_loss([&brick].into_iter(), from, Vec3::new(0.0, 1000.0, 200.0));
+        assert!(!loss.nlos);
+
+        let metal = wall("metal");
+        let loss = path_loss(
+            [&brick, &metal].into_iter(),
+            from,
+            Vec3::new(0.0, 100.0, 200.0),
+        );
+        assert_eq!(loss.attenuation, 50.0);
+        assert!(loss.is_blocked());
+        assert_eq!(loss.figure_of_merit(), 0);
+    }
+
+    #[test]
+    fn test_rssi() {
+        let brick = wall("brick");
+        let from = Vec3::new(0.0, 100.0, 0.0);
+
+        // -55 dBm at 1 meter, -75 dBm at 10 meters in free space.
+        assert_eq!(PathLoss::default().rssi(100), 110);
+        assert_eq!(PathLoss::default().rssi(1000), 150);
+
+        let loss = path_loss([&brick].into_iter(), from, Vec3::new(0.0, 100.0, 1000.0));
+        assert_eq!(loss.rssi(1000), 170);
+    }
+}
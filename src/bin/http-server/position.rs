@@ -99,6 +99,27 @@ impl Position {
         }
     }
 
+    /// Coordinates of the device, in centimeters.
+    pub fn point(&self) -> Vec3 {
+        self.position
+    }
+
+    /// Return the same pose moved to the selected coordinates.
+    pub fn with_point(&self, point: Vec3) -> Self {
+        Self {
+            position: point,
+            ..*self
+        }
+    }
+
+    pub fn location(&self) -> pica::Location {
+        pica::Location {
+            x: self.position.x as i32,
+            y: self.position.y as i32,
+            z: self.position.z as i32,
+        }
+    }
+
     pub fn compute_range_azimuth_elevation(&self, other: &Position) -> (u16, i16, i8) {
         let delta = other.position - self.position;
 
@@ -0,0 +1,394 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use anyhow::{Context as _, Result};
+use hyper::StatusCode as HttpStatusCode;
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use pica::{MacAddress, PicaCommand};
+
+use crate::obstacle::Obstacle;
+use crate::position::Position;
+use crate::trajectory::Trajectory;
+use crate::{Context, PositionBody};
+
+/// Emulated world loaded from a JSON scenario file.
+///
+/// ```json
+/// {
+///   "anchors": [
+///     { "mac_address": "00:01", "position": { "x": 100, "y": 0, "z": 0, "yaw": 0, "pitch": 0, "roll": 0 } }
+///   ],
+///   "devices": [
+///     { "mac_address": "00:00:00:00:00:00:00:01",
+///       "trajectory": { "type": "circle", "center": { "x": 0, "y": 0, "z": 0 }, "radius": 300, "period_ms": 10000 } }
+///   ],
+///   "events": [
+///     { "time_ms": 1000, "action": "set-position", "mac_address": "00:01",
+///       "position": { "x": 200, "y": 0, "z": 0, "yaw": 0, "pitch": 0, "roll": 0 } },
+///     { "time_ms": 2000, "action": "block-link", "mac_addresses": ["00:01", "00:00:00:00:00:00:00:01"] },
+///     { "time_ms": 3000, "action": "destroy-anchor", "mac_address": "00:01" }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    /// Anchors created when the scenario starts.
+    #[serde(default)]
+    anchors: Vec<ScenarioDevice>,
+    /// UCI devices expected to connect during the scenario.
+    #[serde(default)]
+    devices: Vec<ScenarioDevice>,
+    /// Walls and obstacles created when the scenario starts.
+    #[serde(default)]
+    obstacles: Vec<ScenarioObstacle>,
+    /// Events played in order of time.
+    #[serde(default)]
+    events: Vec<ScenarioEvent>,
+}
+
+/// Device declared by the scenario, with its initial position and
+/// trajectory. The device is placed at the origin when the position
+/// is omitted, and is static when the trajectory is omitted.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ScenarioDevice {
+    mac_address: MacAddress,
+    position: Option<PositionBody>,
+    trajectory: Option<Trajectory>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScenarioObstacle {
+    name: String,
+    #[serde(flatten)]
+    obstacle: Obstacle,
+}
+
+#[derive(Debug, Deserialize)]
+struct ScenarioEvent {
+    /// Time of the event in milliseconds, measured on the pica clock
+    /// from the start of pica.
+    time_ms: u64,
+    #[serde(flatten)]
+    action: ScenarioAction,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+enum ScenarioAction {
+    /// Move a device. The position of UCI devices that are not yet
+    /// connected is applied when they connect.
+    SetPosition {
+        mac_address: MacAddress,
+        position: PositionBody,
+    },
+    CreateAnchor {
+        mac_address: MacAddress,
+        position: Option<PositionBody>,
+    },
+    DestroyAnchor {
+        mac_address: MacAddress,
+    },
+    /// Start a device on a new trajectory, or stop the device
+    /// when the trajectory is omitted.
+    SetTrajectory {
+        mac_address: MacAddress,
+        trajectory: Option<Trajectory>,
+    },
+    CreateObstacle {
+        name: String,
+        #[serde(flatten)]
+        obstacle: Obstacle,
+    },
+    DestroyObstacle {
+        name: String,
+    },
+    /// Prevent two devices from ranging with each other.
+    BlockLink {
+        mac_addresses: [MacAddress; 2],
+    },
+    UnblockLink {
+        mac_addresses: [MacAddress; 2],
+    },
+}
+
+impl ScenarioDevice {
+    fn position(&self) -> Position {
+        self.position.map(Position::from).unwrap_or_default()
+    }
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Scenario> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read scenario file {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("failed to parse scenario file {}", path.display()))
+    }
+
+    /// Play the scenario against the context: create the anchors, record
+    /// the initial position of the UCI devices, and schedule the events
+    /// on the pica clock.
+    pub async fn play(self, context: Context, cmd_tx: mpsc::Sender<PicaCommand>) -> Result<()> {
+        log::info!("Pica: Playing scenario");
+
+        for device in &self.devices {
+            context.set_initial_position(device.mac_address, device.position());
+        }
+
+        for device in self.anchors.iter().chain(&self.devices) {
+            if let Some(trajectory) = &device.trajectory {
+                context.set_trajectory(device.mac_address, Some(trajectory.clone()));
+            }
+        }
+
+        for obstacle in self.obstacles {
+            let status = context.create_obstacle(&obstacle.name, obstacle.obstacle);
+            if status != HttpStatusCode::OK {
+                anyhow::bail!("failed to create obstacle {}: {}", obstacle.name, status)
+            }
+        }
+
+        for anchor in &self.anchors {
+            let status = context
+                .create_anchor(anchor.mac_address, anchor.position(), cmd_tx.clone())
+                .await;
+            if status != HttpStatusCode::OK {
+                anyhow::bail!("failed to create anchor {}: {}", anchor.mac_address, status)
+            }
+        }
+
+        for event in self.events {
+            let context = context.clone();
+            cmd_tx
+                .send(PicaCommand::Schedule(
+                    Duration::from_millis(event.time_ms),
+                    Box::new(move || event.action.apply(context)),
+                ))
+                .await
+                .map_err(|_| anyhow::anyhow!("pica command stream closed"))?
+        }
+
+        Ok(())
+    }
+}
+
+impl ScenarioAction {
+    /// Apply the action to the context, and return the command to be
+    /// executed by pica, if any.
+    fn apply(self, context: Context) -> Option<PicaCommand> {
+        log::debug!("Scenario event: {:?}", self);
+
+        match self {
+            ScenarioAction::SetPosition {
+                mac_address,
+                position,
+            } => {
+                if !context.set_position(mac_address, position.into()) {
+                    context.set_initial_position(mac_address, position.into())
+                }
+            }
+            ScenarioAction::CreateAnchor {
+                mac_address,
+                position,
+            } => {
+                let position = position.map(Position::from).unwrap_or_default();
+                return Some(context.create_anchor_command(mac_address, position));
+            }
+            ScenarioAction::DestroyAnchor { mac_address } => {
+                return Some(context.destroy_anchor_command(mac_address))
+            }
+            ScenarioAction::SetTrajectory {
+                mac_address,
+                trajectory,
+            } => context.set_trajectory(mac_address, trajectory),
+            ScenarioAction::CreateObstacle { name, obstacle } => {
+                let status = context.create_obstacle(&name, obstacle);
+                if status != HttpStatusCode::OK {
+                    log::error!("Failed to create obstacle {}: {}", name, status)
+                }
+            }
+            ScenarioAction::DestroyObstacle { name } => {
+                let status = context.destroy_obstacle(&name);
+                if status != HttpStatusCode::OK {
+                    log::error!("Failed to destroy obstacle {}: {}", name, status)
+                }
+            }
+            ScenarioAction::BlockLink {
+                mac_addresses: [left, right],
+            } => context.set_link_blocked(left, right, true),
+            ScenarioAction::UnblockLink {
+                mac_addresses: [left, right],
+            } => context.set_link_blocked(left, right, false),
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pica::{ClockMode, Pica};
+    use std::sync::{Arc, Mutex};
+    use tokio::sync::oneshot;
+
+    /// Return whether the selected anchors are present in the context.
+    fn has_anchors(context: &Context, mac_addresses: &[MacAddress]) -> Vec<bool> {
+        context.apply_anchor_updates();
+        let devices = context.devices.lock().unwrap();
+        mac_addresses
+            .iter()
+            .map(|mac_address| {
+                devices
+                    .values()
+                    .any(|device| device.mac_address == *mac_address)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_scenario() {
+        let scenario: Scenario = serde_json::from_str(
+            r#"{
+                "anchors": [
+                    { "mac_address": "00:01",
+                      "position": { "x": 100, "y": 0, "z": 0, "yaw": 90, "pitch": 0, "roll": 0 } }
+                ],
+                "devices": [
+                    { "mac_address": "00:02",
+                      "trajectory": { "type": "line", "velocity": { "x": 10, "y": 0, "z": 0 } } }
+                ],
+                "events": [
+                    { "time_ms": 1000, "action": "set-position", "mac_address": "00:02",
+                      "position": { "x": 0, "y": 200, "z": 0, "yaw": 0, "pitch": 0, "roll": 0 } },
+                    { "time_ms": 2000, "action": "block-link",
+                      "mac_addresses": ["00:01", "00:02"] },
+                    { "time_ms": 3000, "action": "destroy-anchor", "mac_address": "00:01" },
+                    { "time_ms": 4000, "action": "create-obstacle", "name": "door",
+                      "min": { "x": 0, "y": 0, "z": 0 }, "max": { "x": 100, "y": 200, "z": 5 },
+                      "material": "wood" }
+                ],
+                "obstacles": [
+                    { "name": "wall", "min": { "x": -500, "y": 0, "z": 100 },
+                      "max": { "x": 500, "y": 300, "z": 120 }, "material": "concrete",
+                      "attenuation": 15 }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(scenario.anchors.len(), 1);
+        assert_eq!(scenario.devices.len(), 1);
+        assert!(scenario.devices[0].position.is_none());
+        assert!(matches!(
+            scenario.devices[0].trajectory,
+            Some(Trajectory::Line { .. })
+        ));
+        assert_eq!(scenario.obstacles.len(), 1);
+        assert_eq!(scenario.obstacles[0].name, "wall");
+        assert_eq!(scenario.events.len(), 4);
+        assert!(matches!(
+            scenario.events[3].action,
+            ScenarioAction::CreateObstacle { .. }
+        ));
+        assert_eq!(scenario.events[1].time_ms, 2000);
+        assert!(matches!(
+            scenario.events[2].action,
+            ScenarioAction::DestroyAnchor { .. }
+        ));
+
+        assert!(serde_json::from_str::<Scenario>(
+            r#"{ "events": [ { "time_ms": 0, "action": "teleport" } ] }"#
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_play_fast_clock() {
+        let scenario: Scenario = serde_json::from_str(
+            r#"{
+                "events": [
+                    { "time_ms": 1000, "action": "create-anchor", "mac_address": "00:01" },
+                    { "time_ms": 1500, "action": "create-anchor", "mac_address": "00:02" },
+                    { "time_ms": 2000, "action": "destroy-anchor", "mac_address": "00:01" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let anchors = [
+            MacAddress::Short([0x00, 0x01]),
+            MacAddress::Short([0x00, 0x02]),
+        ];
+
+        let context = Context::new();
+        let pica = Pica::new(Box::new(context.clone()), None).with_clock_mode(ClockMode::Fast);
+        let cmd_tx = pica.commands();
+        scenario
+            .play(context.clone(), cmd_tx.clone())
+            .await
+            .unwrap();
+
+        // Sample the anchors present in the context on either side
+        // of the scenario events.
+        let samples = Arc::new(Mutex::new(vec![]));
+        for time_ms in [999, 1001, 1499, 1501, 1999, 2001] {
+            let context = context.clone();
+            let samples = samples.clone();
+            cmd_tx
+                .send(PicaCommand::Schedule(
+                    Duration::from_millis(time_ms),
+                    Box::new(move || {
+                        let present = has_anchors(&context, &anchors);
+                        samples.lock().unwrap().push((time_ms, present));
+                        None
+                    }),
+                ))
+                .await
+                .unwrap();
+        }
+        let (done_tx, done_rx) = oneshot::channel();
+        cmd_tx
+            .send(PicaCommand::Schedule(
+                Duration::from_millis(3000),
+                Box::new(move || {
+                    let _ = done_tx.send(());
+                    None
+                }),
+            ))
+            .await
+            .unwrap();
+
+        // Run pica once all the actions are scheduled, as the fast
+        // clock advances whenever the command queue is empty.
+        tokio::spawn(pica.run());
+        done_rx.await.unwrap();
+
+        assert_eq!(
+            *samples.lock().unwrap(),
+            vec![
+                (999, vec![false, false]),
+                (1001, vec![true, false]),
+                (1499, vec![true, false]),
+                (1501, vec![true, true]),
+                (1999, vec![true, true]),
+                (2001, vec![false, true]),
+            ]
+        );
+    }
+}
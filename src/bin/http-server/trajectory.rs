@@ -0,0 +1,244 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use glam::Vec3;
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::position::Position;
+
+/// Coordinates in the scene, in centimeters.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+pub struct Point {
+    x: f32,
+    y: f32,
+    z: f32,
+}
+
+impl From<Point> for Vec3 {
+    fn from(point: Point) -> Self {
+        Vec3::new(point.x, point.y, point.z)
+    }
+}
+
+/// Motion model followed by a device. The orientation of the device
+/// is preserved, and the trajectory starts from the position of the
+/// device when the motion begins. Speeds are given in centimeters
+/// per second.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Trajectory {
+    /// Move through the waypoints at constant speed. When `repeat` is set
+    /// the device then cycles through the waypoints, otherwise it stops
+    /// at the last waypoint.
+    Waypoints {
+        waypoints: Vec<Point>,
+        speed: f32,
+        #[serde(default)]
+        repeat: bool,
+    },
+    /// Move in a straight line at constant velocity.
+    Line { velocity: Point },
+    /// Move along a horizontal circle of the selected radius around
+    /// the center, with one revolution per period.
+    Circle {
+        center: Point,
+        radius: f32,
+        period_ms: u64,
+    },
+    /// Move in the horizontal plane by a step of the selected length,
+    /// in a random direction, every `step_ms` milliseconds.
+    RandomWalk {
+        step_length: f32,
+        step_ms: u64,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+/// Trajectory being followed by a device.
+pub struct Motion {
+    trajectory: Trajectory,
+    /// Position of the device and time when the motion began,
+    /// set on the first update.
+    start: Option<(Position, Duration)>,
+    /// Random walk state: number of steps taken and current point.
+    walk: (u64, Vec3),
+}
+
+impl Motion {
+    pub fn new(trajectory: Trajectory) -> Self {
+        Motion {
+            trajectory,
+            start: None,
+            walk: (0, Vec3::ZERO),
+        }
+    }
+
+    /// Return the position of the device at the selected time.
+    /// The motion begins from the current position on the first call.
+    pub fn update(&mut self, current: Position, time: Duration) -> Position {
+        let (origin, start) = *self.start.get_or_insert((current, time));
+        let elapsed = time.saturating_sub(start);
+        let point = match &self.trajectory {
+            Trajectory::Waypoints {
+                waypoints,
+                speed,
+                repeat,
+            } => follow_waypoints(
+                origin.point(),
+                waypoints,
+                *repeat,
+                speed * elapsed.as_secs_f32(),
+            ),
+            Trajectory::Line { velocity } => {
+                origin.point() + Vec3::from(*velocity) * elapsed.as_secs_f32()
+            }
+            Trajectory::Circle {
+                center,
+                radius,
+                period_ms,
+            } => {
+                let period = (*period_ms).max(1) as f32 / 1000.0;
+                let angle = std::f32::consts::TAU * elapsed.as_secs_f32() / period;
+                Vec3::from(*center) + Vec3::new(angle.cos(), 0.0, angle.sin()) * *radius
+            }
+            Trajectory::RandomWalk {
+                step_length,
+                step_ms,
+                seed,
+            } => {
+                let steps = elapsed.as_millis() as u64 / (*step_ms).max(1);
+                if self.walk.0 == 0 {
+                    self.walk.1 = origin.point();
+                }
+                while self.walk.0 < steps {
+                    self.walk.0 += 1;
+                    let random = splitmix64(seed.wrapping_add(self.walk.0));
+                    let angle = std::f32::consts::TAU * (random >> 40) as f32 / (1u64 << 24) as f32;
+                    self.walk.1 += Vec3::new(angle.cos(), 0.0, angle.sin()) * *step_length;
+                }
+                self.walk.1
+            }
+        };
+        origin.with_point(point)
+    }
+}
+
+/// Return the point reached after travelling the selected distance
+/// from the origin through the waypoints.
+fn follow_waypoints(origin: Vec3, waypoints: &[Point], repeat: bool, distance: f32) -> Vec3 {
+    let waypoints: Vec<Vec3> = waypoints.iter().copied().map(Vec3::from).collect();
+    let Some(&last) = waypoints.last() else {
+        return origin;
+    };
+
+    let remaining = match travel(origin, &waypoints, distance) {
+        Ok(point) => return point,
+        Err(remaining) => remaining,
+    };
+
+    // Cycle through the waypoints, starting from the last one.
+    let cycle_length: f32 = waypoints
+        .iter()
+        .scan(last, |from, &to| {
+            Some(std::mem::replace(from, to).distance(to))
+        })
+        .sum();
+    if !repeat || cycle_length == 0.0 {
+        return last;
+    }
+    travel(last, &waypoints, remaining % cycle_length).unwrap_or(last)
+}
+
+/// Travel the selected distance along the path going from the start
+/// through the points. Returns the point reached, or the distance
+/// remaining at the end of the path.
+fn travel(mut from: Vec3, points: &[Vec3], mut distance: f32) -> Result<Vec3, f32> {
+    for &to in points {
+        let length = from.distance(to);
+        if distance < length {
+            return Ok(from.lerp(to, distance / length));
+        }
+        distance -= length;
+        from = to;
+    }
+    Err(distance)
+}
+
+/// SplitMix64 hash function.
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(x: f32, y: f32, z: f32) -> Point {
+        Point { x, y, z }
+    }
+
+    fn update(motion: &mut Motion, time_ms: u64) -> Vec3 {
+        motion
+            .update(Position::default(), Duration::from_millis(time_ms))
+            .point()
+    }
+
+    #[test]
+    fn test_waypoints() {
+        let mut motion = Motion::new(Trajectory::Waypoints {
+            waypoints: vec![point(100.0, 0.0, 0.0), point(100.0, 0.0, 100.0)],
+            speed: 100.0,
+            repeat: true,
+        });
+        assert_eq!(update(&mut motion, 0), Vec3::ZERO);
+        assert!(update(&mut motion, 500).abs_diff_eq(Vec3::new(50.0, 0.0, 0.0), 1e-3));
+        assert!(update(&mut motion, 1500).abs_diff_eq(Vec3::new(100.0, 0.0, 50.0), 1e-3));
+        assert!(update(&mut motion, 2500).abs_diff_eq(Vec3::new(100.0, 0.0, 50.0), 1e-3));
+        assert!(update(&mut motion, 3000).abs_diff_eq(Vec3::new(100.0, 0.0, 0.0), 1e-3));
+    }
+
+    #[test]
+    fn test_circle() {
+        let mut motion = Motion::new(Trajectory::Circle {
+            center: point(0.0, 0.0, 0.0),
+            radius: 100.0,
+            period_ms: 4000,
+        });
+        assert!(update(&mut motion, 0).abs_diff_eq(Vec3::new(100.0, 0.0, 0.0), 1e-3));
+        assert!(update(&mut motion, 1000).abs_diff_eq(Vec3::new(0.0, 0.0, 100.0), 1e-3));
+    }
+
+    #[test]
+    fn test_random_walk() {
+        let trajectory = Trajectory::RandomWalk {
+            step_length: 10.0,
+            step_ms: 100,
+            seed: 42,
+        };
+        let mut a = Motion::new(trajectory.clone());
+        let mut b = Motion::new(trajectory);
+        update(&mut a, 0);
+        update(&mut b, 0);
+        update(&mut a, 500);
+        let point = update(&mut a, 1000);
+        assert_eq!(point, update(&mut b, 1000));
+        assert!(point.length() <= 100.0 + 1e-3);
+        assert!(point != Vec3::ZERO);
+    }
+}
@@ -15,15 +15,19 @@
 use anyhow::Result;
 use clap::Parser;
 use env_logger::Env;
-use pica::{Pica, PicaCommand};
+use pica::{
+    ClockMode, ConnectionHint, DeviceDeclaration, DeviceProfile, LinkBudget, Pica, PicaCommand,
+};
 use std::net::{Ipv4Addr, SocketAddrV4};
 use std::path::PathBuf;
 use tokio::net::TcpListener;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 use tokio::try_join;
 
 const DEFAULT_UCI_PORT: u16 = 7000;
 
+/// Accept the UCI connections on the selected port. The connections
+/// are assigned the MAC address of the devices declared on this port.
 async fn accept_incoming(cmd_tx: mpsc::Sender<PicaCommand>, uci_port: u16) -> Result<()> {
     let uci_socket = SocketAddrV4::new(Ipv4Addr::LOCALHOST, uci_port);
     let uci_listener = TcpListener::bind(uci_socket).await?;
@@ -38,7 +42,11 @@ async fn accept_incoming(cmd_tx: mpsc::Sender<PicaCommand>, uci_port: u16) -> Re
         let sink = Box::pin(futures::sink::unfold(write_half, pica::packets::uci::write));
 
         cmd_tx
-            .send(PicaCommand::Connect(stream, sink))
+            .send(PicaCommand::ConnectWithHint(
+                stream,
+                sink,
+                ConnectionHint::Port(uci_port),
+            ))
             .await
             .map_err(|_| anyhow::anyhow!("pica command stream closed"))?
     }
@@ -55,6 +63,71 @@ struct Args {
     /// Configure the TCP port for the UCI server.
     #[arg(short, long, value_name = "UCI_PORT", default_value_t = DEFAULT_UCI_PORT)]
     uci_port: u16,
+    /// Select how time elapses in the emulation. The virtual clock
+    /// is stepped by entering a new line on the standard input
+    /// in `step` mode.
+    #[arg(long, value_enum, default_value_t = ClockMode::RealTime)]
+    clock: ClockMode,
+    /// Limit the range at which devices can measure each other with
+    /// the link budget defined in a JSON file.
+    #[arg(long, value_name = "FILE")]
+    link_budget: Option<PathBuf>,
+    /// Load the named capability profiles of the UCI devices from
+    /// a JSON file.
+    #[arg(long, value_name = "FILE")]
+    device_profiles: Option<PathBuf>,
+    /// Declare a UCI device with a fixed MAC address, optionally bound
+    /// to a dedicated UCI port and assigned a capability profile.
+    /// Can be repeated.
+    #[arg(long = "device", value_name = "MAC[@PORT][=PROFILE]")]
+    devices: Vec<DeviceDeclaration>,
+    /// Limit the number of connected UCI devices.
+    /// The number of devices is not limited by default.
+    #[arg(long, value_name = "COUNT")]
+    max_device: Option<usize>,
+    /// Maximum number of sessions per UCI device.
+    #[arg(long, value_name = "COUNT", default_value_t = 255)]
+    max_session: usize,
+    /// Assign extended MAC addresses to the undeclared UCI devices,
+    /// instead of short MAC addresses.
+    #[arg(long)]
+    extended_mac_address: bool,
+}
+
+/// Return the UCI ports to listen on: the main port, and the ports
+/// of the declared devices.
+fn uci_ports(args: &Args) -> Vec<u16> {
+    let mut ports = vec![args.uci_port];
+    for declaration in &args.devices {
+        if let Some(ConnectionHint::Port(port)) = declaration.hint {
+            if !ports.contains(&port) {
+                ports.push(port)
+            }
+        }
+    }
+    ports
+}
+
+/// Step the virtual clock for each line read from the standard input.
+async fn step_on_input(cmd_tx: mpsc::Sender<PicaCommand>) -> Result<()> {
+    let (line_tx, mut line_rx) = mpsc::channel(1);
+    std::thread::spawn(move || {
+        for _ in std::io::stdin().lines() {
+            if line_tx.blocking_send(()).is_err() {
+                break;
+            }
+        }
+    });
+
+    while line_rx.recv().await.is_some() {
+        let (rsp_tx, rsp_rx) = oneshot::channel();
+        cmd_tx
+            .send(PicaCommand::Step(rsp_tx))
+            .await
+            .map_err(|_| anyhow::anyhow!("pica command stream closed"))?;
+        log::info!("Pica: Virtual time {:?}", rsp_rx.await?);
+    }
+    Ok(())
 }
 
 struct MockRangingEstimator();
@@ -67,6 +140,7 @@ impl pica::RangingEstimator for MockRangingEstimator {
         &self,
         _left: &pica::Handle,
         _right: &pica::Handle,
+        _context: &pica::RangingContext,
     ) -> Option<pica::RangingMeasurement> {
         Some(Default::default())
     }
@@ -78,10 +152,39 @@ async fn main() -> Result<()> {
 
     let args = Args::parse();
 
-    let pica = Pica::new(Box::new(MockRangingEstimator()), args.pcapng_dir);
+    let uci_ports = uci_ports(&args);
+    let device_profiles = args
+        .device_profiles
+        .as_deref()
+        .map(DeviceProfile::load)
+        .transpose()?
+        .unwrap_or_default();
+    let mut pica = Pica::new(Box::new(MockRangingEstimator()), args.pcapng_dir)
+        .with_clock_mode(args.clock)
+        .with_max_session(args.max_session)
+        .with_device_profiles(device_profiles)
+        .with_devices(args.devices)?;
+    if let Some(max_device) = args.max_device {
+        pica = pica.with_max_device(max_device);
+    }
+    if args.extended_mac_address {
+        pica = pica.with_extended_mac_addresses();
+    }
+    if let Some(path) = args.link_budget {
+        pica = pica.with_link_budget(LinkBudget::load(&path)?);
+    }
     let commands = pica.commands();
+    let accept_incoming = futures::future::try_join_all(
+        uci_ports
+            .into_iter()
+            .map(|uci_port| accept_incoming(commands.clone(), uci_port)),
+    );
 
-    try_join!(accept_incoming(commands.clone(), args.uci_port), pica.run(),)?;
+    if args.clock == ClockMode::Step {
+        try_join!(accept_incoming, step_on_input(commands.clone()), pica.run(),)?;
+    } else {
+        try_join!(accept_incoming, pica.run(),)?;
+    }
 
     Ok(())
 }
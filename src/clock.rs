@@ -0,0 +1,287 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::PicaCommand;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time;
+
+/// Selects how time elapses in the emulation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ClockMode {
+    /// Timers expire on wall-clock time.
+    #[default]
+    RealTime,
+    /// Virtual time, advanced to the next timer expiration as soon
+    /// as all pending commands have been processed.
+    Fast,
+    /// Virtual time, advanced to the next timer expiration
+    /// on each [`PicaCommand::Step`] command.
+    Step,
+}
+
+/// Action invoked on timer expiration, returning the command
+/// to be executed by pica, if any.
+pub(crate) type TimerAction = Box<dyn FnMut() -> Option<PicaCommand> + Send>;
+
+struct VirtualTimer {
+    period: Option<Duration>,
+    action: TimerAction,
+}
+
+#[derive(Default)]
+struct VirtualState {
+    now: Duration,
+    next_id: u64,
+    /// Pending timer expirations, ordered by deadline and creation.
+    queue: BTreeSet<(Duration, u64)>,
+    timers: HashMap<u64, VirtualTimer>,
+}
+
+/// Clock shared by the pica context, devices and sessions,
+/// scheduling the ranging rounds and tests.
+#[derive(Clone)]
+pub(crate) struct Clock {
+    mode: ClockMode,
+    start: Instant,
+    pica_tx: mpsc::Sender<PicaCommand>,
+    state: Arc<Mutex<VirtualState>>,
+}
+
+/// Handle to a scheduled timer.
+pub(crate) struct Timer(TimerKind);
+
+enum TimerKind {
+    Task(JoinHandle<()>),
+    Virtual(u64, Arc<Mutex<VirtualState>>),
+}
+
+impl Timer {
+    /// Cancel the timer. The action is not invoked after this call.
+    pub fn cancel(self) {
+        match self.0 {
+            TimerKind::Task(handle) => handle.abort(),
+            TimerKind::Virtual(id, state) => {
+                state.lock().unwrap().timers.remove(&id);
+            }
+        }
+    }
+}
+
+impl Clock {
+    pub fn new(mode: ClockMode, pica_tx: mpsc::Sender<PicaCommand>) -> Self {
+        Clock {
+            mode,
+            start: Instant::now(),
+            pica_tx,
+            state: Default::default(),
+        }
+    }
+
+    pub fn mode(&self) -> ClockMode {
+        self.mode
+    }
+
+    /// Time elapsed since the creation of the clock.
+    pub fn now(&self) -> Duration {
+        match self.mode {
+            ClockMode::RealTime => self.start.elapsed(),
+            ClockMode::Fast | ClockMode::Step => self.state.lock().unwrap().now,
+        }
+    }
+
+    /// Schedule the action to be invoked after the selected delay,
+    /// then periodically if a period is provided. The command returned
+    /// by the action is executed by pica.
+    pub fn schedule(
+        &self,
+        delay: Duration,
+        period: Option<Duration>,
+        mut action: TimerAction,
+    ) -> Timer {
+        match self.mode {
+            ClockMode::RealTime => {
+                let pica_tx = self.pica_tx.clone();
+                Timer(TimerKind::Task(tokio::spawn(async move {
+                    time::sleep(delay).await;
+                    loop {
+                        if let Some(command) = action() {
+                            if pica_tx.send(command).await.is_err() {
+                                break;
+                            }
+                        }
+                        match period {
+                            Some(period) => time::sleep(period).await,
+                            None => break,
+                        }
+                    }
+                })))
+            }
+            ClockMode::Fast | ClockMode::Step => {
+                let mut state = self.state.lock().unwrap();
+                let id = state.next_id;
+                let deadline = state.now + delay;
+                state.next_id += 1;
+                state.queue.insert((deadline, id));
+                state.timers.insert(id, VirtualTimer { period, action });
+                Timer(TimerKind::Virtual(id, self.state.clone()))
+            }
+        }
+    }
+
+    /// Advance the virtual time to the next timer expiration, and return
+    /// the commands of all the timers expiring at this time, or `None`
+    /// if no timer is pending. Periodic timers are rescheduled.
+    pub fn advance(&self) -> Option<Vec<PicaCommand>> {
+        let mut state = self.state.lock().unwrap();
+
+        // Drop the cancelled timers at the head of the queue.
+        while let Some(&(_, id)) = state.queue.first() {
+            if state.timers.contains_key(&id) {
+                break;
+            }
+            state.queue.pop_first();
+        }
+
+        let &(deadline, _) = state.queue.first()?;
+
+        let mut expired = vec![];
+        while let Some(&(next_deadline, id)) = state.queue.first() {
+            if next_deadline != deadline {
+                break;
+            }
+            state.queue.pop_first();
+            expired.push(id);
+        }
+
+        state.now = deadline;
+        let mut commands = vec![];
+        for id in expired {
+            let Some(mut timer) = state.timers.remove(&id) else {
+                continue;
+            };
+            commands.extend((timer.action)());
+            if let Some(period) = timer.period {
+                state.queue.insert((deadline + period, id));
+                state.timers.insert(id, timer);
+            }
+        }
+        Some(commands)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a clock in the selected mode, and a log of the expired
+    /// timers.
+    fn clock(mode: ClockMode) -> (Clock, Arc<Mutex<Vec<u32>>>) {
+        let (pica_tx, _) = mpsc::channel(1);
+        (Clock::new(mode, pica_tx), Default::default())
+    }
+
+    /// Schedule a timer recording its identifier on expiration.
+    fn schedule(
+        clock: &Clock,
+        log: &Arc<Mutex<Vec<u32>>>,
+        id: u32,
+        delay_ms: u64,
+        period_ms: Option<u64>,
+    ) -> Timer {
+        let log = log.clone();
+        clock.schedule(
+            Duration::from_millis(delay_ms),
+            period_ms.map(Duration::from_millis),
+            Box::new(move || {
+                log.lock().unwrap().push(id);
+                None
+            }),
+        )
+    }
+
+    #[test]
+    fn test_fast_ordering() {
+        let (clock, log) = clock(ClockMode::Fast);
+        let _timers = [
+            schedule(&clock, &log, 0, 30, None),
+            schedule(&clock, &log, 1, 10, None),
+            schedule(&clock, &log, 2, 20, None),
+            schedule(&clock, &log, 3, 10, None),
+        ];
+
+        // Timers expire by deadline, then by creation order.
+        while clock.advance().is_some() {}
+        assert_eq!(*log.lock().unwrap(), vec![1, 3, 2, 0]);
+        assert_eq!(clock.now(), Duration::from_millis(30));
+    }
+
+    #[test]
+    fn test_periodic() {
+        let (clock, log) = clock(ClockMode::Fast);
+        let _periodic = schedule(&clock, &log, 0, 10, Some(20));
+        let _timer = schedule(&clock, &log, 1, 40, None);
+
+        for now_ms in [10, 30, 40, 50, 70] {
+            assert!(clock.advance().is_some());
+            assert_eq!(clock.now(), Duration::from_millis(now_ms));
+        }
+        assert_eq!(*log.lock().unwrap(), vec![0, 0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let (clock, log) = clock(ClockMode::Fast);
+        let periodic = schedule(&clock, &log, 0, 10, Some(10));
+        let timer = schedule(&clock, &log, 1, 5, None);
+        let _timer = schedule(&clock, &log, 2, 25, None);
+
+        timer.cancel();
+        assert!(clock.advance().is_some());
+        assert!(clock.advance().is_some());
+        periodic.cancel();
+
+        // The cancelled timers are skipped.
+        assert!(clock.advance().is_some());
+        assert_eq!(clock.now(), Duration::from_millis(25));
+        assert!(clock.advance().is_none());
+        assert_eq!(*log.lock().unwrap(), vec![0, 0, 2]);
+    }
+
+    #[test]
+    fn test_step() {
+        let (clock, log) = clock(ClockMode::Step);
+        let _timers = [
+            schedule(&clock, &log, 0, 10, None),
+            schedule(&clock, &log, 1, 10, None),
+            schedule(&clock, &log, 2, 20, None),
+        ];
+
+        // Each step expires all the timers of the next deadline only.
+        assert!(clock.advance().is_some());
+        assert_eq!(clock.now(), Duration::from_millis(10));
+        assert_eq!(*log.lock().unwrap(), vec![0, 1]);
+
+        assert!(clock.advance().is_some());
+        assert_eq!(clock.now(), Duration::from_millis(20));
+        assert_eq!(*log.lock().unwrap(), vec![0, 1, 2]);
+
+        // The time does not elapse without pending timers.
+        assert!(clock.advance().is_none());
+        assert_eq!(clock.now(), Duration::from_millis(20));
+    }
+}
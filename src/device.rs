@@ -17,23 +17,25 @@ use crate::MacAddress;
 use crate::PicaCommand;
 
 use std::collections::HashMap;
-use std::time::Duration;
+use std::sync::Arc;
 
 use pdl_runtime::Packet;
 use tokio::sync::mpsc;
 use tokio::time;
 
 use super::app_config::SubSessionKey;
+use super::clock::Clock;
+use super::device_profile::{DeviceProfile, UciVersion};
+use super::fault_injection::FaultInjector;
+use super::outbound_queue::OutboundQueue;
 use super::session::Session;
+use super::stochastic_estimator::Rng;
+use super::test_mode::Test;
+use super::uwbs_clock::UwbsClock;
 use super::UciPacket;
 
-pub const MAX_DEVICE: usize = 4;
-pub const MAX_SESSION: usize = 255;
-
-const UCI_VERSION: u16 = 0x0002; // Version 2.0
-const MAC_VERSION: u16 = 0x3001; // Version 1.3.0
-const PHY_VERSION: u16 = 0x3001; // Version 1.3.0
-const TEST_VERSION: u16 = 0x1001; // Version 1.1
+/// Default maximum number of sessions per device.
+pub const DEFAULT_MAX_SESSION: usize = 255;
 
 /// cf. [UCI] 8.3 Table 29
 pub const MAX_NUMBER_OF_CONTROLEES: usize = 8;
@@ -46,13 +48,13 @@ pub const DEFAULT_CAPS_INFO: &[(CapTlvType, &[u8])] = &[
     // Fira params
     (CapTlvType::SupportedFiraPhyVersionRange, &[1, 1, 1, 3]), // 1.1 - 1.3
     (CapTlvType::SupportedFiraMacVersionRange, &[1, 1, 1, 3]), // 1.1 - 1.3
-    (CapTlvType::SupportedDeviceRoles, &[0x3]),                // INTIATOR | RESPONDER
-    (CapTlvType::SupportedRangingMethod, &[0x1f]), // DS_TWR_NON_DEFERRED | SS_TWR_NON_DEFERRED | DS_TWR_DEFERRED | SS_TWR_DEFERRED | OWR
+    (CapTlvType::SupportedDeviceRoles, &[0xe3, 0x01]), // INITIATOR | RESPONDER | ADVERTISER | OBSERVER | DT_ANCHOR | DT_TAG
+    (CapTlvType::SupportedRangingMethod, &[0xff, 0x01]), // OWR_UL_TDOA | SS_TWR_DEFERRED | DS_TWR_DEFERRED | SS_TWR_NON_DEFERRED | DS_TWR_NON_DEFERRED | OWR_DL_TDOA | OWR_AOA | ESS_TWR_NON_DEFERRED | ADS_TWR_NON_DEFERRED
     (CapTlvType::SupportedStsConfig, &[0x1f]), // STATIC_STS | DYNAMIC_STS | DYNAMIC_STS_RESPONDER_SPECIFIC_SUBSESSION_KEY | PROVISIONED_STS | PROVISIONED_STS_RESPONDER_SPECIFIC_SUBSESSION_KEY
     (CapTlvType::SupportedMultiNodeModes, &[0xff]),
     (CapTlvType::SupportedRangingTimeStruct, &[0x01]), // Block Based Scheduling (default)
     (CapTlvType::SupportedScheduledMode, &[0x01]),     // Time scheduled ranging (default)
-    (CapTlvType::SupportedHoppingMode, &[0x00]),       // Hopping disable
+    (CapTlvType::SupportedHoppingMode, &[0x01]),       // Hopping enable
     (CapTlvType::SupportedBlockStriding, &[0x1]),
     (CapTlvType::SupportedUwbInitiationTime, &[0x01]),
     (CapTlvType::SupportedChannels, &[0xff]),
@@ -109,19 +111,36 @@ pub struct Device {
     config: DeviceConfig,
     /// [UCI] 5. UWBS Device State Machine
     state: DeviceState,
+    /// Sessions indexed by session handle.
     sessions: HashMap<u32, Session>,
-    pub tx: mpsc::UnboundedSender<UciPacket>,
+    /// Generator for the session handles returned in SESSION_INIT_RSP.
+    session_handles: Rng,
+    pub tx: OutboundQueue,
+    /// Faults injected on the UCI transport of the device.
+    pub faults: FaultInjector,
     pica_tx: mpsc::Sender<PicaCommand>,
+    clock: Clock,
+    /// Emulated UWBS clock, derived from the pica clock.
+    pub uwbs_clock: UwbsClock,
     country_code: [u8; 2],
     pub n_active_sessions: usize,
+    /// Maximum number of sessions, reported in the capabilities.
+    max_session: usize,
+    /// Versions and capabilities reported by the device.
+    profile: Arc<DeviceProfile>,
 }
 
 impl Device {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         handle: usize,
         mac_address: MacAddress,
-        tx: mpsc::UnboundedSender<UciPacket>,
+        tx: OutboundQueue,
+        faults: FaultInjector,
         pica_tx: mpsc::Sender<PicaCommand>,
+        clock: Clock,
+        max_session: usize,
+        profile: Arc<DeviceProfile>,
     ) -> Self {
         Device {
             handle,
@@ -130,10 +149,16 @@ impl Device {
             config: Default::default(),
             state: DeviceState::DeviceStateError, // Will be overwitten
             sessions: Default::default(),
+            session_handles: Rng(handle as u64),
             tx,
+            faults,
             pica_tx,
+            clock,
+            uwbs_clock: Default::default(),
             country_code: Default::default(),
             n_active_sessions: 0,
+            max_session,
+            profile,
         }
     }
 
@@ -145,44 +170,91 @@ impl Device {
 
         // Send status notification
         self.state = device_state;
-        let tx = self.tx.clone();
-        tokio::spawn(async move {
-            time::sleep(Duration::from_millis(5)).await;
-            tx.send(
-                CoreDeviceStatusNtf { device_state }
-                    .encode_to_vec()
-                    .unwrap(),
-            )
-            .unwrap()
-        });
+        let _ = self.tx.send(
+            CoreDeviceStatusNtf { device_state }
+                .encode_to_vec()
+                .unwrap(),
+        );
     }
 
     pub fn init(&mut self) {
         self.set_state(DeviceState::DeviceStateReady);
     }
 
-    pub fn session(&self, session_id: u32) -> Option<&Session> {
-        self.sessions.get(&session_id)
+    /// Simulate an internal error of the UWBS. The ranging tasks and tests
+    /// are stopped without notifying the sessions, and the commands are
+    /// rejected until the host resets the device.
+    pub fn set_error_state(&mut self) {
+        log::debug!("[{}] Error state", self.handle);
+
+        for session in self.sessions.values_mut() {
+            session.stop_ranging_task();
+            session.test = None;
+            if session.state == SessionState::SessionStateActive {
+                session.state = SessionState::SessionStateIdle;
+            }
+        }
+        self.n_active_sessions = 0;
+        self.set_state(DeviceState::DeviceStateError);
+    }
+
+    pub fn session(&self, session_handle: u32) -> Option<&Session> {
+        self.sessions.get(&session_handle)
     }
 
-    pub fn session_mut(&mut self, session_id: u32) -> Option<&mut Session> {
-        self.sessions.get_mut(&session_id)
+    /// Return the session with the selected session ID. Peer devices
+    /// are matched by session ID, the session handles are local
+    /// to each device.
+    pub fn session_by_id(&self, session_id: u32) -> Option<&Session> {
+        self.sessions
+            .values()
+            .find(|session| session.id == session_id)
+    }
+
+    pub fn sessions(&self) -> impl Iterator<Item = &Session> {
+        self.sessions.values()
+    }
+
+    pub fn session_mut(&mut self, session_handle: u32) -> Option<&mut Session> {
+        self.sessions.get_mut(&session_handle)
+    }
+
+    /// Stop the ranging task of an active session, and move the session
+    /// to the IDLE state with the selected reason code.
+    pub fn stop_ranging(&mut self, session_handle: u32, reason_code: ReasonCode) {
+        let Some(session) = self.sessions.get_mut(&session_handle) else {
+            return;
+        };
+
+        session.stop_ranging_task();
+        session.set_state(SessionState::SessionStateIdle, reason_code);
+
+        self.n_active_sessions = self.n_active_sessions.saturating_sub(1);
+        if self.n_active_sessions == 0 {
+            self.set_state(DeviceState::DeviceStateReady);
+        }
     }
 
-    pub fn can_start_ranging(&self, peer_session: &Session, session_id: u32) -> bool {
-        match self.session(session_id) {
+    pub fn can_start_ranging(&self, peer_session: &Session) -> bool {
+        match self.session_by_id(peer_session.id) {
             Some(session) => {
                 session.session_state() == SessionState::SessionStateActive
-                    && session
-                        .app_config
-                        .is_compatible_for_ranging(&peer_session.app_config)
+                    && match (session.session_type, peer_session.session_type) {
+                        (SessionType::Ccc, SessionType::Ccc) => session
+                            .app_config
+                            .is_compatible_for_ccc_ranging(&peer_session.app_config),
+                        (SessionType::Ccc, _) | (_, SessionType::Ccc) => false,
+                        _ => session
+                            .app_config
+                            .is_compatible_for_ranging(&peer_session.app_config),
+                    }
             }
             None => false,
         }
     }
 
-    pub fn can_start_data_transfer(&self, session_id: u32) -> bool {
-        match self.session(session_id) {
+    pub fn can_start_data_transfer(&self, session_handle: u32) -> bool {
+        match self.session(session_handle) {
             Some(session) => {
                 session.session_state() == SessionState::SessionStateActive
                     && session.session_type() == SessionType::FiraRangingAndInBandDataSession
@@ -193,7 +265,7 @@ impl Device {
     }
 
     pub fn can_receive_data_transfer(&self, session_id: u32) -> bool {
-        match self.session(session_id) {
+        match self.session_by_id(session_id) {
             Some(session) => {
                 session.session_state() == SessionState::SessionStateActive
                     && session.session_type() == SessionType::FiraRangingAndInBandDataSession
@@ -224,7 +296,11 @@ impl Device {
             self.handle,
             self.mac_address,
             self.tx.clone(),
+            self.faults.clone(),
             self.pica_tx.clone(),
+            self.clock.clone(),
+            self.max_session,
+            self.profile.clone(),
         );
         self.is_reset = true;
         self.init();
@@ -240,28 +316,48 @@ impl Device {
         assert_eq!(self.state, DeviceState::DeviceStateReady);
         CoreGetDeviceInfoRsp {
             status: uci::Status::Ok,
-            uci_version: UCI_VERSION,
-            mac_version: MAC_VERSION,
-            phy_version: PHY_VERSION,
-            uci_test_version: TEST_VERSION,
-            vendor_spec_info: Vec::new(),
+            uci_version: self.profile.uci_version.version(),
+            mac_version: self.profile.mac_version,
+            phy_version: self.profile.phy_version,
+            uci_test_version: self.profile.test_version,
+            vendor_spec_info: self.profile.vendor_spec_info.clone(),
+        }
+    }
+
+    /// Return the capabilities of the device: the capabilities
+    /// of the device profile, completed with the session limits.
+    fn caps(&self) -> Vec<CapTlv> {
+        let mut caps: Vec<CapTlv> = self.profile.caps();
+        if !self
+            .profile
+            .has_cap(CapTlvType::CccSupportedMaxRangingSessionNumber)
+        {
+            caps.push(CapTlv {
+                t: CapTlvType::CccSupportedMaxRangingSessionNumber,
+                v: vec![self.max_session.min(u8::MAX as usize) as u8],
+            });
+        }
+        if !self
+            .profile
+            .has_cap(CapTlvType::SupportedMaxRangingSessionNumber)
+        {
+            caps.push(CapTlv {
+                t: CapTlvType::SupportedMaxRangingSessionNumber,
+                v: (self.max_session.min(u32::MAX as usize) as u32)
+                    .to_le_bytes()
+                    .to_vec(),
+            });
         }
+
+        caps
     }
 
     pub fn core_get_caps_info(&self, _cmd: CoreGetCapsInfoCmd) -> CoreGetCapsInfoRsp {
         log::debug!("[{}] GetCapsInfo", self.handle);
 
-        let caps = DEFAULT_CAPS_INFO
-            .iter()
-            .map(|(id, value)| CapTlv {
-                t: *id,
-                v: (*value).into(),
-            })
-            .collect();
-
         CoreGetCapsInfoRsp {
             status: uci::Status::Ok,
-            tlvs: caps,
+            tlvs: self.caps(),
         }
     }
 
@@ -348,7 +444,35 @@ impl Device {
         }
     }
 
-    fn session_init(&mut self, cmd: SessionInitCmd) -> SessionInitRsp {
+    /// Return the current UWBS time, in microseconds.
+    pub fn uwbs_timestamp(&self) -> u64 {
+        self.uwbs_clock.timestamp(self.clock.now())
+    }
+
+    fn core_query_uwbs_timestamp(&self, _cmd: CoreQueryTimeStampCmd) -> CoreQueryTimeStampRsp {
+        log::debug!("[{}] Query UWBS timestamp", self.handle);
+
+        CoreQueryTimeStampRsp {
+            status: uci::Status::Ok,
+            timestamp: self.uwbs_timestamp(),
+        }
+    }
+
+    /// Generate a new session handle. [UCI 1.1] devices use the session ID
+    /// as session handle; [UCI 2.0] devices generate unique non-zero handles.
+    fn new_session_handle(&mut self, session_id: u32) -> u32 {
+        if self.profile.uci_version == UciVersion::V1_1 {
+            return session_id;
+        }
+        loop {
+            let session_handle = self.session_handles.next_u64() as u32;
+            if session_handle != 0 && !self.sessions.contains_key(&session_handle) {
+                return session_handle;
+            }
+        }
+    }
+
+    fn session_init(&mut self, cmd: SessionInitCmd) -> ControlPacket {
         let session_id = cmd.session_id;
         let session_type = cmd.session_type;
 
@@ -356,31 +480,51 @@ impl Device {
         log::debug!("  session_id=0x{:x}", session_id);
         log::debug!("  session_type={:?}", session_type);
 
-        let status = if self.sessions.len() >= MAX_SESSION {
-            uci::Status::ErrorMaxSessionsExceeded
+        let (status, session_handle) = if self.sessions.len() >= self.max_session {
+            (uci::Status::ErrorMaxSessionsExceeded, 0)
+        } else if self.session_by_id(session_id).is_some() {
+            (uci::Status::ErrorSessionDuplicate, 0)
+        } else if session_type == SessionType::DeviceTestMode
+            && self
+                .sessions
+                .values()
+                .any(|session| session.session_type == SessionType::DeviceTestMode)
+        {
+            // The test commands do not identify the session, only one
+            // session of type DEVICE_TEST_MODE can exist at a time.
+            (uci::Status::Rejected, 0)
         } else {
-            match self.sessions.insert(
+            let session_handle = self.new_session_handle(session_id);
+            let mut session = Session::new(
                 session_id,
-                Session::new(session_id, session_type, self.handle, self.tx.clone()),
-            ) {
-                Some(_) => uci::Status::ErrorSessionDuplicate,
-                None => {
-                    // Should not fail
-                    self.session_mut(session_id).unwrap().init();
-                    uci::Status::Ok
-                }
-            }
+                session_handle,
+                session_type,
+                self.handle,
+                self.tx.clone(),
+            );
+            session.init();
+            self.sessions.insert(session_handle, session);
+            log::debug!("  session_handle=0x{:x}", session_handle);
+            (uci::Status::Ok, session_handle)
         };
 
-        SessionInitRsp { status }
+        match self.profile.uci_version {
+            UciVersion::V1_1 => SessionInitRsp { status }.try_into().unwrap(),
+            UciVersion::V2_0 => SessionInitRsp_V2 {
+                status,
+                session_handle,
+            }
+            .try_into()
+            .unwrap(),
+        }
     }
 
     fn session_deinit(&mut self, cmd: SessionDeinitCmd) -> SessionDeinitRsp {
-        let session_id = cmd.session_token;
+        let session_handle = cmd.session_token;
         log::debug!("[{}] Session deinit", self.handle);
-        log::debug!("  session_id=0x{:x}", session_id);
+        log::debug!("  session_handle=0x{:x}", session_handle);
 
-        let status = match self.sessions.get_mut(&session_id) {
+        let status = match self.sessions.get_mut(&session_handle) {
             Some(session) => {
                 if session.state == SessionState::SessionStateActive {
                     self.n_active_sessions -= 1;
@@ -388,7 +532,7 @@ impl Device {
                         self.set_state(DeviceState::DeviceStateReady);
                     }
                 }
-                self.sessions.remove(&session_id);
+                self.sessions.remove(&session_handle);
                 uci::Status::Ok
             }
             None => uci::Status::ErrorSessionNotExist,
@@ -414,6 +558,7 @@ impl Device {
             session_handle
         );
 
+        let caps = self.caps();
         let Some(session) = self.sessions.get_mut(&session_handle) else {
             return SessionSetAppConfigRsp {
                 cfg_status: Vec::new(),
@@ -424,6 +569,8 @@ impl Device {
         assert!(
             session.session_type == SessionType::FiraRangingSession
                 || session.session_type == SessionType::FiraRangingAndInBandDataSession
+                || session.session_type == SessionType::Ccc
+                || session.session_type == SessionType::DeviceTestMode
         );
 
         if session.state == SessionState::SessionStateActive {
@@ -440,6 +587,10 @@ impl Device {
             }
         }
 
+        // Reason code of the first parameter value not supported
+        // by the device capabilities.
+        let mut unsupported_reason_code = None;
+        let session_state = session.state;
         let (status, invalid_parameters) = if session.state != SessionState::SessionStateInit
             && session.state != SessionState::SessionStateActive
         {
@@ -448,12 +599,31 @@ impl Device {
             let mut app_config = session.app_config.clone();
             let mut invalid_parameters = vec![];
             for cfg in cmd.tlvs {
-                match app_config.set(cfg.cfg_id, &cfg.v) {
-                    Ok(_) => (),
-                    Err(_) => invalid_parameters.push(AppConfigStatus {
+                let valid = match app_config.set(cfg.cfg_id, &cfg.v) {
+                    Ok(_) => {
+                        match app_config.check_capabilities(cfg.cfg_id, session.session_type, &caps)
+                        {
+                            Ok(_) => true,
+                            Err(reason_code) => {
+                                log::error!(
+                                    "[{}:0x{:x}] unsupported value for {:?}: {:?}",
+                                    self.handle,
+                                    session_handle,
+                                    cfg.cfg_id,
+                                    reason_code
+                                );
+                                unsupported_reason_code.get_or_insert(reason_code);
+                                false
+                            }
+                        }
+                    }
+                    Err(_) => false,
+                };
+                if !valid {
+                    invalid_parameters.push(AppConfigStatus {
                         cfg_id: cfg.cfg_id,
                         status: uci::Status::InvalidParam,
-                    }),
+                    })
                 }
             }
 
@@ -469,12 +639,22 @@ impl Device {
             // - DEVICE_MAC_ADDRESS
             // - DEVICE_TYPE (see Note1)
             // - SCHEDULE_MODE
-            if app_config.device_role.is_none()
-                || app_config.multi_node_mode.is_none()
-                || app_config.ranging_round_usage.is_none()
-                || app_config.device_mac_address.is_none()
-                || app_config.schedule_mode.is_none()
-            {
+            //
+            // The parameters are not mandatory for the session of type
+            // DEVICE_TEST_MODE. Sessions of type CCC only require
+            // the DEVICE_TYPE, the roles being negotiated out of band.
+            let missing_mandatory_parameters = match session.session_type {
+                SessionType::DeviceTestMode => false,
+                SessionType::Ccc => app_config.device_type.is_none(),
+                _ => {
+                    app_config.device_role.is_none()
+                        || app_config.multi_node_mode.is_none()
+                        || app_config.ranging_round_usage.is_none()
+                        || app_config.device_mac_address.is_none()
+                        || app_config.schedule_mode.is_none()
+                }
+            };
+            if missing_mandatory_parameters {
                 log::error!(
                     "[{}:0x{:x}] missing mandatory APP config parameters",
                     self.handle,
@@ -500,6 +680,14 @@ impl Device {
             }
         };
 
+        // Active sessions are stopped when reconfigured with
+        // unsupported values.
+        if let Some(reason_code) = unsupported_reason_code {
+            if session_state == SessionState::SessionStateActive {
+                self.stop_ranging(session_handle, reason_code);
+            }
+        }
+
         SessionSetAppConfigRsp {
             status,
             cfg_status: invalid_parameters,
@@ -572,7 +760,7 @@ impl Device {
     fn session_update_controller_multicast_list(
         &mut self,
         cmd: SessionUpdateControllerMulticastListCmd,
-    ) -> SessionUpdateControllerMulticastListRsp {
+    ) -> ControlPacket {
         let session_handle = cmd.session_token;
 
         log::debug!(
@@ -581,79 +769,53 @@ impl Device {
             session_handle
         );
 
+        let uci_version = self.profile.uci_version;
         let Some(session) = self.sessions.get_mut(&session_handle) else {
-            return SessionUpdateControllerMulticastListRsp {
-                status: uci::Status::ErrorSessionNotExist,
-                controlee_status: vec![],
-            };
+            return multicast_list_rsp(
+                uci_version,
+                MacAddressMode::Mode0,
+                uci::Status::ErrorSessionNotExist,
+                vec![],
+            );
         };
+        let session_id = session.id;
 
         if (session.state != SessionState::SessionStateActive
             && session.state != SessionState::SessionStateIdle)
             || session.app_config.device_type != Some(DeviceType::Controller)
             || session.app_config.multi_node_mode != Some(MultiNodeMode::OneToMany)
         {
-            return SessionUpdateControllerMulticastListRsp {
-                status: uci::Status::Rejected,
-                controlee_status: vec![],
-            };
+            return multicast_list_rsp(
+                uci_version,
+                MacAddressMode::Mode0,
+                uci::Status::Rejected,
+                vec![],
+            );
         }
         let action = cmd.action;
+        // The actions adding controlees with sub-session keys
+        // were introduced in UCI 2.0.
+        if uci_version == UciVersion::V1_1
+            && action != UpdateMulticastListAction::AddControlee
+            && action != UpdateMulticastListAction::RemoveControlee
+        {
+            return multicast_list_rsp(
+                uci_version,
+                MacAddressMode::Mode0,
+                uci::Status::InvalidParam,
+                vec![],
+            );
+        }
+        let mac_address_mode = session.app_config.mac_address_mode;
         let mut dst_addresses = session.app_config.dst_mac_address.clone();
-        let new_controlees: Vec<Controlee> = match action {
-            UpdateMulticastListAction::AddControlee
-            | UpdateMulticastListAction::RemoveControlee => {
-                if let Ok(packet) =
-                    SessionUpdateControllerMulticastListCmdPayload::decode_full(&cmd.payload)
-                {
-                    packet
-                        .controlees
-                        .iter()
-                        .map(|controlee| controlee.into())
-                        .collect()
-                } else {
-                    return SessionUpdateControllerMulticastListRsp {
-                        status: uci::Status::SyntaxError,
-                        controlee_status: vec![],
-                    };
-                }
-            }
-            UpdateMulticastListAction::AddControleeWithShortSubSessionKey => {
-                if let Ok(packet) =
-                    SessionUpdateControllerMulticastListCmd_2_0_16_Byte_Payload::decode_full(
-                        &cmd.payload,
-                    )
-                {
-                    packet
-                        .controlees
-                        .iter()
-                        .map(|controlee| controlee.into())
-                        .collect()
-                } else {
-                    return SessionUpdateControllerMulticastListRsp {
-                        status: uci::Status::SyntaxError,
-                        controlee_status: vec![],
-                    };
-                }
-            }
-            UpdateMulticastListAction::AddControleeWithExtendedSubSessionKey => {
-                if let Ok(packet) =
-                    SessionUpdateControllerMulticastListCmd_2_0_32_Byte_Payload::decode_full(
-                        &cmd.payload,
-                    )
-                {
-                    packet
-                        .controlees
-                        .iter()
-                        .map(|controlee| controlee.into())
-                        .collect()
-                } else {
-                    return SessionUpdateControllerMulticastListRsp {
-                        status: uci::Status::SyntaxError,
-                        controlee_status: vec![],
-                    };
-                }
-            }
+        let Some(new_controlees) = Controlee::decode_list(action, mac_address_mode, &cmd.payload)
+        else {
+            return multicast_list_rsp(
+                uci_version,
+                MacAddressMode::Mode0,
+                uci::Status::SyntaxError,
+                vec![],
+            );
         };
         let mut controlee_status_ntf = Vec::new();
         let mut controlee_status_rsp = Vec::new();
@@ -677,43 +839,27 @@ impl Device {
                         // Controlee in the Controlee List.
                         status = uci::Status::Failed;
                         update_status = MulticastUpdateStatus::ErrorSubSessionKeyNotApplicable;
-                        controlee_status_ntf.push(ControleeStatus {
-                            mac_address: match controlee.short_address {
-                                MacAddress::Short(address) => address,
-                                MacAddress::Extended(_) => {
-                                    panic!("Extended address is not supported!")
-                                }
-                            },
-                            status: update_status,
-                        });
+                        controlee_status_ntf.push((controlee.mac_address, update_status));
                     } else {
-                        if !dst_addresses.contains(&controlee.short_address) {
+                        if !dst_addresses.contains(&controlee.mac_address) {
                             if dst_addresses.len() == MAX_NUMBER_OF_CONTROLEES {
                                 status = uci::Status::ErrorMulticastListFull;
                                 update_status = MulticastUpdateStatus::ErrorMulticastListFull;
                             } else {
-                                dst_addresses.push(controlee.short_address);
+                                dst_addresses.push(controlee.mac_address);
                             };
                         } else {
                             status = uci::Status::Failed;
                             update_status = MulticastUpdateStatus::ErrorAddressAlreadyPresent;
                         }
-                        controlee_status_rsp.push(ControleeStatus {
-                            mac_address: match controlee.short_address {
-                                MacAddress::Short(address) => address,
-                                MacAddress::Extended(_) => {
-                                    panic!("Extended address is not supported!")
-                                }
-                            },
-                            status: update_status,
-                        });
+                        controlee_status_rsp.push((controlee.mac_address, update_status));
                     }
                 });
             }
             UpdateMulticastListAction::RemoveControlee => {
                 new_controlees.iter().for_each(|controlee: &Controlee| {
                     let pica_tx = self.pica_tx.clone();
-                    let address = controlee.short_address;
+                    let address = controlee.mac_address;
                     let attempt_count = session.app_config.in_band_termination_attempt_count;
                     let mut update_status = MulticastUpdateStatus::OkMulticastListUpdate;
                     if !dst_addresses.contains(&address) {
@@ -729,29 +875,15 @@ impl Device {
                             tokio::spawn(async move {
                                 for _ in 0..attempt_count {
                                     pica_tx
-                                        .send(PicaCommand::StopRanging(address, session_handle))
+                                        .send(PicaCommand::StopRanging(address, session_id))
                                         .await
                                         .unwrap()
                                 }
                             });
                         }
-                        controlee_status_ntf.push(ControleeStatus {
-                            mac_address: match address {
-                                MacAddress::Short(addr) => addr,
-                                MacAddress::Extended(_) => {
-                                    panic!("Extended address is not supported!")
-                                }
-                            },
-                            status: update_status,
-                        });
+                        controlee_status_ntf.push((address, update_status));
                     }
-                    controlee_status_rsp.push(ControleeStatus {
-                        mac_address: match address {
-                            MacAddress::Short(addr) => addr,
-                            MacAddress::Extended(_) => panic!("Extended address is not supported!"),
-                        },
-                        status: update_status,
-                    });
+                    controlee_status_rsp.push((address, update_status));
                 });
                 // Following requirements are applicable when the action is set to Delete (Action field set to 0x01):
                 // When the command is received while the Session State is SESSION_STATE_ACTIVE,
@@ -759,23 +891,16 @@ impl Device {
                 // UWBS shall send the SESSION_UPDATE_CONTROLLER_MULTICAST_LIST_NTF and the
                 // corresponding Controlee status shall be set to STATUS_OK_MULTICAST_LIST_UPDATE
                 // in the Status List of SESSION_UPDATE_CONTROLLER_MULTICAST_LIST_NTF.
-                if session.state == SessionState::SessionStateActive {
-                    let tx = self.tx.clone();
-                    tokio::spawn(async move {
-                        // Sleep for 5ms to make sure the notification is not being
-                        // sent before the response.
-                        // TODO(#84) remove the sleep.
-                        time::sleep(Duration::from_millis(5)).await;
-                        tx.send(
-                            SessionUpdateControllerMulticastListNtf {
-                                controlee_status: controlee_status_ntf,
-                                session_token: session_handle,
-                            }
-                            .encode_to_vec()
-                            .unwrap(),
-                        )
-                        .unwrap()
-                    });
+                if session.state == SessionState::SessionStateActive
+                    && uci_version == UciVersion::V2_0
+                {
+                    // The notification is held by the outbound queue
+                    // until the response is sent.
+                    let _ = self.tx.send(multicast_list_ntf(
+                        mac_address_mode,
+                        session_handle,
+                        controlee_status_ntf,
+                    ));
                 }
             }
         }
@@ -790,18 +915,182 @@ impl Device {
                 ReasonCode::ErrorInvalidNumOfControlees,
             )
         }
-        SessionUpdateControllerMulticastListRsp {
-            status,
-            controlee_status: controlee_status_rsp,
+        // [UCI 1.1] The status of each controlee is reported in the
+        // notification sent after the response, for all actions.
+        if uci_version == UciVersion::V1_1 {
+            let _ = self.tx.send(
+                SessionUpdateControllerMulticastListNtf_V1 {
+                    session_token: session_handle,
+                    remaining_multicast_list_size: (MAX_NUMBER_OF_CONTROLEES - dst_addresses.len())
+                        as u8,
+                    controlee_status: new_controlees
+                        .iter()
+                        .zip(controlee_status_rsp.iter())
+                        .map(|(controlee, (mac_address, status))| ControleeStatus_V1 {
+                            mac_address: mac_address.short_address(),
+                            subsession_id: controlee.sub_session_id,
+                            status: *status,
+                        })
+                        .collect(),
+                }
+                .encode_to_vec()
+                .unwrap(),
+            );
+        }
+        multicast_list_rsp(uci_version, mac_address_mode, status, controlee_status_rsp)
+    }
+
+    fn session_update_dt_anchor_ranging_rounds(
+        &mut self,
+        cmd: SessionUpdateDtAnchorRangingRoundsCmd,
+    ) -> SessionUpdateDtAnchorRangingRoundsRsp {
+        let session_handle = cmd.session_token;
+
+        log::debug!(
+            "[{}:0x{:x}] Session Update DT Anchor Ranging Rounds",
+            self.handle,
+            session_handle
+        );
+
+        let Some(session) = self.sessions.get_mut(&session_handle) else {
+            return SessionUpdateDtAnchorRangingRoundsRsp {
+                status: uci::Status::ErrorSessionNotExist,
+                ranging_round_indexes: vec![],
+            };
+        };
+
+        if session.app_config.device_role != Some(DeviceRole::DtAnchor) {
+            return SessionUpdateDtAnchorRangingRoundsRsp {
+                status: uci::Status::Rejected,
+                ranging_round_indexes: vec![],
+            };
+        }
+
+        session.dt_anchor_ranging_rounds = cmd.ranging_rounds;
+        SessionUpdateDtAnchorRangingRoundsRsp {
+            status: uci::Status::Ok,
+            ranging_round_indexes: vec![],
+        }
+    }
+
+    fn session_update_dt_tag_ranging_rounds(
+        &mut self,
+        cmd: SessionUpdateDtTagRangingRoundsCmd,
+    ) -> SessionUpdateDtTagRangingRoundsRsp {
+        let session_handle = cmd.session_token;
+
+        log::debug!(
+            "[{}:0x{:x}] Session Update DT Tag Ranging Rounds",
+            self.handle,
+            session_handle
+        );
+
+        let Some(session) = self.sessions.get_mut(&session_handle) else {
+            return SessionUpdateDtTagRangingRoundsRsp {
+                status: uci::Status::ErrorSessionNotExist,
+                ranging_round_indexes: vec![],
+            };
+        };
+
+        if session.app_config.device_role != Some(DeviceRole::DtTag) {
+            return SessionUpdateDtTagRangingRoundsRsp {
+                status: uci::Status::Rejected,
+                ranging_round_indexes: vec![],
+            };
+        }
+
+        session.dt_tag_ranging_rounds = cmd.ranging_round_indexes;
+        SessionUpdateDtTagRangingRoundsRsp {
+            status: uci::Status::Ok,
+            ranging_round_indexes: vec![],
+        }
+    }
+
+    /// Report the maximum size of the application data transmitted in one
+    /// ranging round. The maximum size is 0 for unknown sessions, and
+    /// sessions not supporting in-band data.
+    fn session_query_max_data_size_in_ranging(
+        &self,
+        cmd: SessionQueryMaxDataSizeInRangingCmd,
+    ) -> SessionQueryMaxDataSizeInRangingRsp {
+        let session_handle = cmd.session_token;
+
+        log::debug!(
+            "[{}:0x{:x}] Session Query Max Data Size In Ranging",
+            self.handle,
+            session_handle
+        );
+
+        let max_data_size = match self.sessions.get(&session_handle) {
+            Some(session)
+                if session.session_type == SessionType::FiraRangingAndInBandDataSession =>
+            {
+                session.app_config.max_data_size_in_ranging()
+            }
+            _ => 0,
+        };
+
+        log::debug!("  max_data_size={}", max_data_size);
+        SessionQueryMaxDataSizeInRangingRsp {
+            session_token: session_handle,
+            max_data_size,
+        }
+    }
+
+    /// Return the UWBS time of the first ranging round of the session,
+    /// in microseconds. The first ranging round is scheduled:
+    /// - relative to the first ranging round of the reference session
+    ///   when enabled by SESSION_TIME_BASE,
+    /// - at CCC_UWB_TIME0 when set for CCC sessions, as an absolute
+    ///   UWBS time in microseconds,
+    /// - at UWB_INITIATION_TIME when set, as a relative time in
+    ///   milliseconds for [UCI 1.1] devices, or an absolute UWBS time
+    ///   in microseconds for [UCI 2.0] devices,
+    /// - one ranging interval after the session start otherwise.
+    ///
+    /// Ranging rounds scheduled in the past are skipped. Returns None
+    /// if the reference session is not active.
+    fn first_round_timestamp(&self, session: &Session) -> Option<u64> {
+        let now = self.uwbs_timestamp();
+        let app_config = &session.app_config;
+        let ranging_interval_us = app_config.ranging_duration as u64 * 1000;
+        let session_time_base = &app_config.session_time_base;
+
+        let timestamp = if session_time_base[0] & 0x1 != 0 {
+            let reference_handle = u32::from_le_bytes(session_time_base[1..5].try_into().unwrap());
+            let offset_us = u32::from_le_bytes(session_time_base[5..9].try_into().unwrap());
+            let reference_session = self.session(reference_handle)?;
+            if reference_session.state != SessionState::SessionStateActive {
+                return None;
+            }
+            reference_session.first_round_timestamp? + offset_us as u64
+        } else if session.session_type == SessionType::Ccc && app_config.ccc_uwb_time0 != 0 {
+            app_config.ccc_uwb_time0
+        } else if app_config.uwb_initiation_time != 0 {
+            match self.profile.uci_version {
+                UciVersion::V1_1 => now + app_config.uwb_initiation_time * 1000,
+                UciVersion::V2_0 => app_config.uwb_initiation_time,
+            }
+        } else {
+            now + ranging_interval_us
+        };
+
+        if timestamp >= now {
+            Some(timestamp)
+        } else if ranging_interval_us == 0 {
+            Some(now)
+        } else {
+            let skipped_rounds = (now - timestamp).div_ceil(ranging_interval_us);
+            Some(timestamp + skipped_rounds * ranging_interval_us)
         }
     }
 
     fn session_start(&mut self, cmd: SessionStartCmd) -> SessionStartRsp {
-        let session_id = cmd.session_id;
+        let session_handle = cmd.session_id;
 
-        log::debug!("[{}:0x{:x}] Session Start", self.handle, session_id);
+        log::debug!("[{}:0x{:x}] Session Start", self.handle, session_handle);
 
-        let Some(session) = self.sessions.get_mut(&session_id) else {
+        let Some(session) = self.sessions.get(&session_handle) else {
             return SessionStartRsp {
                 status: uci::Status::ErrorSessionNotExist,
             };
@@ -813,21 +1102,43 @@ impl Device {
             };
         }
 
+        let Some(first_round_timestamp) = self.first_round_timestamp(session) else {
+            log::debug!(
+                "[{}:0x{:x}] Reference session not active",
+                self.handle,
+                session_handle
+            );
+            return SessionStartRsp {
+                status: uci::Status::Rejected,
+            };
+        };
+        log::debug!("  first_round_timestamp={}", first_round_timestamp);
+
+        // The ranging rounds are timed by the UWBS clock.
+        let first_round_delay = self
+            .uwbs_clock
+            .time(first_round_timestamp)
+            .saturating_sub(self.clock.now());
+        let ranging_interval = self.uwbs_clock.duration(time::Duration::from_millis(
+            session.app_config.ranging_duration as u64,
+        ));
+
+        let session = self.sessions.get_mut(&session_handle).unwrap();
         assert!(session.ranging_task.is_none());
+        session.failed_round_count = 0;
+        session.first_round_timestamp = Some(first_round_timestamp);
+        // [CCC] The UWBS reports the start time of the first ranging
+        // block in CCC_UWB_TIME0 when it was not configured by the Host.
+        if session.session_type == SessionType::Ccc {
+            session.app_config.ccc_uwb_time0 = first_round_timestamp;
+        }
 
-        let ranging_interval =
-            time::Duration::from_millis(session.app_config.ranging_duration as u64);
-
-        let tx = self.pica_tx.clone();
         let handle = self.handle;
-        session.ranging_task = Some(tokio::spawn(async move {
-            loop {
-                time::sleep(ranging_interval).await;
-                tx.send(PicaCommand::Ranging(handle, session_id))
-                    .await
-                    .unwrap();
-            }
-        }));
+        session.ranging_task = Some(self.clock.schedule(
+            first_round_delay,
+            Some(ranging_interval),
+            Box::new(move || Some(PicaCommand::Ranging(handle, session_handle))),
+        ));
 
         session.set_state(
             SessionState::SessionStateActive,
@@ -843,11 +1154,11 @@ impl Device {
     }
 
     fn session_stop(&mut self, cmd: SessionStopCmd) -> SessionStopRsp {
-        let session_id = cmd.session_id;
+        let session_handle = cmd.session_id;
 
-        log::debug!("[{}:0x{:x}] Session Stop", self.handle, session_id);
+        log::debug!("[{}:0x{:x}] Session Stop", self.handle, session_handle);
 
-        let Some(session) = self.sessions.get_mut(&session_id) else {
+        let Some(session) = self.sessions.get_mut(&session_handle) else {
             return SessionStopRsp {
                 status: uci::Status::ErrorSessionNotExist,
             };
@@ -859,17 +1170,11 @@ impl Device {
             };
         }
 
-        session.stop_ranging_task();
-        session.set_state(
-            SessionState::SessionStateIdle,
+        self.stop_ranging(
+            session_handle,
             ReasonCode::StateChangeWithSessionManagementCommands,
         );
 
-        self.n_active_sessions -= 1;
-        if self.n_active_sessions == 0 {
-            self.set_state(DeviceState::DeviceStateReady);
-        }
-
         SessionStopRsp {
             status: uci::Status::Ok,
         }
@@ -879,15 +1184,15 @@ impl Device {
         &self,
         cmd: SessionGetRangingCountCmd,
     ) -> SessionGetRangingCountRsp {
-        let session_id = cmd.session_id;
+        let session_handle = cmd.session_id;
 
         log::debug!(
             "[{}:0x{:x}] Session Get Ranging Count",
             self.handle,
-            session_id
+            session_handle
         );
 
-        let Some(session) = self.sessions.get(&session_id) else {
+        let Some(session) = self.sessions.get(&session_handle) else {
             return SessionGetRangingCountRsp {
                 status: uci::Status::ErrorSessionNotExist,
                 count: 0,
@@ -900,6 +1205,212 @@ impl Device {
         }
     }
 
+    fn test_config_set(&mut self, cmd: TestConfigSetCmd) -> TestConfigSetRsp {
+        let session_handle = cmd.session_token;
+
+        log::debug!("[{}:0x{:x}] Test Config Set", self.handle, session_handle);
+
+        let Some(session) = self.sessions.get_mut(&session_handle) else {
+            return TestConfigSetRsp {
+                status: uci::Status::ErrorSessionNotExist,
+                cfg_status: vec![],
+            };
+        };
+
+        if session.session_type != SessionType::DeviceTestMode {
+            return TestConfigSetRsp {
+                status: uci::Status::Rejected,
+                cfg_status: vec![],
+            };
+        }
+
+        if session.state == SessionState::SessionStateActive {
+            return TestConfigSetRsp {
+                status: uci::Status::ErrorSessionActive,
+                cfg_status: vec![],
+            };
+        }
+
+        let mut test_config = session.test_config.clone();
+        let mut invalid_parameters = vec![];
+        for cfg in cmd.tlvs {
+            if test_config.set(cfg.cfg_id, &cfg.v).is_err() {
+                invalid_parameters.push(TestConfigStatus {
+                    cfg_id: cfg.cfg_id,
+                    status: uci::Status::InvalidParam,
+                })
+            }
+        }
+
+        if invalid_parameters.is_empty() {
+            session.test_config = test_config;
+            TestConfigSetRsp {
+                status: uci::Status::Ok,
+                cfg_status: vec![],
+            }
+        } else {
+            TestConfigSetRsp {
+                status: uci::Status::InvalidParam,
+                cfg_status: invalid_parameters,
+            }
+        }
+    }
+
+    fn test_config_get(&self, cmd: TestConfigGetCmd) -> TestConfigGetRsp {
+        let session_handle = cmd.session_token;
+
+        log::debug!("[{}:0x{:x}] Test Config Get", self.handle, session_handle);
+
+        let Some(session) = self.sessions.get(&session_handle) else {
+            return TestConfigGetRsp {
+                status: uci::Status::ErrorSessionNotExist,
+                tlvs: vec![],
+            };
+        };
+
+        if session.session_type != SessionType::DeviceTestMode {
+            return TestConfigGetRsp {
+                status: uci::Status::Rejected,
+                tlvs: vec![],
+            };
+        }
+
+        TestConfigGetRsp {
+            status: uci::Status::Ok,
+            tlvs: cmd
+                .test_config_ids
+                .into_iter()
+                .map(|cfg_id| TestConfigTlv {
+                    cfg_id,
+                    v: session.test_config.get(cfg_id),
+                })
+                .collect(),
+        }
+    }
+
+    /// Start a test in the session of type DEVICE_TEST_MODE, which is unique.
+    /// The test notification is generated by pica when the test completes,
+    /// after the test duration configured with the TEST_CONFIG_SET command.
+    fn test_start(&mut self, test: Test) -> uci::Status {
+        log::debug!("[{}] Test Start", self.handle);
+        log::debug!("  test={:?}", test);
+
+        let Some((&session_handle, session)) = self
+            .sessions
+            .iter_mut()
+            .find(|(_, session)| session.session_type == SessionType::DeviceTestMode)
+        else {
+            return uci::Status::ErrorSessionNotExist;
+        };
+
+        match session.state {
+            SessionState::SessionStateIdle => (),
+            SessionState::SessionStateActive => return uci::Status::ErrorSessionActive,
+            _ => return uci::Status::ErrorSessionNotConfigured,
+        }
+
+        assert!(session.ranging_task.is_none());
+
+        let test_duration = test.duration(&session.test_config);
+        session.test = Some(test);
+
+        let handle = self.handle;
+        session.ranging_task = Some(self.clock.schedule(
+            test_duration,
+            None,
+            Box::new(move || Some(PicaCommand::Test(handle, session_handle))),
+        ));
+
+        session.set_state(
+            SessionState::SessionStateActive,
+            ReasonCode::StateChangeWithSessionManagementCommands,
+        );
+
+        self.n_active_sessions += 1;
+        self.set_state(DeviceState::DeviceStateActive);
+
+        uci::Status::Ok
+    }
+
+    /// Stop the test running in the selected session, and move the session
+    /// back to the idle state.
+    pub fn test_stop(&mut self, session_handle: u32) {
+        let Some(session) = self.sessions.get_mut(&session_handle) else {
+            return;
+        };
+
+        if session.test.take().is_none() {
+            return;
+        }
+
+        session.stop_ranging_task();
+        session.set_state(
+            SessionState::SessionStateIdle,
+            ReasonCode::StateChangeWithSessionManagementCommands,
+        );
+
+        self.n_active_sessions -= 1;
+        if self.n_active_sessions == 0 {
+            self.set_state(DeviceState::DeviceStateReady);
+        }
+    }
+
+    fn test_periodic_tx(&mut self, cmd: TestPeriodicTxCmd) -> TestPeriodicTxRsp {
+        TestPeriodicTxRsp {
+            status: self.test_start(Test::PeriodicTx {
+                psdu_data: cmd.psdu_data,
+            }),
+        }
+    }
+
+    fn test_per_rx(&mut self, cmd: TestPerRxCmd) -> TestPerRxRsp {
+        TestPerRxRsp {
+            status: self.test_start(Test::PerRx {
+                psdu_data: cmd.psdu_data,
+            }),
+        }
+    }
+
+    fn test_rx(&mut self, _cmd: TestRxCmd) -> TestRxRsp {
+        TestRxRsp {
+            status: self.test_start(Test::Rx),
+        }
+    }
+
+    fn test_loopback(&mut self, cmd: TestLoopbackCmd) -> TestLoopbackRsp {
+        TestLoopbackRsp {
+            status: self.test_start(Test::Loopback {
+                psdu_data: cmd.psdu_data,
+            }),
+        }
+    }
+
+    fn test_ss_twr(&mut self, _cmd: TestSsTwrCmd) -> TestSsTwrRsp {
+        TestSsTwrRsp {
+            status: self.test_start(Test::SsTwr),
+        }
+    }
+
+    fn test_stop_session(&mut self, _cmd: TestStopSessionCmd) -> TestStopSessionRsp {
+        log::debug!("[{}] Test Stop Session", self.handle);
+
+        let Some(session_handle) = self
+            .sessions
+            .iter()
+            .find(|(_, session)| session.test.is_some())
+            .map(|(session_handle, _)| *session_handle)
+        else {
+            return TestStopSessionRsp {
+                status: uci::Status::Rejected,
+            };
+        };
+
+        self.test_stop(session_handle);
+        TestStopSessionRsp {
+            status: uci::Status::Ok,
+        }
+    }
+
     fn android_set_country_code(
         &mut self,
         cmd: AndroidSetCountryCodeCmd,
@@ -935,11 +1446,8 @@ impl Device {
 
     pub fn data_message_snd(&mut self, data: DataPacket) -> ControlPacket {
         log::debug!("[{}] data_message_send", self.handle);
-        match data
-            .specialize()
-            .expect("failed to parse Data packet child")
-        {
-            DataPacketChild::DataMessageSnd(data_msg_snd) => {
+        match data.specialize() {
+            Ok(DataPacketChild::DataMessageSnd(data_msg_snd)) => {
                 let session_token = data_msg_snd.session_handle;
                 if let Some(session) = self.session_mut(session_token) {
                     session.data_message_snd(data_msg_snd)
@@ -954,7 +1462,7 @@ impl Device {
                     .unwrap()
                 }
             }
-            DataPacketChild::DataMessageRcv(data_msg_rcv) => {
+            Ok(DataPacketChild::DataMessageRcv(data_msg_rcv)) => {
                 // This function should not be passed anything besides DataMessageSnd
                 let session_token = data_msg_rcv.session_handle;
                 SessionDataTransferStatusNtf {
@@ -966,8 +1474,27 @@ impl Device {
                 .try_into()
                 .unwrap()
             }
-            _ => {
-                unimplemented!()
+            result => {
+                if let Err(err) = result {
+                    log::error!("[{}] Malformed Data packet: {}", self.handle, err);
+                } else {
+                    log::error!("[{}] Unexpected Data packet format", self.handle);
+                }
+                // The session handle is the first field of the payload,
+                // and is reported when present.
+                let session_token = data
+                    .payload()
+                    .get(..4)
+                    .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+                    .unwrap_or_default();
+                SessionDataTransferStatusNtf {
+                    session_token,
+                    status: DataTransferNtfStatusCode::UciDataTransferStatusInvalidFormat,
+                    tx_count: 1,
+                    uci_sequence_number: 0,
+                }
+                .try_into()
+                .unwrap()
             }
         }
     }
@@ -978,6 +1505,7 @@ impl Device {
         use CorePacketChild::*;
         use SessionConfigPacketChild::*;
         use SessionControlPacketChild::*;
+        use TestPacketChild::*;
 
         // Check whether the first command received is the Core Device
         // Reset command. The controller responds with Device Status
@@ -990,6 +1518,22 @@ impl Device {
             .unwrap();
         }
 
+        // [UCI] The commands introduced in UCI 2.0 are unknown
+        // to the UCI 1.x devices.
+        if self.profile.uci_version == UciVersion::V1_1 && is_uci_v2_command(&cmd) {
+            log::debug!("[{}] UCI 2.0 command rejected", self.handle);
+            return ControlPacket {
+                mt: uci::MessageType::Response,
+                gid: cmd.gid,
+                payload: vec![
+                    cmd.payload[0],
+                    cmd.payload[1],
+                    0x1,
+                    uci::Status::UnknownOid.into(),
+                ],
+            };
+        }
+
         match cmd
             .specialize()
             .expect("Failed to parse Control packet child")
@@ -1000,6 +1544,9 @@ impl Device {
                 Ok(CoreGetCapsInfoCmd(cmd)) => self.core_get_caps_info(cmd).try_into().unwrap(),
                 Ok(CoreSetConfigCmd(cmd)) => self.core_set_config(cmd).try_into().unwrap(),
                 Ok(CoreGetConfigCmd(cmd)) => self.core_get_config(cmd).try_into().unwrap(),
+                Ok(CoreQueryTimeStampCmd(cmd)) => {
+                    self.core_query_uwbs_timestamp(cmd).try_into().unwrap()
+                }
                 _ => uci::CorePacket {
                     mt: uci::MessageType::Response,
                     oid: cmd.oid,
@@ -1009,7 +1556,7 @@ impl Device {
                 .unwrap(),
             },
             SessionConfigPacket(cmd) => match cmd.specialize() {
-                Ok(SessionInitCmd(cmd)) => self.session_init(cmd).try_into().unwrap(),
+                Ok(SessionInitCmd(cmd)) => self.session_init(cmd),
                 Ok(SessionDeinitCmd(cmd)) => self.session_deinit(cmd).try_into().unwrap(),
                 Ok(SessionGetCountCmd(cmd)) => self.session_get_count(cmd).try_into().unwrap(),
                 Ok(SessionSetAppConfigCmd(cmd)) => {
@@ -1019,8 +1566,19 @@ impl Device {
                     self.session_get_app_config(cmd).try_into().unwrap()
                 }
                 Ok(SessionGetStateCmd(cmd)) => self.session_get_state(cmd).try_into().unwrap(),
-                Ok(SessionUpdateControllerMulticastListCmd(cmd)) => self
-                    .session_update_controller_multicast_list(cmd)
+                Ok(SessionUpdateControllerMulticastListCmd(cmd)) => {
+                    self.session_update_controller_multicast_list(cmd)
+                }
+                Ok(SessionUpdateDtAnchorRangingRoundsCmd(cmd)) => self
+                    .session_update_dt_anchor_ranging_rounds(cmd)
+                    .try_into()
+                    .unwrap(),
+                Ok(SessionUpdateDtTagRangingRoundsCmd(cmd)) => self
+                    .session_update_dt_tag_ranging_rounds(cmd)
+                    .try_into()
+                    .unwrap(),
+                Ok(SessionQueryMaxDataSizeInRangingCmd(cmd)) => self
+                    .session_query_max_data_size_in_ranging(cmd)
                     .try_into()
                     .unwrap(),
                 _ => uci::SessionConfigPacket {
@@ -1045,6 +1603,23 @@ impl Device {
                 .try_into()
                 .unwrap(),
             },
+            TestPacket(cmd) => match cmd.specialize() {
+                Ok(TestConfigSetCmd(cmd)) => self.test_config_set(cmd).try_into().unwrap(),
+                Ok(TestConfigGetCmd(cmd)) => self.test_config_get(cmd).try_into().unwrap(),
+                Ok(TestPeriodicTxCmd(cmd)) => self.test_periodic_tx(cmd).try_into().unwrap(),
+                Ok(TestPerRxCmd(cmd)) => self.test_per_rx(cmd).try_into().unwrap(),
+                Ok(TestRxCmd(cmd)) => self.test_rx(cmd).try_into().unwrap(),
+                Ok(TestLoopbackCmd(cmd)) => self.test_loopback(cmd).try_into().unwrap(),
+                Ok(TestSsTwrCmd(cmd)) => self.test_ss_twr(cmd).try_into().unwrap(),
+                Ok(TestStopSessionCmd(cmd)) => self.test_stop_session(cmd).try_into().unwrap(),
+                _ => uci::TestPacket {
+                    mt: uci::MessageType::Response,
+                    oid: cmd.oid,
+                    payload: vec![0x1, uci::Status::SyntaxError.into()],
+                }
+                .try_into()
+                .unwrap(),
+            },
             AndroidPacket(cmd) => match cmd.specialize() {
                 Ok(AndroidSetCountryCodeCmd(cmd)) => {
                     self.android_set_country_code(cmd).try_into().unwrap()
@@ -1093,6 +1668,12 @@ impl Device {
     pub fn receive_packet(&mut self, packet: Vec<u8>) {
         let mt = parse_message_type(packet[0]);
         match mt {
+            MessageType::Data if self.state == DeviceState::DeviceStateError => {
+                log::warn!("[{}] Data packet dropped in error state", self.handle)
+            }
+            MessageType::Data if self.profile.uci_version == UciVersion::V1_1 => {
+                log::warn!("[{}] Data packet dropped by UCI 1.x device", self.handle)
+            }
             MessageType::Data => match DataPacket::decode_full(&packet) {
                 Ok(packet) => {
                     let notification = self.data_message_snd(packet);
@@ -1131,10 +1712,30 @@ impl Device {
                         self.send_raw_control(response)
                     }
 
+                    // The device is in the error state, reject all commands
+                    // but the Core Device Reset command.
+                    Ok(cmd)
+                        if self.state == DeviceState::DeviceStateError
+                            && !cmd.is_core_device_reset_cmd() =>
+                    {
+                        log::debug!("[{}] Command rejected in error state", self.handle);
+                        let response = vec![
+                            (u8::from(MessageType::Response) << 5) | (packet[0] & 0xf),
+                            packet[1] & 0x3f,
+                            0,
+                            1,
+                            uci::Status::Rejected.into(),
+                        ];
+                        self.send_raw_control(response)
+                    }
+
                     // Parsing success, ignore non command packets.
                     Ok(cmd) => {
+                        // Hold the notifications generated by the command
+                        // until the response is sent.
+                        self.tx.hold();
                         let response = self.receive_command(cmd);
-                        self.send_control(response)
+                        let _ = self.tx.send_response(response.encode_to_vec().unwrap());
                     }
                 }
             }
@@ -1146,18 +1747,93 @@ impl Device {
     }
 }
 
+/// Return true if the command was introduced in UCI 2.0.
+fn is_uci_v2_command(cmd: &ControlPacket) -> bool {
+    let oid = cmd.payload[0] & 0x3f;
+    match cmd.gid {
+        GroupId::Core => oid == u8::from(CoreOpcodeId::QueryUwbsTimestamp),
+        GroupId::SessionConfig => [
+            SessionConfigOpcodeId::UpdateDtAnchorRangingRounds,
+            SessionConfigOpcodeId::UpdateDtTagRangingRounds,
+            SessionConfigOpcodeId::QueryDataSizeInRanging,
+        ]
+        .iter()
+        .any(|opcode| oid == u8::from(*opcode)),
+        _ => false,
+    }
+}
+
 struct Controlee {
-    short_address: MacAddress,
-    #[allow(dead_code)]
+    mac_address: MacAddress,
     sub_session_id: u32,
     #[allow(dead_code)]
     session_key: SubSessionKey,
 }
 
+impl Controlee {
+    /// Decode the controlee list of a multicast list update command.
+    /// The layout of the list depends on the action and on the MAC address
+    /// mode of the session: extended addresses are used when
+    /// MAC_ADDRESS_MODE is set to 0x01 or 0x02.
+    fn decode_list(
+        action: UpdateMulticastListAction,
+        mac_address_mode: MacAddressMode,
+        payload: &[u8],
+    ) -> Option<Vec<Controlee>> {
+        fn convert<T>(controlees: &[T]) -> Vec<Controlee>
+        where
+            for<'a> &'a T: Into<Controlee>,
+        {
+            controlees
+                .iter()
+                .map(|controlee| controlee.into())
+                .collect()
+        }
+
+        use UpdateMulticastListAction::*;
+        Some(match (action, mac_address_mode) {
+            (AddControlee | RemoveControlee, MacAddressMode::Mode0) => convert(
+                &SessionUpdateControllerMulticastListCmdPayload::decode_full(payload)
+                    .ok()?
+                    .controlees,
+            ),
+            (AddControleeWithShortSubSessionKey, MacAddressMode::Mode0) => convert(
+                &SessionUpdateControllerMulticastListCmd_2_0_16_Byte_Payload::decode_full(payload)
+                    .ok()?
+                    .controlees,
+            ),
+            (AddControleeWithExtendedSubSessionKey, MacAddressMode::Mode0) => convert(
+                &SessionUpdateControllerMulticastListCmd_2_0_32_Byte_Payload::decode_full(payload)
+                    .ok()?
+                    .controlees,
+            ),
+            (AddControlee | RemoveControlee, _) => convert(
+                &SessionUpdateControllerMulticastListCmdExtendedPayload::decode_full(payload)
+                    .ok()?
+                    .controlees,
+            ),
+            (AddControleeWithShortSubSessionKey, _) => convert(
+                &SessionUpdateControllerMulticastListCmd_2_0_16_Byte_ExtendedPayload::decode_full(
+                    payload,
+                )
+                .ok()?
+                .controlees,
+            ),
+            (AddControleeWithExtendedSubSessionKey, _) => convert(
+                &SessionUpdateControllerMulticastListCmd_2_0_32_Byte_ExtendedPayload::decode_full(
+                    payload,
+                )
+                .ok()?
+                .controlees,
+            ),
+        })
+    }
+}
+
 impl From<&uci::Controlee> for Controlee {
     fn from(value: &uci::Controlee) -> Self {
         Controlee {
-            short_address: MacAddress::Short(value.short_address),
+            mac_address: MacAddress::Short(value.short_address),
             sub_session_id: value.subsession_id,
             session_key: SubSessionKey::None,
         }
@@ -1167,7 +1843,7 @@ impl From<&uci::Controlee> for Controlee {
 impl From<&uci::Controlee_V2_0_16_Byte_Version> for Controlee {
     fn from(value: &uci::Controlee_V2_0_16_Byte_Version) -> Self {
         Controlee {
-            short_address: MacAddress::Short(value.short_address),
+            mac_address: MacAddress::Short(value.short_address),
             sub_session_id: value.subsession_id,
             session_key: SubSessionKey::Short(value.subsession_key),
         }
@@ -1177,9 +1853,120 @@ impl From<&uci::Controlee_V2_0_16_Byte_Version> for Controlee {
 impl From<&uci::Controlee_V2_0_32_Byte_Version> for Controlee {
     fn from(value: &uci::Controlee_V2_0_32_Byte_Version) -> Self {
         Controlee {
-            short_address: MacAddress::Short(value.short_address),
+            mac_address: MacAddress::Short(value.short_address),
+            sub_session_id: value.subsession_id,
+            session_key: SubSessionKey::Extended(value.subsession_key),
+        }
+    }
+}
+
+impl From<&uci::ExtendedControlee> for Controlee {
+    fn from(value: &uci::ExtendedControlee) -> Self {
+        Controlee {
+            mac_address: MacAddress::Extended(value.extended_address),
+            sub_session_id: value.subsession_id,
+            session_key: SubSessionKey::None,
+        }
+    }
+}
+
+impl From<&uci::ExtendedControlee_V2_0_16_Byte_Version> for Controlee {
+    fn from(value: &uci::ExtendedControlee_V2_0_16_Byte_Version) -> Self {
+        Controlee {
+            mac_address: MacAddress::Extended(value.extended_address),
+            sub_session_id: value.subsession_id,
+            session_key: SubSessionKey::Short(value.subsession_key),
+        }
+    }
+}
+
+impl From<&uci::ExtendedControlee_V2_0_32_Byte_Version> for Controlee {
+    fn from(value: &uci::ExtendedControlee_V2_0_32_Byte_Version) -> Self {
+        Controlee {
+            mac_address: MacAddress::Extended(value.extended_address),
             sub_session_id: value.subsession_id,
             session_key: SubSessionKey::Extended(value.subsession_key),
         }
     }
 }
+
+/// Build the multicast list update response, with the controlee status
+/// list encoded according to the MAC address mode of the session.
+/// The UCI 1.x response only includes the status.
+fn multicast_list_rsp(
+    uci_version: UciVersion,
+    mac_address_mode: MacAddressMode,
+    status: uci::Status,
+    controlee_status: Vec<(MacAddress, MulticastUpdateStatus)>,
+) -> ControlPacket {
+    match mac_address_mode {
+        _ if uci_version == UciVersion::V1_1 => {
+            SessionUpdateControllerMulticastListRsp_V1 { status }
+                .try_into()
+                .unwrap()
+        }
+        MacAddressMode::Mode0 => SessionUpdateControllerMulticastListRsp {
+            status,
+            controlee_status: controlee_status
+                .into_iter()
+                .map(|(mac_address, status)| ControleeStatus {
+                    mac_address: mac_address.short_address(),
+                    status,
+                })
+                .collect(),
+        }
+        .try_into()
+        .unwrap(),
+        MacAddressMode::Mode1 | MacAddressMode::Mode2 => {
+            SessionUpdateControllerMulticastListExtendedRsp {
+                status,
+                controlee_status: controlee_status
+                    .into_iter()
+                    .map(|(mac_address, status)| ExtendedControleeStatus {
+                        mac_address: mac_address.extended_address(),
+                        status,
+                    })
+                    .collect(),
+            }
+            .try_into()
+            .unwrap()
+        }
+    }
+}
+
+/// Build the multicast list update notification, with the controlee status
+/// list encoded according to the MAC address mode of the session.
+fn multicast_list_ntf(
+    mac_address_mode: MacAddressMode,
+    session_token: u32,
+    controlee_status: Vec<(MacAddress, MulticastUpdateStatus)>,
+) -> UciPacket {
+    match mac_address_mode {
+        MacAddressMode::Mode0 => SessionUpdateControllerMulticastListNtf {
+            session_token,
+            controlee_status: controlee_status
+                .into_iter()
+                .map(|(mac_address, status)| ControleeStatus {
+                    mac_address: mac_address.short_address(),
+                    status,
+                })
+                .collect(),
+        }
+        .encode_to_vec()
+        .unwrap(),
+        MacAddressMode::Mode1 | MacAddressMode::Mode2 => {
+            SessionUpdateControllerMulticastListExtendedNtf {
+                session_token,
+                controlee_status: controlee_status
+                    .into_iter()
+                    .map(|(mac_address, status)| ExtendedControleeStatus {
+                        mac_address: mac_address.extended_address(),
+                        status,
+                    })
+                    .collect(),
+            }
+            .encode_to_vec()
+            .unwrap()
+        }
+    }
+}
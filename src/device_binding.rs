@@ -0,0 +1,170 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{Handle, MacAddress};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Connection-time information used to select the identity
+/// of a UCI device.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConnectionHint {
+    /// The connection was accepted on the selected UCI port.
+    Port(u16),
+    /// The connection was tagged by the application.
+    Tag(String),
+}
+
+/// UCI device declared with a fixed MAC address. The MAC address is
+/// assigned to the connections matching the hint, or to any connection
+/// when the hint is omitted. The device reports the capabilities of the
+/// selected profile, or of the default profile when omitted.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceDeclaration {
+    pub mac_address: MacAddress,
+    pub hint: Option<ConnectionHint>,
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+/// MAC address assigned to a UCI device, with the handle of the
+/// connected device if any.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DeviceBinding {
+    pub mac_address: MacAddress,
+    pub hint: Option<ConnectionHint>,
+    pub profile: Option<String>,
+    pub handle: Option<Handle>,
+}
+
+impl FromStr for ConnectionHint {
+    type Err = String;
+
+    /// Parse a port number, or a tag.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("empty connection hint".to_owned());
+        }
+        Ok(match s.parse::<u16>() {
+            Ok(port) => ConnectionHint::Port(port),
+            Err(_) => ConnectionHint::Tag(s.to_owned()),
+        })
+    }
+}
+
+impl FromStr for DeviceDeclaration {
+    type Err = String;
+
+    /// Parse a device declaration with the format
+    /// `MAC[@PORT|@TAG][=PROFILE]`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (s, profile) = match s.split_once('=') {
+            Some((_, "")) => return Err("empty device profile".to_owned()),
+            Some((s, profile)) => (s, Some(profile.to_owned())),
+            None => (s, None),
+        };
+        let (mac_address, hint) = match s.split_once('@') {
+            Some((mac_address, hint)) => (mac_address, Some(hint.parse()?)),
+            None => (s, None),
+        };
+        Ok(DeviceDeclaration {
+            mac_address: MacAddress::new(mac_address.to_owned()).map_err(|err| err.to_string())?,
+            hint,
+            profile,
+        })
+    }
+}
+
+/// Select the declared MAC address assigned to a new connection:
+/// the first unused address declared with the same hint, or else the
+/// first unused address declared without a hint.
+pub(crate) fn assign_mac_address(
+    declarations: &[DeviceDeclaration],
+    hint: Option<&ConnectionHint>,
+    is_used: impl Fn(&MacAddress) -> bool,
+) -> Option<MacAddress> {
+    let find = |hint: Option<&ConnectionHint>| {
+        declarations
+            .iter()
+            .find(|declaration| {
+                declaration.hint.as_ref() == hint && !is_used(&declaration.mac_address)
+            })
+            .map(|declaration| declaration.mac_address)
+    };
+    hint.and_then(|hint| find(Some(hint)))
+        .or_else(|| find(None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_declaration() {
+        let declaration: DeviceDeclaration = "00:01@7001".parse().unwrap();
+        assert_eq!(declaration.hint, Some(ConnectionHint::Port(7001)));
+
+        let declaration: DeviceDeclaration = "00:00:00:00:00:00:00:02@phone".parse().unwrap();
+        assert_eq!(
+            declaration.hint,
+            Some(ConnectionHint::Tag("phone".to_owned()))
+        );
+
+        let declaration: DeviceDeclaration = "00:03".parse().unwrap();
+        assert_eq!(declaration.mac_address, MacAddress::Short([0, 3]));
+        assert_eq!(declaration.hint, None);
+        assert_eq!(declaration.profile, None);
+
+        let declaration: DeviceDeclaration = "00:04@7001=chip-a".parse().unwrap();
+        assert_eq!(declaration.hint, Some(ConnectionHint::Port(7001)));
+        assert_eq!(declaration.profile, Some("chip-a".to_owned()));
+        assert!("00:04=".parse::<DeviceDeclaration>().is_err());
+
+        assert!("00:03@".parse::<DeviceDeclaration>().is_err());
+        assert!("00:0z".parse::<DeviceDeclaration>().is_err());
+    }
+
+    #[test]
+    fn test_assign_mac_address() {
+        let declarations: Vec<DeviceDeclaration> = ["00:01@7001", "00:02", "00:03"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        let short = |address: u8| MacAddress::Short([0, address]);
+        let unused = |_: &MacAddress| false;
+
+        let port = ConnectionHint::Port(7001);
+        assert_eq!(
+            assign_mac_address(&declarations, Some(&port), unused),
+            Some(short(1))
+        );
+        assert_eq!(
+            assign_mac_address(&declarations, None, unused),
+            Some(short(2))
+        );
+        assert_eq!(
+            assign_mac_address(&declarations, Some(&port), |mac_address| {
+                *mac_address == short(1)
+            }),
+            Some(short(2))
+        );
+        assert_eq!(
+            assign_mac_address(&declarations, None, |mac_address| {
+                *mac_address != short(1)
+            }),
+            None
+        );
+    }
+}
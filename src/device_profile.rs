@@ -0,0 +1,238 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::device::DEFAULT_CAPS_INFO;
+use crate::packets::uci::{CapTlv, CapTlvType};
+use anyhow::Context;
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Name of the profile assigned to the devices connecting without
+/// a profile selection.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Version of the UCI protocol implemented by a device.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+pub enum UciVersion {
+    /// UCI 1.1: sessions are identified by the session ID selected by the
+    /// host, and the commands introduced in UCI 2.0 are not supported.
+    #[serde(rename = "1.1")]
+    V1_1,
+    /// UCI 2.0.
+    #[default]
+    #[serde(rename = "2.0")]
+    V2_0,
+}
+
+impl UciVersion {
+    /// Return the version reported in CORE_GET_DEVICE_INFO_RSP:
+    /// major version in the first octet, minor and maintenance
+    /// versions in the second octet.
+    pub fn version(&self) -> u16 {
+        match self {
+            UciVersion::V1_1 => 0x1001,
+            UciVersion::V2_0 => 0x0002,
+        }
+    }
+}
+
+/// Device role advertised in the SUPPORTED_DEVICE_ROLES capability.
+/// The discriminant is the index of the bit in the capability value.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SupportedDeviceRole {
+    Responder = 0,
+    Initiator = 1,
+    UtSynchronizationAnchor = 2,
+    UtAnchor = 3,
+    UtTag = 4,
+    Advertiser = 5,
+    Observer = 6,
+    DtAnchor = 7,
+    DtTag = 8,
+}
+
+/// Capability TLV set by a device profile.
+/// The value is written as an hexadecimal string.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileCap {
+    #[serde(deserialize_with = "deserialize_cap_type")]
+    pub id: CapTlvType,
+    #[serde(deserialize_with = "deserialize_hex")]
+    pub value: Vec<u8>,
+}
+
+/// Versions, vendor specific information and capabilities reported
+/// by an emulated UWB chip. The omitted fields take the default Pica
+/// values.
+///
+/// ```json
+/// {
+///   "uci_version": "1.1",
+///   "vendor_spec_info": "0102",
+///   "device_roles": ["initiator", "responder"],
+///   "caps": [ { "id": 11, "value": "28" } ]
+/// }
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct DeviceProfile {
+    /// Version of the UCI protocol, selecting the encoding of the
+    /// UCI messages.
+    pub uci_version: UciVersion,
+    /// Versions reported in CORE_GET_DEVICE_INFO_RSP, written as
+    /// the hexadecimal representation of the little endian value.
+    #[serde(deserialize_with = "deserialize_version")]
+    pub mac_version: u16,
+    #[serde(deserialize_with = "deserialize_version")]
+    pub phy_version: u16,
+    #[serde(deserialize_with = "deserialize_version")]
+    pub test_version: u16,
+    /// Vendor specific information reported in CORE_GET_DEVICE_INFO_RSP.
+    #[serde(deserialize_with = "deserialize_hex")]
+    pub vendor_spec_info: Vec<u8>,
+    /// Set to false to report only the capabilities of the profile,
+    /// instead of adding them to the default capabilities.
+    pub default_caps: bool,
+    /// Capabilities replacing the default capabilities with the same
+    /// identifier.
+    pub caps: Vec<ProfileCap>,
+    /// Device roles reported in the SUPPORTED_DEVICE_ROLES capability.
+    pub device_roles: Option<Vec<SupportedDeviceRole>>,
+}
+
+impl Default for DeviceProfile {
+    fn default() -> Self {
+        DeviceProfile {
+            uci_version: UciVersion::default(),
+            mac_version: 0x3001,  // Version 1.3.0
+            phy_version: 0x3001,  // Version 1.3.0
+            test_version: 0x1001, // Version 1.1
+            vendor_spec_info: vec![],
+            default_caps: true,
+            caps: vec![],
+            device_roles: None,
+        }
+    }
+}
+
+fn deserialize_hex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    hex::decode(value).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_cap_type<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<CapTlvType, D::Error> {
+    let value = u8::deserialize(deserializer)?;
+    CapTlvType::try_from(value)
+        .map_err(|_| serde::de::Error::custom(format!("invalid capability type {:#x}", value)))
+}
+
+fn deserialize_version<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u16, D::Error> {
+    let value = deserialize_hex(deserializer)?;
+    let value: [u8; 2] = value
+        .try_into()
+        .map_err(|_| serde::de::Error::custom("expected a version of 2 bytes"))?;
+    Ok(u16::from_le_bytes(value))
+}
+
+impl DeviceProfile {
+    /// Load named device profiles from a JSON file.
+    pub fn load(path: &Path) -> anyhow::Result<HashMap<String, DeviceProfile>> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read device profile file {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("failed to parse device profile file {}", path.display()))
+    }
+
+    /// Return the capabilities reported by the device.
+    pub fn caps(&self) -> Vec<CapTlv> {
+        let mut caps: Vec<CapTlv> = vec![];
+        let mut set_cap = |t: CapTlvType, v: Vec<u8>| match caps.iter_mut().find(|cap| cap.t == t) {
+            Some(cap) => cap.v = v,
+            None => caps.push(CapTlv { t, v }),
+        };
+
+        if self.default_caps {
+            for (t, v) in DEFAULT_CAPS_INFO {
+                set_cap(*t, v.to_vec())
+            }
+        }
+        for cap in &self.caps {
+            set_cap(cap.id, cap.value.clone())
+        }
+        if let Some(device_roles) = &self.device_roles {
+            let roles = device_roles
+                .iter()
+                .fold(0u16, |roles, role| roles | (1 << *role as u16));
+            set_cap(
+                CapTlvType::SupportedDeviceRoles,
+                roles.to_le_bytes().to_vec(),
+            )
+        }
+        caps
+    }
+
+    /// Return true if the capability is set by the profile itself.
+    pub fn has_cap(&self, t: CapTlvType) -> bool {
+        self.caps.iter().any(|cap| cap.id == t)
+            || (t == CapTlvType::SupportedDeviceRoles && self.device_roles.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_profile() {
+        let profile: DeviceProfile = serde_json::from_str(
+            r#"{
+                "uci_version": "1.1",
+                "vendor_spec_info": "cafe",
+                "device_roles": ["initiator", "responder", "dt-tag"],
+                "caps": [ { "id": 11, "value": "28" }, { "id": 224, "value": "01" } ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(profile.uci_version, UciVersion::V1_1);
+        assert_eq!(profile.mac_version, DeviceProfile::default().mac_version);
+        assert_eq!(profile.vendor_spec_info, vec![0xca, 0xfe]);
+
+        let caps = profile.caps();
+        let cap = |t: CapTlvType| caps.iter().find(|cap| cap.t == t).map(|cap| cap.v.clone());
+        assert_eq!(caps.len(), DEFAULT_CAPS_INFO.len() + 1);
+        assert_eq!(cap(CapTlvType::SupportedChannels), Some(vec![0x28]));
+        assert_eq!(
+            cap(CapTlvType::SupportedDeviceRoles),
+            Some(vec![0x03, 0x01])
+        );
+        assert!(profile.has_cap(CapTlvType::SupportedDeviceRoles));
+        assert!(!profile.has_cap(CapTlvType::SupportedAoa));
+
+        let profile: DeviceProfile = serde_json::from_str(
+            r#"{ "default_caps": false, "caps": [ { "id": 0, "value": "0102" } ] }"#,
+        )
+        .unwrap();
+        assert_eq!(profile.caps().len(), 1);
+
+        assert!(serde_json::from_str::<DeviceProfile>(r#"{ "mac_version": "02" }"#).is_err());
+        assert!(serde_json::from_str::<DeviceProfile>(r#"{ "uci_version": "3.0" }"#).is_err());
+        assert!(serde_json::from_str::<DeviceProfile>(r#"{ "roles": [] }"#).is_err());
+    }
+}
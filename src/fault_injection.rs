@@ -0,0 +1,411 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::clock::Clock;
+use crate::packets::uci;
+use crate::stochastic_estimator::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Direction of the packets affected by a fault.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FaultDirection {
+    /// Packets sent by Pica to the host.
+    #[default]
+    ToHost,
+    /// Packets sent by the host to Pica.
+    FromHost,
+}
+
+/// Message type of the packets affected by a fault.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FaultMessageType {
+    Data,
+    Command,
+    Response,
+    Notification,
+}
+
+/// Fault applied to the matching packets.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(tag = "action", rename_all = "kebab-case")]
+pub enum FaultAction {
+    /// Discard the packet.
+    Drop,
+    /// Delay the packet, and the packets following it on the transport.
+    Delay { delay_ms: u64 },
+    /// Send the packet twice.
+    Duplicate,
+    /// Hold the packet, and send it after the next packet.
+    Reorder,
+    /// Flip the bits of a random byte of the packet payload.
+    Corrupt,
+    /// Segment the packet with fragments of the selected payload size.
+    /// Only applies to the packets sent to the host.
+    Fragment { fragment_size: usize },
+}
+
+/// Fault injected on the UCI transport of a device.
+/// The fault applies to the packets matching the direction, message type,
+/// group identifier and opcode identifier, with the selected probability.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Fault {
+    #[serde(flatten)]
+    pub action: FaultAction,
+    #[serde(default)]
+    pub direction: FaultDirection,
+    /// Probability (0-1) of applying the fault to a matching packet.
+    #[serde(default = "default_rate")]
+    pub rate: f64,
+    pub message_type: Option<FaultMessageType>,
+    pub gid: Option<u8>,
+    pub oid: Option<u8>,
+}
+
+fn default_rate() -> f64 {
+    1.0
+}
+
+/// Unsolicited notification injected to the host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultNotification {
+    /// CORE_GENERIC_ERROR_NTF with the selected status.
+    GenericError(uci::Status),
+    /// CORE_DEVICE_STATUS_NTF with the state DEVICE_STATE_ERROR.
+    DeviceStateError,
+}
+
+impl Fault {
+    fn matches(&self, direction: FaultDirection, packet: &[u8]) -> bool {
+        let mt = match uci::parse_message_type(packet[0]) {
+            uci::MessageType::Data => FaultMessageType::Data,
+            uci::MessageType::Command => FaultMessageType::Command,
+            uci::MessageType::Response => FaultMessageType::Response,
+            uci::MessageType::Notification => FaultMessageType::Notification,
+        };
+        let is_control = mt != FaultMessageType::Data;
+        self.direction == direction
+            && self
+                .message_type
+                .iter()
+                .all(|&message_type| message_type == mt)
+            && self
+                .gid
+                .iter()
+                .all(|&gid| is_control && gid == packet[0] & 0xf)
+            && self
+                .oid
+                .iter()
+                .all(|&oid| is_control && oid == packet[1] & 0x3f)
+    }
+}
+
+/// Packets released by the fault injector.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct FaultOutput {
+    /// Packets to send, in order.
+    pub packets: Vec<Vec<u8>>,
+    /// Delay of the packets, measured by the pica clock.
+    pub delay: Duration,
+    /// Fragment payload size overriding the default segmentation.
+    pub fragment_size: Option<usize>,
+}
+
+struct FaultState {
+    faults: Vec<Fault>,
+    rng: Rng,
+    /// Packets held by the reorder fault, for each direction.
+    reordered: [Option<Vec<u8>>; 2],
+}
+
+/// Fault injection layer of the UCI transport of a device.
+/// The injector is shared by the device and its read and write routines,
+/// and the faults can be modified while the device is connected.
+#[derive(Clone)]
+pub(crate) struct FaultInjector(Arc<Mutex<FaultState>>);
+
+impl FaultInjector {
+    /// Create a fault injector without faults. The random sequence
+    /// deciding which packets are affected is seeded with the provided
+    /// value.
+    pub fn new(seed: u64) -> Self {
+        FaultInjector(Arc::new(Mutex::new(FaultState {
+            faults: vec![],
+            rng: Rng(seed),
+            reordered: Default::default(),
+        })))
+    }
+
+    /// Replace the faults injected on the transport.
+    /// The packets held by reorder faults are released with
+    /// the next packets.
+    pub fn set_faults(&self, faults: Vec<Fault>) {
+        self.0.lock().unwrap().faults = faults;
+    }
+
+    /// Apply the faults to a complete packet sent in the selected direction.
+    pub fn apply(&self, direction: FaultDirection, mut packet: Vec<u8>) -> FaultOutput {
+        let mut state = self.0.lock().unwrap();
+        let FaultState {
+            faults,
+            rng,
+            reordered,
+        } = &mut *state;
+        let reordered = &mut reordered[direction as usize];
+
+        let mut output = FaultOutput::default();
+        let mut duplicate = false;
+        let mut reorder = false;
+
+        for fault in faults.iter() {
+            if !fault.matches(direction, &packet) || rng.uniform() > fault.rate {
+                continue;
+            }
+            match fault.action {
+                FaultAction::Drop => return output,
+                FaultAction::Delay { delay_ms } => output.delay += Duration::from_millis(delay_ms),
+                FaultAction::Duplicate => duplicate = true,
+                FaultAction::Reorder => reorder = true,
+                FaultAction::Corrupt => corrupt(&mut packet, rng),
+                FaultAction::Fragment { fragment_size } => {
+                    output.fragment_size = Some(fragment_size.max(1))
+                }
+            }
+        }
+
+        if reorder && reordered.is_none() {
+            *reordered = Some(packet);
+            return output;
+        }
+
+        if duplicate {
+            output.packets.push(packet.clone());
+        }
+        output.packets.push(packet);
+        output.packets.extend(reordered.take());
+        output
+    }
+}
+
+/// Packets delayed on the UCI transport of a device. The packets are
+/// released in order by timers of the pica clock, and the packets
+/// following a delayed packet are held until it is released.
+pub(crate) struct DelayQueue<T> {
+    clock: Clock,
+    /// Packets waiting for the expiration of their timer.
+    queued: Arc<Mutex<VecDeque<T>>>,
+    release_tx: mpsc::UnboundedSender<T>,
+    release_rx: mpsc::UnboundedReceiver<T>,
+    /// Number of packets queued or released, and not yet received.
+    pending: usize,
+    /// Clock time at which the last queued packet is released.
+    release_time: Duration,
+}
+
+impl<T: Send + 'static> DelayQueue<T> {
+    pub fn new(clock: Clock) -> Self {
+        let (release_tx, release_rx) = mpsc::unbounded_channel();
+        DelayQueue {
+            clock,
+            queued: Default::default(),
+            release_tx,
+            release_rx,
+            pending: 0,
+            release_time: Duration::ZERO,
+        }
+    }
+
+    /// Delay a packet. The packet is returned immediately when not
+    /// delayed and no packet is pending, or else released with
+    /// [`DelayQueue::recv`] after the selected delay.
+    pub fn push(&mut self, delay: Duration, packet: T) -> Option<T> {
+        if delay.is_zero() && self.pending == 0 {
+            return Some(packet);
+        }
+
+        let now = self.clock.now();
+        self.release_time = self.release_time.max(now + delay);
+        self.queued.lock().unwrap().push_back(packet);
+        self.pending += 1;
+
+        // The packets are released in order by the timers, regardless
+        // of the order of expiration of the timers with the same deadline.
+        // The timer is never cancelled.
+        let queued = self.queued.clone();
+        let release_tx = self.release_tx.clone();
+        self.clock.schedule(
+            self.release_time - now,
+            None,
+            Box::new(move || {
+                let mut queued = queued.lock().unwrap();
+                if let Some(packet) = queued.pop_front() {
+                    let _ = release_tx.send(packet);
+                }
+                None
+            }),
+        );
+        None
+    }
+
+    /// Receive the next released packet.
+    pub async fn recv(&mut self) -> T {
+        // The queue holds a sender, the channel is never closed.
+        let packet = self.release_rx.recv().await.unwrap();
+        self.pending -= 1;
+        packet
+    }
+}
+
+/// Flip the bits of a random byte of the payload, or of the opcode
+/// identifier if the payload is empty. The header is otherwise preserved
+/// so that the packet can still be segmented.
+fn corrupt(packet: &mut [u8], rng: &mut Rng) {
+    let random = rng.next_u64();
+    let index = if packet.len() > uci::HEADER_SIZE {
+        uci::HEADER_SIZE + (random as usize % (packet.len() - uci::HEADER_SIZE))
+    } else {
+        1
+    };
+    packet[index] ^= ((random >> 32) as u8).max(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pdl_runtime::Packet;
+
+    fn fault(value: serde_json::Value) -> Fault {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn notification(session_id: u32) -> Vec<u8> {
+        uci::SessionStatusNtf {
+            session_token: session_id,
+            session_state: uci::SessionState::SessionStateIdle,
+            reason_code: 0,
+        }
+        .encode_to_vec()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_filter() {
+        let injector = FaultInjector::new(0);
+        injector.set_faults(vec![fault(serde_json::json!({
+            "action": "drop",
+            "message_type": "notification",
+            "gid": 1,
+        }))]);
+
+        let packet = notification(1);
+        assert!(injector
+            .apply(FaultDirection::ToHost, packet.clone())
+            .packets
+            .is_empty());
+        assert_eq!(
+            injector
+                .apply(FaultDirection::FromHost, packet.clone())
+                .packets,
+            vec![packet]
+        );
+
+        let packet = uci::CoreGenericErrorNtf {
+            status: uci::Status::Failed,
+        }
+        .encode_to_vec()
+        .unwrap();
+        assert_eq!(
+            injector
+                .apply(FaultDirection::ToHost, packet.clone())
+                .packets,
+            vec![packet]
+        );
+    }
+
+    #[test]
+    fn test_actions() {
+        let injector = FaultInjector::new(0);
+        let (first, second) = (notification(1), notification(2));
+
+        injector.set_faults(vec![fault(serde_json::json!({ "action": "duplicate" }))]);
+        let output = injector.apply(FaultDirection::ToHost, first.clone());
+        assert_eq!(output.packets, vec![first.clone(), first.clone()]);
+
+        injector.set_faults(vec![fault(serde_json::json!({ "action": "reorder" }))]);
+        assert!(injector
+            .apply(FaultDirection::ToHost, first.clone())
+            .packets
+            .is_empty());
+        let output = injector.apply(FaultDirection::ToHost, second.clone());
+        assert_eq!(output.packets, vec![second.clone(), first.clone()]);
+
+        injector.set_faults(vec![
+            fault(serde_json::json!({ "action": "corrupt" })),
+            fault(serde_json::json!({ "action": "delay", "delay_ms": 20 })),
+            fault(serde_json::json!({ "action": "fragment", "fragment_size": 3 })),
+        ]);
+        let output = injector.apply(FaultDirection::ToHost, first.clone());
+        assert_eq!(output.delay, Duration::from_millis(20));
+        assert_eq!(output.fragment_size, Some(3));
+        assert_eq!(output.packets.len(), 1);
+        assert_eq!(
+            output.packets[0][..uci::HEADER_SIZE],
+            first[..uci::HEADER_SIZE]
+        );
+        assert_ne!(output.packets[0], first);
+
+        injector.set_faults(vec![fault(
+            serde_json::json!({ "action": "drop", "rate": 0.5 }),
+        )]);
+        let delivered = (0..1000)
+            .filter(|_| {
+                !injector
+                    .apply(FaultDirection::ToHost, first.clone())
+                    .packets
+                    .is_empty()
+            })
+            .count();
+        assert!((400..600).contains(&delivered), "delivered {}", delivered);
+    }
+
+    #[tokio::test]
+    async fn test_delay_queue() {
+        let (pica_tx, _) = mpsc::channel(1);
+        let clock = Clock::new(crate::clock::ClockMode::Step, pica_tx);
+        let mut delayed = DelayQueue::new(clock.clone());
+
+        assert_eq!(delayed.push(Duration::ZERO, 1), Some(1));
+
+        // The packets following a delayed packet are held,
+        // including the packets delayed for a shorter time.
+        assert_eq!(delayed.push(Duration::from_millis(20), 2), None);
+        assert_eq!(delayed.push(Duration::ZERO, 3), None);
+        assert_eq!(delayed.push(Duration::from_millis(10), 4), None);
+
+        // The packets are released in order when the clock advances.
+        assert!(clock.advance().is_some());
+        assert_eq!(clock.now(), Duration::from_millis(20));
+        assert!(clock.advance().is_none());
+        assert_eq!(delayed.recv().await, 2);
+        assert_eq!(delayed.recv().await, 3);
+        assert_eq!(delayed.recv().await, 4);
+
+        assert_eq!(delayed.push(Duration::ZERO, 5), Some(5));
+    }
+}
@@ -19,6 +19,7 @@ use std::collections::HashMap;
 use std::fmt::Display;
 use std::path::PathBuf;
 use std::pin::Pin;
+use std::sync::Arc;
 use thiserror::Error;
 use tokio::sync::{broadcast, mpsc, oneshot};
 
@@ -27,17 +28,49 @@ mod pcapng;
 
 use packets::uci::{self, *};
 
+mod clock;
+use clock::Clock;
+pub use clock::ClockMode;
+
+mod outbound_queue;
+use outbound_queue::OutboundQueue;
+
 mod device;
-use device::{Device, MAX_DEVICE, MAX_SESSION};
+use device::{Device, DEFAULT_MAX_SESSION};
 
 mod session;
 
 mod mac_address;
 pub use mac_address::MacAddress;
 
+mod device_binding;
+pub use device_binding::{ConnectionHint, DeviceBinding, DeviceDeclaration};
+
+mod device_profile;
+pub use device_profile::{
+    DeviceProfile, ProfileCap, SupportedDeviceRole, UciVersion, DEFAULT_PROFILE,
+};
+
 mod app_config;
 pub use app_config::AppConfig;
 
+mod test_mode;
+
+mod fault_injection;
+use fault_injection::{DelayQueue, FaultInjector};
+pub use fault_injection::{
+    Fault, FaultAction, FaultDirection, FaultMessageType, FaultNotification,
+};
+
+mod link_budget;
+pub use link_budget::LinkBudget;
+
+mod stochastic_estimator;
+pub use stochastic_estimator::{NoiseModel, StochasticRangingEstimator};
+
+mod uwbs_clock;
+pub use uwbs_clock::UwbsClock;
+
 pub type UciPacket = Vec<u8>;
 pub type UciStream = Pin<Box<dyn futures::stream::Stream<Item = Vec<u8>> + Send>>;
 pub type UciSink = Pin<Box<dyn futures::sink::Sink<Vec<u8>, Error = anyhow::Error> + Send>>;
@@ -48,11 +81,75 @@ pub type UciSink = Pin<Box<dyn futures::sink::Sink<Vec<u8>, Error = anyhow::Erro
 pub type Handle = usize;
 
 /// Ranging measurement produced by a ranging estimator.
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct RangingMeasurement {
     pub range: u16,
     pub azimuth: i16,
     pub elevation: i8,
+    /// Set if the measurement was made in Non Line Of Sight.
+    pub nlos: bool,
+    /// Figure of merit of the azimuth, from 0 to 100.
+    pub azimuth_fom: u8,
+    /// Figure of merit of the elevation, from 0 to 100.
+    pub elevation_fom: u8,
+    /// Received signal strength, in -dBm Q7.1 format.
+    pub rssi: u8,
+    /// Index of the slot in which the measurement was made.
+    pub slot_index: u8,
+    /// Status of the measurement. The other fields are ignored
+    /// when the status is not `Ok`, e.g. for `RangingRxTimeout`.
+    pub status: uci::Status,
+}
+
+impl Default for RangingMeasurement {
+    fn default() -> Self {
+        RangingMeasurement {
+            range: 0,
+            azimuth: 0,
+            elevation: 0,
+            nlos: false,
+            azimuth_fom: 100,
+            elevation_fom: 100,
+            rssi: u8::MAX,
+            slot_index: 0,
+            status: uci::Status::Ok,
+        }
+    }
+}
+
+impl RangingMeasurement {
+    /// Failed measurement reported with the selected status.
+    pub fn failed(status: uci::Status) -> Self {
+        RangingMeasurement {
+            azimuth_fom: 0,
+            elevation_fom: 0,
+            status,
+            ..Default::default()
+        }
+    }
+}
+
+/// Location of a device in the emulated space, in centimeters.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+}
+
+/// Session context in which a ranging measurement is estimated,
+/// provided to the ranging estimator.
+#[derive(Clone, Copy)]
+pub struct RangingContext<'a> {
+    pub session_id: u32,
+    pub session_type: SessionType,
+    /// Application configuration of the session of the measuring device.
+    pub app_config: &'a AppConfig,
+    /// Index of the ranging round, counted from the session creation.
+    pub round_index: u32,
+    /// Time of the ranging round on the pica clock, counted from
+    /// the start of the emulation.
+    pub time: std::time::Duration,
 }
 
 /// Trait matching the capabilities of a ranging estimator.
@@ -60,12 +157,27 @@ pub struct RangingMeasurement {
 /// the algorithm used to generate the ranging measurements.
 pub trait RangingEstimator: Send + Sync {
     /// Evaluate the ranging measurement for the two input devices
-    /// identified by their respective handle. The result is a triplet
-    /// containing the range, azimuth, and elevation of the right device
-    /// relative to the left device.
+    /// identified by their respective handle. The result contains the
+    /// range, azimuth, and elevation of the right device relative to the
+    /// left device, as well as the quality indicators of the measurement.
     /// Return `None` if the measurement could not be estimated, e.g. because
-    /// the devices are out of range.
-    fn estimate(&self, left: &Handle, right: &Handle) -> Option<RangingMeasurement>;
+    /// the devices are out of range; two way ranging measurements are then
+    /// reported with the status `RangingRxTimeout`. Other failures can be
+    /// reported by returning a measurement with a failed status.
+    /// The context describes the session of the left device.
+    fn estimate(
+        &self,
+        left: &Handle,
+        right: &Handle,
+        context: &RangingContext,
+    ) -> Option<RangingMeasurement>;
+
+    /// Return the location of the selected device, if known.
+    /// The location of the anchors is reported to DT-Tags
+    /// in DL-TDoA measurements.
+    fn location(&self, _device: &Handle) -> Option<Location> {
+        None
+    }
 }
 
 /// Pica emulation environment.
@@ -80,27 +192,49 @@ pub struct Pica {
     event_tx: broadcast::Sender<PicaEvent>,
     ranging_estimator: Box<dyn RangingEstimator>,
     pcapng_dir: Option<PathBuf>,
+    clock: Clock,
+    link_budget: Option<LinkBudget>,
+    /// UCI devices declared with a fixed MAC address.
+    declared_devices: Vec<DeviceDeclaration>,
+    /// Maximum number of connected UCI devices, unlimited if not set.
+    max_device: Option<usize>,
+    /// Maximum number of sessions per UCI device.
+    max_session: usize,
+    /// Capability profiles of the UCI devices, by name.
+    device_profiles: HashMap<String, Arc<DeviceProfile>>,
+    /// Assign extended MAC addresses to the undeclared UCI devices.
+    extended_mac_addresses: bool,
 }
 
+/// Capacity of the pica command channel.
+const COMMAND_CHANNEL_CAPACITY: usize = 1024;
+
 #[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum PicaCommandError {
     #[error("Device already exists: {0}")]
     DeviceAlreadyExists(MacAddress),
     #[error("Device not found: {0}")]
     DeviceNotFound(MacAddress),
+    #[error("Device profile not found: {0}")]
+    ProfileNotFound(String),
 }
 
 pub enum PicaCommand {
     // Connect a new device.
     Connect(UciStream, UciSink),
+    // Connect a new device, with a hint selecting the MAC address
+    // of a declared device.
+    ConnectWithHint(UciStream, UciSink, ConnectionHint),
     // Disconnect the selected device.
     Disconnect(usize),
-    // Execute ranging command for selected device and session.
+    // Execute ranging command for selected device and session handle.
     Ranging(usize, u32),
     // Send an in-band request to stop ranging to a peer controlee identified by address and session id.
     StopRanging(MacAddress, u32),
     // UCI packet received for the selected device.
     UciPacket(usize, Vec<u8>),
+    // Complete the test running for selected device and session handle.
+    Test(usize, u32),
     // Create Anchor
     CreateAnchor(
         MacAddress,
@@ -111,18 +245,68 @@ pub enum PicaCommand {
         MacAddress,
         oneshot::Sender<Result<Handle, PicaCommandError>>,
     ),
+    // Advance the virtual clock to the next timer expiration, and
+    // return the current virtual time.
+    Step(oneshot::Sender<std::time::Duration>),
+    // Run an action at the selected time of the pica clock, measured
+    // from the start of pica. The command returned by the action, if any,
+    // is executed by pica.
+    Schedule(
+        std::time::Duration,
+        Box<dyn FnOnce() -> Option<PicaCommand> + Send>,
+    ),
+    // Replace the faults injected on the UCI transport of the selected device.
+    SetFaults(
+        MacAddress,
+        Vec<Fault>,
+        oneshot::Sender<Result<Handle, PicaCommandError>>,
+    ),
+    // Set the offset and drift of the UWBS clock of the selected device.
+    SetUwbsClock(
+        MacAddress,
+        UwbsClock,
+        oneshot::Sender<Result<Handle, PicaCommandError>>,
+    ),
+    // Put the selected device in the error state, until it is reset by the host.
+    SetDeviceError(
+        MacAddress,
+        oneshot::Sender<Result<Handle, PicaCommandError>>,
+    ),
+    // Declare a UCI device with a fixed MAC address.
+    DeclareDevice(
+        DeviceDeclaration,
+        oneshot::Sender<Result<(), PicaCommandError>>,
+    ),
+    // Return the MAC addresses of the declared and connected UCI devices.
+    GetDeviceBindings(oneshot::Sender<Vec<DeviceBinding>>),
+    // Send an unsolicited notification to the selected device.
+    InjectNotification(
+        MacAddress,
+        FaultNotification,
+        oneshot::Sender<Result<Handle, PicaCommandError>>,
+    ),
 }
 
 impl Display for PicaCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let cmd = match self {
             PicaCommand::Connect(_, _) => "Connect",
+            PicaCommand::ConnectWithHint(_, _, _) => "ConnectWithHint",
             PicaCommand::Disconnect(_) => "Disconnect",
             PicaCommand::Ranging(_, _) => "Ranging",
             PicaCommand::StopRanging(_, _) => "StopRanging",
             PicaCommand::UciPacket(_, _) => "UciPacket",
+            PicaCommand::Test(_, _) => "Test",
             PicaCommand::CreateAnchor(_, _) => "CreateAnchor",
             PicaCommand::DestroyAnchor(_, _) => "DestroyAnchor",
+            PicaCommand::Step(_) => "Step",
+            PicaCommand::Schedule(_, _) => "Schedule",
+            PicaCommand::SetFaults(_, _, _) => "SetFaults",
+            PicaCommand::SetUwbsClock(_, _, _) => "SetUwbsClock",
+            PicaCommand::SetDeviceError(_, _) => "SetDeviceError",
+            PicaCommand::DeclareDevice(_, _) => "DeclareDevice",
+            PicaCommand::GetDeviceBindings(_) => "GetDeviceBindings",
+            PicaCommand::InjectNotification(_, _, _) => "InjectNotification",
         };
         write!(f, "{}", cmd)
     }
@@ -156,47 +340,456 @@ struct Anchor {
     mac_address: MacAddress,
 }
 
+/// DL-TDoA timestamps are expressed in units of 1/(128 * 499.2 MHz),
+/// about 15.65 ps.
+const DL_TDOA_TICKS_PER_MS: u64 = 63_897_600;
+/// The ranging scheduling time unit (RSTU) is 416 chips, i.e. 1/1.2 µs.
+const DL_TDOA_TICKS_PER_RSTU: u64 = 53_248;
+/// Speed of light, in centimeters per DL-TDoA timestamp unit.
+const SPEED_OF_LIGHT_CM_PER_TICK: f64 = 29.979_245_8 / 63.897_6;
+
+/// Test mode timestamps are expressed in units of 1/124.8 MHz,
+/// with a 16-bit fractional part.
+const TEST_TICKS_PER_US: f64 = 124.8;
+/// Speed of light, in centimeters per test mode timestamp unit.
+const SPEED_OF_LIGHT_CM_PER_TEST_TICK: f64 = 29.979_245_8 * 1000.0 / 124.8;
+
+/// Split a test mode timestamp into its integer and fractional parts.
+fn make_test_timestamp(ticks: f64) -> (u32, u16) {
+    (ticks as u32, (ticks.fract() * 65536.0) as u16)
+}
+
+/// Count the bit errors between the transmitted and received PSDU.
+fn psdu_bit_errors(tx_psdu: &[u8], rx_psdu: &[u8]) -> u32 {
+    let length_difference = tx_psdu.len().abs_diff(rx_psdu.len()) as u32;
+    tx_psdu
+        .iter()
+        .zip(rx_psdu)
+        .map(|(tx, rx)| (tx ^ rx).count_ones())
+        .sum::<u32>()
+        + 8 * length_difference
+}
+
+/// Location of a DT-Anchor, as reported in DL-TDoA measurements.
+#[derive(Clone, Debug)]
+enum DtAnchorLocation {
+    Wgs84(Wgs84Location),
+    Relative(RelativeLocation),
+}
+
+impl DtAnchorLocation {
+    /// Parse the DL_TDOA_ANCHOR_LOCATION app configuration.
+    fn from_app_config(value: &[u8]) -> Option<Self> {
+        match value.split_first()? {
+            (0x00, data) => Some(DtAnchorLocation::Wgs84(Wgs84Location {
+                data: data.try_into().ok()?,
+            })),
+            (0x01, data) => RelativeLocation::decode_full(data)
+                .ok()
+                .map(DtAnchorLocation::Relative),
+            _ => None,
+        }
+    }
+}
+
+impl From<Location> for DtAnchorLocation {
+    fn from(location: Location) -> Self {
+        DtAnchorLocation::Relative(RelativeLocation {
+            x: location.x as u32 & 0xfff_ffff,
+            y: location.y as u32 & 0xfff_ffff,
+            z: location.z as u32 & 0xff_ffff,
+        })
+    }
+}
+
+/// Anchor transmitting DL-TDoA messages in a DT-Tag session.
+struct DtAnchor {
+    handle: Handle,
+    mac_address: MacAddress,
+    ranging_rounds: Vec<DtAnchorRangingRound>,
+    location: Option<DtAnchorLocation>,
+    tx_timestamp_conf: u8,
+    report_active_ranging_rounds: bool,
+}
+
 fn make_measurement(
     mac_address: &MacAddress,
     local: RangingMeasurement,
     remote: RangingMeasurement,
 ) -> ShortAddressTwoWayRangingMeasurement {
-    if let MacAddress::Short(address) = mac_address {
-        ShortAddressTwoWayRangingMeasurement {
-            mac_address: u16::from_le_bytes(*address),
-            status: uci::Status::Ok,
-            nlos: 0, // in Line Of Sight
-            distance: local.range,
-            aoa_azimuth: local.azimuth as u16,
-            aoa_azimuth_fom: 100, // Yup, pretty sure about this
-            aoa_elevation: local.elevation as u16,
-            aoa_elevation_fom: 100, // Yup, pretty sure about this
-            aoa_destination_azimuth: remote.azimuth as u16,
-            aoa_destination_azimuth_fom: 100,
-            aoa_destination_elevation: remote.elevation as u16,
-            aoa_destination_elevation_fom: 100,
-            slot_index: 0,
-            rssi: u8::MAX,
+    ShortAddressTwoWayRangingMeasurement {
+        mac_address: u16::from_le_bytes(mac_address.short_address()),
+        status: local.status,
+        nlos: local.nlos.into(),
+        distance: local.range,
+        aoa_azimuth: local.azimuth as u16,
+        aoa_azimuth_fom: local.azimuth_fom,
+        aoa_elevation: local.elevation as u16,
+        aoa_elevation_fom: local.elevation_fom,
+        aoa_destination_azimuth: remote.azimuth as u16,
+        aoa_destination_azimuth_fom: remote.azimuth_fom,
+        aoa_destination_elevation: remote.elevation as u16,
+        aoa_destination_elevation_fom: remote.elevation_fom,
+        slot_index: local.slot_index,
+        rssi: local.rssi,
+    }
+}
+
+fn make_extended_measurement(
+    mac_address: &MacAddress,
+    local: RangingMeasurement,
+    remote: RangingMeasurement,
+) -> ExtendedAddressTwoWayRangingMeasurement {
+    ExtendedAddressTwoWayRangingMeasurement {
+        mac_address: u64::from(mac_address),
+        status: local.status,
+        nlos: local.nlos.into(),
+        distance: local.range,
+        aoa_azimuth: local.azimuth as u16,
+        aoa_azimuth_fom: local.azimuth_fom,
+        aoa_elevation: local.elevation as u16,
+        aoa_elevation_fom: local.elevation_fom,
+        aoa_destination_azimuth: remote.azimuth as u16,
+        aoa_destination_azimuth_fom: remote.azimuth_fom,
+        aoa_destination_elevation: remote.elevation as u16,
+        aoa_destination_elevation_fom: remote.elevation_fom,
+        slot_index: local.slot_index,
+        rssi: local.rssi,
+    }
+}
+
+/// Build the two way ranging session info notification for the
+/// selected session. The MAC address format of the measurements is
+/// selected by the MAC_ADDRESS_MODE of the session.
+fn make_two_way_session_info_ntf(
+    session: &session::Session,
+    measurements: Vec<(MacAddress, RangingMeasurement, RangingMeasurement)>,
+) -> UciPacket {
+    match session.app_config.mac_address_mode {
+        MacAddressMode::Mode0 => ShortMacTwoWaySessionInfoNtf {
+            sequence_number: session.sequence_number,
+            session_token: session.handle,
+            rcr_indicator: 0,            //TODO
+            current_ranging_interval: 0, //TODO
+            two_way_ranging_measurements: measurements
+                .iter()
+                .map(|(mac_address, local, remote)| make_measurement(mac_address, *local, *remote))
+                .collect(),
+            vendor_data: vec![],
         }
+        .encode_to_vec()
+        .unwrap(),
+        MacAddressMode::Mode1 | MacAddressMode::Mode2 => ExtendedMacTwoWaySessionInfoNtf {
+            sequence_number: session.sequence_number,
+            session_token: session.handle,
+            rcr_indicator: 0,            //TODO
+            current_ranging_interval: 0, //TODO
+            two_way_ranging_measurements: measurements
+                .iter()
+                .map(|(mac_address, local, remote)| {
+                    make_extended_measurement(mac_address, *local, *remote)
+                })
+                .collect(),
+            vendor_data: vec![],
+        }
+        .encode_to_vec()
+        .unwrap(),
+    }
+}
+
+/// Segment an application data message delivered to a peer session in
+/// DATA_MESSAGE_RCV packets, with payloads not exceeding the maximum data
+/// packet payload size. All the segments carry the fields of the message.
+fn make_data_message_rcv(
+    session_handle: u32,
+    source_address: u64,
+    message: &session::DataMessage,
+) -> Vec<UciPacket> {
+    // Size of the fields preceding the application data: session handle,
+    // status, source address, sequence number and application data length.
+    const DATA_MESSAGE_RCV_FIELDS_SIZE: usize = 17;
+    let segments: Vec<&[u8]> = if message.application_data.is_empty() {
+        vec![&[]]
     } else {
-        panic!("Extended address is not supported.")
+        message
+            .application_data
+            .chunks(MAX_DATA_PACKET_PAYLOAD_SIZE - DATA_MESSAGE_RCV_FIELDS_SIZE)
+            .collect()
+    };
+    let last_index = segments.len() - 1;
+    segments
+        .into_iter()
+        .enumerate()
+        .map(|(index, application_data)| {
+            DataMessageRcv {
+                application_data: application_data.to_vec(),
+                data_sequence_number: message.sequence_number,
+                pbf: if index == last_index {
+                    PacketBoundaryFlag::Complete
+                } else {
+                    PacketBoundaryFlag::NotComplete
+                },
+                session_handle,
+                source_address,
+                status: uci::Status::Ok,
+            }
+            .encode_to_vec()
+            .unwrap()
+        })
+        .collect()
+}
+
+fn make_dl_tdoa_session_info_ntf(
+    session: &session::Session,
+    measurements: Vec<(MacAddress, DlTdoaRangingMeasurement)>,
+) -> UciPacket {
+    match session.app_config.mac_address_mode {
+        MacAddressMode::Mode0 => ShortMacDlTDoASessionInfoNtf {
+            sequence_number: session.sequence_number,
+            session_token: session.handle,
+            rcr_indicator: 0,            //TODO
+            current_ranging_interval: 0, //TODO
+            dl_tdoa_measurements: measurements
+                .into_iter()
+                .map(
+                    |(mac_address, measurement)| ShortAddressDlTdoaRangingMeasurement {
+                        mac_address: u16::from_le_bytes(mac_address.short_address()),
+                        measurement,
+                    },
+                )
+                .collect(),
+        }
+        .encode_to_vec()
+        .unwrap(),
+        MacAddressMode::Mode1 | MacAddressMode::Mode2 => ExtendedMacDlTDoASessionInfoNtf {
+            sequence_number: session.sequence_number,
+            session_token: session.handle,
+            rcr_indicator: 0,            //TODO
+            current_ranging_interval: 0, //TODO
+            dl_tdoa_measurements: measurements
+                .into_iter()
+                .map(
+                    |(mac_address, measurement)| ExtendedAddressDlTdoaRangingMeasurement {
+                        mac_address: u64::from(mac_address),
+                        measurement,
+                    },
+                )
+                .collect(),
+        }
+        .encode_to_vec()
+        .unwrap(),
+    }
+}
+
+fn make_owr_aoa_session_info_ntf(
+    session: &session::Session,
+    round_index: u32,
+    measurements: Vec<(MacAddress, RangingMeasurement)>,
+) -> UciPacket {
+    match session.app_config.mac_address_mode {
+        MacAddressMode::Mode0 => ShortMacOwrAoaSessionInfoNtf {
+            sequence_number: session.sequence_number,
+            session_token: session.handle,
+            rcr_indicator: 0,            //TODO
+            current_ranging_interval: 0, //TODO
+            owr_aoa_ranging_measurements: measurements
+                .into_iter()
+                .map(
+                    |(mac_address, local)| ShortAddressOwrAoaRangingMeasurement {
+                        mac_address: u16::from_le_bytes(mac_address.short_address()),
+                        status: local.status,
+                        nlos: local.nlos.into(),
+                        frame_sequence_number: round_index as u8,
+                        block_index: round_index as u16,
+                        aoa_azimuth: local.azimuth as u16,
+                        aoa_azimuth_fom: local.azimuth_fom,
+                        aoa_elevation: local.elevation as u16,
+                        aoa_elevation_fom: local.elevation_fom,
+                    },
+                )
+                .collect(),
+            vendor_data: vec![],
+        }
+        .encode_to_vec()
+        .unwrap(),
+        MacAddressMode::Mode1 | MacAddressMode::Mode2 => ExtendedMacOwrAoaSessionInfoNtf {
+            sequence_number: session.sequence_number,
+            session_token: session.handle,
+            rcr_indicator: 0,            //TODO
+            current_ranging_interval: 0, //TODO
+            owr_aoa_ranging_measurements: measurements
+                .into_iter()
+                .map(
+                    |(mac_address, local)| ExtendedAddressOwrAoaRangingMeasurement {
+                        mac_address: u64::from(mac_address),
+                        status: local.status,
+                        nlos: local.nlos.into(),
+                        frame_sequence_number: round_index as u8,
+                        block_index: round_index as u16,
+                        aoa_azimuth: local.azimuth as u16,
+                        aoa_azimuth_fom: local.azimuth_fom,
+                        aoa_elevation: local.elevation as u16,
+                        aoa_elevation_fom: local.elevation_fom,
+                    },
+                )
+                .collect(),
+            vendor_data: vec![],
+        }
+        .encode_to_vec()
+        .unwrap(),
     }
 }
 
 impl Pica {
     pub fn new(ranging_estimator: Box<dyn RangingEstimator>, pcapng_dir: Option<PathBuf>) -> Self {
-        let (command_tx, command_rx) = mpsc::channel(MAX_SESSION * MAX_DEVICE);
+        let (command_tx, command_rx) = mpsc::channel(COMMAND_CHANNEL_CAPACITY);
         let (event_tx, _) = broadcast::channel(16);
         Pica {
             devices: HashMap::new(),
             anchors: HashMap::new(),
             counter: 0,
             command_rx: Some(command_rx),
+            clock: Clock::new(ClockMode::default(), command_tx.clone()),
             command_tx,
             event_tx,
             ranging_estimator,
             pcapng_dir,
+            link_budget: None,
+            declared_devices: vec![],
+            max_device: None,
+            max_session: DEFAULT_MAX_SESSION,
+            device_profiles: HashMap::new(),
+            extended_mac_addresses: false,
+        }
+    }
+
+    /// Limit the number of connected UCI devices. New connections are
+    /// rejected when the limit is reached. The number of devices is
+    /// not limited by default.
+    pub fn with_max_device(mut self, max_device: usize) -> Self {
+        self.max_device = Some(max_device);
+        self
+    }
+
+    /// Select the maximum number of sessions per UCI device, reported
+    /// in the device capabilities. The default is 255 sessions.
+    pub fn with_max_session(mut self, max_session: usize) -> Self {
+        self.max_session = max_session;
+        self
+    }
+
+    /// Register the capability profiles of the UCI devices. The profile
+    /// named `default` applies to the devices declared without a profile,
+    /// and to the undeclared devices. Devices without a profile report
+    /// the default Pica capabilities.
+    pub fn with_device_profiles(
+        mut self,
+        profiles: impl IntoIterator<Item = (String, DeviceProfile)>,
+    ) -> Self {
+        self.device_profiles.extend(
+            profiles
+                .into_iter()
+                .map(|(name, profile)| (name, Arc::new(profile))),
+        );
+        self
+    }
+
+    /// Declare UCI devices with a fixed MAC address. The connections
+    /// matching the hint of a declared device are assigned its MAC address.
+    pub fn with_devices(
+        mut self,
+        declarations: impl IntoIterator<Item = DeviceDeclaration>,
+    ) -> Result<Self, PicaCommandError> {
+        for declaration in declarations {
+            self.declare_device(declaration)?;
+        }
+        Ok(self)
+    }
+
+    /// Declare a UCI device with a fixed MAC address. The declaration
+    /// applies to the next connections.
+    pub fn declare_device(
+        &mut self,
+        declaration: DeviceDeclaration,
+    ) -> Result<(), PicaCommandError> {
+        if self
+            .declared_devices
+            .iter()
+            .any(|declared| declared.mac_address == declaration.mac_address)
+            || self.anchors.contains_key(&declaration.mac_address)
+        {
+            return Err(PicaCommandError::DeviceAlreadyExists(
+                declaration.mac_address,
+            ));
         }
+        if let Some(profile) = &declaration.profile {
+            if !self.device_profiles.contains_key(profile) {
+                return Err(PicaCommandError::ProfileNotFound(profile.clone()));
+            }
+        }
+        self.declared_devices.push(declaration);
+        Ok(())
+    }
+
+    /// Return the MAC addresses of the declared devices, and of the
+    /// connected UCI devices.
+    pub fn device_bindings(&self) -> Vec<DeviceBinding> {
+        let handle = |mac_address: &MacAddress| {
+            self.devices
+                .values()
+                .find(|device| device.mac_address == *mac_address)
+                .map(|device| device.handle)
+        };
+        let mut bindings: Vec<DeviceBinding> = self
+            .declared_devices
+            .iter()
+            .map(|declaration| DeviceBinding {
+                mac_address: declaration.mac_address,
+                hint: declaration.hint.clone(),
+                profile: declaration.profile.clone(),
+                handle: handle(&declaration.mac_address),
+            })
+            .collect();
+        let mut undeclared: Vec<&Device> = self
+            .devices
+            .values()
+            .filter(|device| {
+                !self
+                    .declared_devices
+                    .iter()
+                    .any(|declaration| declaration.mac_address == device.mac_address)
+            })
+            .collect();
+        undeclared.sort_by_key(|device| device.handle);
+        bindings.extend(undeclared.into_iter().map(|device| DeviceBinding {
+            mac_address: device.mac_address,
+            hint: None,
+            profile: None,
+            handle: Some(device.handle),
+        }));
+        bindings
+    }
+
+    /// Assign extended MAC addresses derived from the connection order
+    /// to the undeclared UCI devices, instead of short MAC addresses.
+    pub fn with_extended_mac_addresses(mut self) -> Self {
+        self.extended_mac_addresses = true;
+        self
+    }
+
+    /// Limit the range at which devices can measure each other with
+    /// a link budget. The range is not limited by default.
+    pub fn with_link_budget(mut self, link_budget: LinkBudget) -> Self {
+        self.link_budget = Some(link_budget);
+        self
+    }
+
+    /// Select how time elapses in the emulation. With the virtual clock
+    /// modes, the ranging rounds are executed deterministically, either
+    /// as fast as possible or when stepped with [`PicaCommand::Step`].
+    pub fn with_clock_mode(mut self, mode: ClockMode) -> Self {
+        self.clock = Clock::new(mode, self.command_tx.clone());
+        self
     }
 
     pub fn events(&self) -> broadcast::Receiver<PicaEvent> {
@@ -237,125 +830,242 @@ impl Pica {
 
     /// Handle an incoming stream of UCI packets.
     /// Reassemble control packets when fragmented, data packets are unmodified.
+    /// The faults are injected on the reassembled packets.
     async fn read_routine(
         mut uci_stream: impl futures::stream::Stream<Item = Vec<u8>> + Unpin,
         cmd_tx: mpsc::Sender<PicaCommand>,
         handle: Handle,
+        faults: FaultInjector,
+        clock: Clock,
         pcapng_file: Option<&pcapng::File>,
     ) -> anyhow::Result<()> {
         use futures::stream::StreamExt;
 
+        let mut delayed = DelayQueue::new(clock);
+        let mut complete_packet: Option<Vec<u8>> = None;
         loop {
-            let mut complete_packet: Option<Vec<u8>> = None;
-            loop {
-                let packet = uci_stream
-                    .next()
-                    .await
-                    .ok_or(anyhow::anyhow!("input packet stream closed"))?;
-                let header =
-                    packets::uci::CommonPacketHeader::decode_full(&packet[0..COMMON_HEADER_SIZE])?;
-
-                if let Some(file) = pcapng_file {
-                    file.write(&packet, pcapng::Direction::Tx)?;
+            let packet = tokio::select! {
+                packet = uci_stream.next() => {
+                    packet.ok_or(anyhow::anyhow!("input packet stream closed"))?
                 }
-
-                match &mut complete_packet {
-                    Some(complete_packet) => {
-                        complete_packet.extend_from_slice(&packet[HEADER_SIZE..])
-                    }
-                    None => complete_packet = Some(packet),
+                packet = delayed.recv() => {
+                    cmd_tx
+                        .send(PicaCommand::UciPacket(handle, packet))
+                        .await
+                        .unwrap();
+                    continue;
                 }
+            };
+            let header =
+                packets::uci::CommonPacketHeader::decode_full(&packet[0..COMMON_HEADER_SIZE])?;
 
-                if header.pbf == packets::uci::PacketBoundaryFlag::Complete
-                    || header.mt == packets::uci::MessageType::Data
-                {
-                    break;
-                }
+            if let Some(file) = pcapng_file {
+                file.write(&packet, pcapng::Direction::Tx)?;
             }
 
-            cmd_tx
-                .send(PicaCommand::UciPacket(handle, complete_packet.unwrap()))
-                .await
-                .unwrap()
+            match &mut complete_packet {
+                Some(complete_packet) => complete_packet.extend_from_slice(&packet[HEADER_SIZE..]),
+                None => complete_packet = Some(packet),
+            }
+
+            if header.pbf != packets::uci::PacketBoundaryFlag::Complete
+                && header.mt != packets::uci::MessageType::Data
+            {
+                continue;
+            }
+
+            let output = faults.apply(FaultDirection::FromHost, complete_packet.take().unwrap());
+            for packet in output.packets {
+                if let Some(packet) = delayed.push(output.delay, packet) {
+                    cmd_tx
+                        .send(PicaCommand::UciPacket(handle, packet))
+                        .await
+                        .unwrap()
+                }
+            }
         }
     }
 
     /// Segment a stream of UCI packets.
+    /// The faults are injected on the complete packets before segmentation.
     async fn write_routine(
         mut uci_sink: impl futures::sink::Sink<Vec<u8>> + Unpin,
         mut packet_rx: mpsc::UnboundedReceiver<UciPacket>,
         _handle: Handle,
+        faults: FaultInjector,
+        clock: Clock,
         pcapng_file: Option<&pcapng::File>,
     ) -> anyhow::Result<()> {
-        use futures::sink::SinkExt;
-
+        let mut delayed = DelayQueue::new(clock);
         loop {
-            let complete_packet = packet_rx
-                .recv()
-                .await
-                .ok_or(anyhow::anyhow!("output packet stream closed"))?;
-            let mut offset = HEADER_SIZE;
-            let mt = parse_message_type(complete_packet[0]);
-
-            while offset < complete_packet.len() {
-                let remaining_length = complete_packet.len() - offset;
-                let fragment_length = std::cmp::min(
-                    remaining_length,
-                    if mt == MessageType::Data {
-                        MAX_DATA_PACKET_PAYLOAD_SIZE
-                    } else {
-                        MAX_CTRL_PACKET_PAYLOAD_SIZE
-                    },
-                );
-                let pbf = if fragment_length == remaining_length {
-                    PacketBoundaryFlag::Complete
-                } else {
-                    PacketBoundaryFlag::NotComplete
-                };
+            let (packet, fragment_size) = tokio::select! {
+                packet = packet_rx.recv() => {
+                    let packet = packet.ok_or(anyhow::anyhow!("output packet stream closed"))?;
+                    let output = faults.apply(FaultDirection::ToHost, packet);
+                    for packet in output.packets {
+                        if let Some((packet, fragment_size)) =
+                            delayed.push(output.delay, (packet, output.fragment_size))
+                        {
+                            Self::write_packet(&mut uci_sink, packet, fragment_size, pcapng_file)
+                                .await?
+                        }
+                    }
+                    continue;
+                }
+                released = delayed.recv() => released,
+            };
+            Self::write_packet(&mut uci_sink, packet, fragment_size, pcapng_file).await?
+        }
+    }
 
-                let mut packet = Vec::with_capacity(HEADER_SIZE + fragment_length);
+    /// Segment and write a complete UCI packet. The payload size of
+    /// the fragments defaults to the maximum payload size.
+    async fn write_packet(
+        uci_sink: &mut (impl futures::sink::Sink<Vec<u8>> + Unpin),
+        complete_packet: UciPacket,
+        fragment_size: Option<usize>,
+        pcapng_file: Option<&pcapng::File>,
+    ) -> anyhow::Result<()> {
+        use futures::sink::SinkExt;
 
-                packet.extend_from_slice(&complete_packet[0..HEADER_SIZE]);
-                const PBF_MASK: u8 = 0x10;
-                packet[0] &= !PBF_MASK;
-                packet[0] |= (pbf as u8) << 4;
+        let mut offset = HEADER_SIZE;
+        let mt = parse_message_type(complete_packet[0]);
+        // Application data messages are segmented before the UCI transport,
+        // the last fragment keeps the packet boundary flag of the segment.
+        const PBF_MASK: u8 = 0x10;
+        let last_pbf = if complete_packet[0] & PBF_MASK != 0 {
+            PacketBoundaryFlag::NotComplete
+        } else {
+            PacketBoundaryFlag::Complete
+        };
+        let max_fragment_length = if mt == MessageType::Data {
+            MAX_DATA_PACKET_PAYLOAD_SIZE
+        } else {
+            MAX_CTRL_PACKET_PAYLOAD_SIZE
+        };
+        let max_fragment_length =
+            fragment_size.map_or(max_fragment_length, |size| size.min(max_fragment_length));
+
+        while offset < complete_packet.len() {
+            let remaining_length = complete_packet.len() - offset;
+            let fragment_length = std::cmp::min(remaining_length, max_fragment_length);
+            let pbf = if fragment_length == remaining_length {
+                last_pbf
+            } else {
+                PacketBoundaryFlag::NotComplete
+            };
 
-                match mt {
-                    MessageType::Data => {
-                        packet[2..4].copy_from_slice(&(fragment_length as u16).to_le_bytes())
-                    }
-                    _ => packet[3] = fragment_length as u8,
-                }
+            let mut packet = Vec::with_capacity(HEADER_SIZE + fragment_length);
 
-                packet.extend_from_slice(&complete_packet[offset..offset + fragment_length]);
+            packet.extend_from_slice(&complete_packet[0..HEADER_SIZE]);
+            packet[0] &= !PBF_MASK;
+            packet[0] |= (pbf as u8) << 4;
 
-                if let Some(file) = pcapng_file {
-                    file.write(&packet, pcapng::Direction::Rx)?;
+            match mt {
+                MessageType::Data => {
+                    packet[2..4].copy_from_slice(&(fragment_length as u16).to_le_bytes())
                 }
+                _ => packet[3] = fragment_length as u8,
+            }
 
-                uci_sink
-                    .send(packet)
-                    .await
-                    .map_err(|_| anyhow::anyhow!("output packet sink closed"))?;
+            packet.extend_from_slice(&complete_packet[offset..offset + fragment_length]);
 
-                offset += fragment_length;
+            if let Some(file) = pcapng_file {
+                file.write(&packet, pcapng::Direction::Rx)?;
             }
+
+            uci_sink
+                .send(packet)
+                .await
+                .map_err(|_| anyhow::anyhow!("output packet sink closed"))?;
+
+            offset += fragment_length;
         }
+        Ok(())
     }
 
     pub fn add_device(&mut self, stream: UciStream, sink: UciSink) -> Result<Handle> {
+        self.add_device_with_hint(stream, sink, None)
+    }
+
+    /// Select the MAC address of a new device: the address of the
+    /// matching declared device, or else an unused short or extended
+    /// address derived from the device handle.
+    fn assign_mac_address(&self, handle: Handle, hint: Option<&ConnectionHint>) -> MacAddress {
+        let is_used = |mac_address: &MacAddress| self.get_category(mac_address).is_some();
+        if let Some(mac_address) =
+            device_binding::assign_mac_address(&self.declared_devices, hint, is_used)
+        {
+            return mac_address;
+        }
+        let is_declared = |mac_address: &MacAddress| {
+            self.declared_devices
+                .iter()
+                .any(|declaration| declaration.mac_address == *mac_address)
+        };
+        if self.extended_mac_addresses {
+            return (handle as u64..=u64::MAX)
+                .map(|address| MacAddress::Extended(address.to_be_bytes()))
+                .find(|mac_address| !is_used(mac_address) && !is_declared(mac_address))
+                .expect("no extended MAC address available");
+        }
+        (handle as u16..=u16::MAX)
+            .chain(0..handle as u16)
+            .map(|address| MacAddress::Short(address.to_be_bytes()))
+            .find(|mac_address| !is_used(mac_address) && !is_declared(mac_address))
+            .expect("no short MAC address available")
+    }
+
+    /// Select the capability profile of a new device: the profile of
+    /// the declared device, or else the default profile.
+    fn device_profile(&self, mac_address: &MacAddress) -> Arc<DeviceProfile> {
+        let name = self
+            .declared_devices
+            .iter()
+            .find(|declaration| declaration.mac_address == *mac_address)
+            .and_then(|declaration| declaration.profile.as_deref())
+            .unwrap_or(DEFAULT_PROFILE);
+        self.device_profiles.get(name).cloned().unwrap_or_default()
+    }
+
+    /// Add a new device. The hint selects the MAC address of
+    /// a declared device.
+    pub fn add_device_with_hint(
+        &mut self,
+        stream: UciStream,
+        sink: UciSink,
+        hint: Option<ConnectionHint>,
+    ) -> Result<Handle> {
+        if let Some(max_device) = self.max_device {
+            if self.devices.len() >= max_device {
+                anyhow::bail!("maximum number of devices reached ({})", max_device)
+            }
+        }
+
         let (packet_tx, packet_rx) = mpsc::unbounded_channel();
         let pica_tx = self.command_tx.clone();
         let disconnect_tx = self.command_tx.clone();
         let pcapng_dir = self.pcapng_dir.clone();
+        let clock = self.clock.clone();
 
         let handle = self.counter;
         self.counter += 1;
 
         log::debug!("[{}] Connecting device", handle);
 
-        let mac_address = MacAddress::Short((handle as u16).to_be_bytes());
-        let mut device = Device::new(handle, mac_address, packet_tx, self.command_tx.clone());
+        let mac_address = self.assign_mac_address(handle, hint.as_ref());
+        log::debug!("  mac_address: {}", mac_address);
+        let faults = FaultInjector::new(handle as u64);
+        let mut device = Device::new(
+            handle,
+            mac_address,
+            OutboundQueue::new(packet_tx),
+            faults.clone(),
+            self.command_tx.clone(),
+            self.clock.clone(),
+            self.max_session,
+            self.device_profile(&mac_address),
+        );
         device.init();
 
         self.send_event(PicaEvent::Connected {
@@ -378,8 +1088,28 @@ impl Pica {
             };
 
             let _ = tokio::try_join!(
-                async { Self::read_routine(stream, pica_tx, handle, pcapng_file.as_ref()).await },
-                async { Self::write_routine(sink, packet_rx, handle, pcapng_file.as_ref()).await }
+                async {
+                    Self::read_routine(
+                        stream,
+                        pica_tx,
+                        handle,
+                        faults.clone(),
+                        clock.clone(),
+                        pcapng_file.as_ref(),
+                    )
+                    .await
+                },
+                async {
+                    Self::write_routine(
+                        sink,
+                        packet_rx,
+                        handle,
+                        faults.clone(),
+                        clock.clone(),
+                        pcapng_file.as_ref(),
+                    )
+                    .await
+                }
             );
 
             disconnect_tx
@@ -403,12 +1133,33 @@ impl Pica {
         }
     }
 
-    fn ranging(&mut self, device_handle: usize, session_id: u32) {
+    fn ranging(&mut self, device_handle: usize, session_handle: u32) {
         log::debug!("[{}] Ranging event", device_handle);
-        log::debug!("  session_id={}", session_id);
+        log::debug!("  session_handle={}", session_handle);
+
+        let device = self.get_device_mut(device_handle).unwrap();
+        let session = device.session_mut(session_handle).unwrap();
+        let round_index = session.round_index;
+        session.round_index += 1;
+
+        match session.app_config.device_role {
+            Some(DeviceRole::DtTag) => {
+                return self.dl_tdoa_ranging(device_handle, session_handle, round_index)
+            }
+            // DT-Anchors only transmit DL-TDoA messages,
+            // the measurements are reported by the DT-Tags.
+            Some(DeviceRole::DtAnchor) => return,
+            Some(DeviceRole::Observer) => {
+                return self.owr_aoa_ranging(device_handle, session_handle, round_index)
+            }
+            // Advertisers only transmit blink messages,
+            // the measurements are reported by the observers.
+            Some(DeviceRole::Advertiser) => return,
+            _ => (),
+        }
 
         let device = self.get_device(device_handle).unwrap();
-        let session = device.session(session_id).unwrap();
+        let session = device.session(session_handle).unwrap();
 
         let mut data_transfer = Vec::new();
         let mut measurements = Vec::new();
@@ -416,19 +1167,12 @@ impl Pica {
         // Look for compatible anchors.
         for mac_address in session.get_dst_mac_address() {
             if let Some(other) = self.anchors.get(mac_address) {
-                let Some(local) = self
-                    .ranging_estimator
-                    .estimate(&device.handle, &other.handle)
-                else {
-                    continue;
-                };
-                let Some(remote) = self
-                    .ranging_estimator
-                    .estimate(&other.handle, &device.handle)
-                else {
-                    continue;
-                };
-                measurements.push(make_measurement(mac_address, local, remote));
+                let (local, remote) = self.estimate_two_way(
+                    device.handle,
+                    other.handle,
+                    &session.ranging_context(round_index, self.clock.now()),
+                );
+                measurements.push((*mac_address, local, remote));
             }
         }
 
@@ -438,121 +1182,762 @@ impl Pica {
                 continue;
             }
 
-            if peer_device.can_start_ranging(session, session_id) {
+            if peer_device.can_start_ranging(session) {
+                // The MAC address of CCC sessions is not configured
+                // by the Host, fallback to the device MAC address.
                 let peer_mac_address = peer_device
-                    .session(session_id)
+                    .session_by_id(session.id)
                     .unwrap()
                     .app_config
                     .device_mac_address
-                    .unwrap();
-                let Some(local) = self
-                    .ranging_estimator
-                    .estimate(&device.handle, &peer_device.handle)
-                else {
-                    continue;
-                };
-                let Some(remote) = self
-                    .ranging_estimator
-                    .estimate(&peer_device.handle, &device.handle)
-                else {
+                    .unwrap_or(peer_device.mac_address);
+                let (local, remote) = self.estimate_two_way(
+                    device.handle,
+                    peer_device.handle,
+                    &session.ranging_context(round_index, self.clock.now()),
+                );
+                measurements.push((peer_mac_address, local, remote));
+                // Data cannot be exchanged with peers out of range.
+                if local.status != uci::Status::Ok {
                     continue;
-                };
-                measurements.push(make_measurement(&peer_mac_address, local, remote));
+                }
             }
 
-            if device.can_start_data_transfer(session_id)
-                && peer_device.can_receive_data_transfer(session_id)
+            if device.can_start_data_transfer(session_handle)
+                && peer_device.can_receive_data_transfer(session.id)
             {
                 data_transfer.push(peer_device);
             }
         }
 
-        // TODO: Data transfer should be limited in size for
-        // each round of ranging
-        for peer_device in data_transfer.iter() {
-            peer_device
-                .tx
-                .send(
-                    DataMessageRcv {
-                        application_data: session.data().clone().into(),
-                        data_sequence_number: 0x01,
-                        pbf: PacketBoundaryFlag::Complete,
-                        session_handle: session_id,
-                        source_address: session.app_config.device_mac_address.unwrap().into(),
-                        status: uci::Status::Ok,
-                    }
-                    .encode_to_vec()
-                    .unwrap(),
-                )
-                .unwrap();
+        // Deliver the application data messages transmitted in this
+        // ranging round to the destination peers in range.
+        let mut data_delivered = Vec::new();
+        for message in session.pending_data_messages() {
+            let mut delivered = false;
+            for peer_device in data_transfer.iter() {
+                let peer_session = peer_device.session_by_id(session.id).unwrap();
+                if peer_session.app_config.device_mac_address.map(u64::from)
+                    != Some(message.destination_address)
+                {
+                    continue;
+                }
+                for packet in make_data_message_rcv(
+                    peer_session.handle,
+                    session.app_config.device_mac_address.unwrap().into(),
+                    message,
+                ) {
+                    peer_device.tx.send(packet).unwrap();
+                }
+                delivered = true;
+            }
+            data_delivered.push(delivered);
         }
+        // The ranging round fails when no peer could be measured.
+        let round_failed = measurements
+            .iter()
+            .all(|(_, local, _)| local.status != uci::Status::Ok);
+
         if session.is_session_info_ntf_enabled() {
+            // CCC sessions only measure the distance to the peer device,
+            // the angles of arrival are not reported. The ranging round
+            // selected by the hopping sequence is reported in the slot
+            // index of the measurements.
+            if session.session_type == SessionType::Ccc {
+                let slot_index = session.app_config.ccc_ranging_round_index(round_index);
+                for (_, local, remote) in measurements.iter_mut() {
+                    for measurement in [&mut *local, &mut *remote] {
+                        measurement.azimuth = 0;
+                        measurement.azimuth_fom = 0;
+                        measurement.elevation = 0;
+                        measurement.elevation_fom = 0;
+                    }
+                    local.slot_index = slot_index;
+                }
+            }
             device
                 .tx
-                .send(
-                    // TODO: support extended address
-                    ShortMacTwoWaySessionInfoNtf {
-                        sequence_number: session.sequence_number,
-                        session_token: session_id,
-                        rcr_indicator: 0,            //TODO
-                        current_ranging_interval: 0, //TODO
-                        two_way_ranging_measurements: measurements,
-                        vendor_data: vec![],
-                    }
-                    .encode_to_vec()
-                    .unwrap(),
-                )
+                .send(make_two_way_session_info_ntf(session, measurements))
                 .unwrap();
 
             let device = self.get_device_mut(device_handle).unwrap();
-            let session = device.session_mut(session_id).unwrap();
+            let session = device.session_mut(session_handle).unwrap();
 
             session.sequence_number += 1;
         }
 
-        // TODO: Clean the data only when all the data is transfered
         let device = self.get_device_mut(device_handle).unwrap();
-        let session = device.session_mut(session_id).unwrap();
+        let session = device.session_mut(session_handle).unwrap();
 
-        session.clear_data();
-    }
+        session.data_messages_transmitted(&data_delivered);
 
-    fn uci_packet(&mut self, device_handle: usize, packet: Vec<u8>) {
-        match self.get_device_mut(device_handle) {
-            Some(device) => device.receive_packet(packet),
-            None => log::error!("Device {} not found", device_handle),
+        // [CCC] Each ranging block consumes one STS index, starting from
+        // STS_INDEX. The last index used is reported to the Host so that
+        // the session can be resumed without reusing an STS index.
+        if session.session_type == SessionType::Ccc {
+            session.app_config.ccc_last_index_used =
+                session.app_config.sts_index.wrapping_add(round_index);
+        }
+
+        // [UCI] MAX_RR_RETRY: the session is stopped after the configured
+        // number of consecutive failed ranging rounds; never when zero.
+        if round_failed {
+            session.failed_round_count = session.failed_round_count.saturating_add(1);
+        } else {
+            session.failed_round_count = 0;
+        }
+        let max_rr_retry = session.app_config.max_rr_retry;
+        if max_rr_retry != 0 && session.failed_round_count >= max_rr_retry {
+            log::debug!(
+                "[{}:0x{:x}] Max ranging round retry count reached",
+                device_handle,
+                session_handle
+            );
+            device.stop_ranging(session_handle, ReasonCode::MaxRangingRoundRetryCountReached);
         }
     }
 
-    fn pica_command(&mut self, command: PicaCommand) {
+    /// Estimate the measurement of the right device by the left device.
+    /// The measurement is dropped when the devices are out of the range
+    /// allowed by the link budget.
+    fn estimate(
+        &self,
+        left: &Handle,
+        right: &Handle,
+        context: &RangingContext,
+    ) -> Option<RangingMeasurement> {
+        let measurement = self.ranging_estimator.estimate(left, right, context)?;
+        match &self.link_budget {
+            Some(link_budget)
+                if measurement.status == uci::Status::Ok
+                    && measurement.range > link_budget.max_range(context.app_config) =>
+            {
+                None
+            }
+            _ => Some(measurement),
+        }
+    }
+
+    /// Estimate the local and remote measurements of a two way ranging
+    /// exchange. Both measurements are failed with the status
+    /// `RangingRxTimeout` when either one cannot be estimated.
+    fn estimate_two_way(
+        &self,
+        left: Handle,
+        right: Handle,
+        context: &RangingContext,
+    ) -> (RangingMeasurement, RangingMeasurement) {
+        match (
+            self.estimate(&left, &right, context),
+            self.estimate(&right, &left, context),
+        ) {
+            (Some(local), Some(remote)) => (local, remote),
+            _ => (
+                RangingMeasurement::failed(uci::Status::RangingRxTimeout),
+                RangingMeasurement::failed(uci::Status::RangingRxTimeout),
+            ),
+        }
+    }
+
+    /// Gather the anchors taking part in the DL-TDoA session of a DT-Tag.
+    /// The tag listens to the anchors listed in its DST_MAC_ADDRESS
+    /// configuration, or to all anchors if the list is empty.
+    fn dt_anchors(&self, device_handle: usize, session_handle: u32) -> Vec<DtAnchor> {
+        let device = self.get_device(device_handle).unwrap();
+        let session = device.session(session_handle).unwrap();
+        let dst_mac_address = session.get_dst_mac_address();
+        let is_selected = |mac_address: &MacAddress| {
+            dst_mac_address.is_empty() || dst_mac_address.contains(mac_address)
+        };
+
+        let mut dt_anchors = Vec::new();
+
+        // Anchors created from the HTTP interface all take part in the
+        // first ranging round. The anchor with the lowest handle acts
+        // as initiator and the other anchors respond.
+        let mut anchors = self
+            .anchors
+            .values()
+            .filter(|anchor| is_selected(&anchor.mac_address))
+            .collect::<Vec<_>>();
+        anchors.sort_by_key(|anchor| anchor.handle);
+        for (index, anchor) in anchors.into_iter().enumerate() {
+            dt_anchors.push(DtAnchor {
+                handle: anchor.handle,
+                mac_address: anchor.mac_address,
+                ranging_rounds: vec![DtAnchorRangingRound {
+                    ranging_round_index: 0,
+                    acting_role: if index == 0 {
+                        DtAnchorActingRole::Initiator
+                    } else {
+                        DtAnchorActingRole::Responder
+                    },
+                }],
+                location: self
+                    .ranging_estimator
+                    .location(&anchor.handle)
+                    .map(DtAnchorLocation::from),
+                tx_timestamp_conf: 0,
+                report_active_ranging_rounds: false,
+            })
+        }
+
+        // UCI devices with an active DT-Anchor session with the same
+        // session id. Anchors with no configured ranging rounds respond
+        // in the first ranging round.
+        for peer_device in self.devices.values() {
+            if peer_device.handle == device_handle {
+                continue;
+            }
+            let Some(peer_session) = peer_device.session_by_id(session.id) else {
+                continue;
+            };
+            let peer_config = &peer_session.app_config;
+            if peer_session.session_state() != SessionState::SessionStateActive
+                || peer_config.device_role != Some(DeviceRole::DtAnchor)
+            {
+                continue;
+            }
+            let Some(mac_address) = peer_config.device_mac_address else {
+                continue;
+            };
+            if !is_selected(&mac_address) {
+                continue;
+            }
+            dt_anchors.push(DtAnchor {
+                handle: peer_device.handle,
+                mac_address,
+                ranging_rounds: if peer_session.dt_anchor_ranging_rounds.is_empty() {
+                    vec![DtAnchorRangingRound {
+                        ranging_round_index: 0,
+                        acting_role: DtAnchorActingRole::Responder,
+                    }]
+                } else {
+                    peer_session.dt_anchor_ranging_rounds.clone()
+                },
+                location: DtAnchorLocation::from_app_config(&peer_config.dl_tdoa_anchor_location)
+                    .or_else(|| {
+                        self.ranging_estimator
+                            .location(&peer_device.handle)
+                            .map(DtAnchorLocation::from)
+                    }),
+                tx_timestamp_conf: peer_config.dl_tdoa_tx_timestamp_conf,
+                report_active_ranging_rounds: peer_config.dl_tdoa_tx_active_ranging_rounds != 0,
+            })
+        }
+
+        dt_anchors
+    }
+
+    /// Generate the DL-TDoA measurements for a DT-Tag session.
+    /// Every ranging event of the tag session is a ranging block,
+    /// divided in ranging rounds of SLOTS_PER_RR slots. In each round
+    /// the initiator anchor transmits first and the responder anchors
+    /// transmit in the following slots. All clocks are assumed ideal and
+    /// synchronized, the timestamps are computed from the block start
+    /// time and the time of flight between the anchor and the tag.
+    fn dl_tdoa_ranging(&mut self, device_handle: usize, session_handle: u32, round_index: u32) {
+        let dt_anchors = self.dt_anchors(device_handle, session_handle);
+        let device = self.get_device(device_handle).unwrap();
+        let session = device.session(session_handle).unwrap();
+        let app_config = &session.app_config;
+        let context = session.ranging_context(round_index, self.clock.now());
+
+        let block_index = session.sequence_number;
+        let slot_duration = app_config.slot_duration as u64 * DL_TDOA_TICKS_PER_RSTU;
+        let round_duration = app_config.slots_per_rr as u64 * slot_duration;
+        let block_start =
+            block_index as u64 * app_config.ranging_duration as u64 * DL_TDOA_TICKS_PER_MS;
+
+        let mut transmissions = dt_anchors
+            .iter()
+            .flat_map(|anchor| {
+                anchor
+                    .ranging_rounds
+                    .iter()
+                    .map(move |round| (round.ranging_round_index, round.acting_role, anchor))
+            })
+            .filter(|(round_index, _, _)| {
+                session.dt_tag_ranging_rounds.is_empty()
+                    || session.dt_tag_ranging_rounds.contains(round_index)
+            })
+            .collect::<Vec<_>>();
+        transmissions.sort_by_key(|(round_index, acting_role, anchor)| {
+            (
+                *round_index,
+                *acting_role != DtAnchorActingRole::Initiator,
+                anchor.handle,
+            )
+        });
+
+        let mut measurements = Vec::new();
+        let mut slot_index = 0;
+        let mut current_round = None;
+        for (round_index, acting_role, anchor) in transmissions {
+            if current_round != Some(round_index) {
+                current_round = Some(round_index);
+                slot_index = 0;
+            }
+            let reply_time = slot_index * slot_duration;
+            let tx_timestamp = block_start + round_index as u64 * round_duration + reply_time;
+            slot_index += 1;
+
+            let Some(local) = self.estimate(&device.handle, &anchor.handle, &context) else {
+                continue;
+            };
+            let time_of_flight = (local.range as f64 / SPEED_OF_LIGHT_CM_PER_TICK).round() as u64;
+            let rx_timestamp = tx_timestamp + time_of_flight;
+
+            let tx_timestamp_64 = anchor.tx_timestamp_conf & 0x2 != 0;
+            let rx_timestamp_64 = app_config.dl_tdoa_tx_timestamp_conf & 0x2 != 0;
+            let (wgs84_location, relative_location) = match &anchor.location {
+                Some(DtAnchorLocation::Wgs84(location)) => (vec![location.clone()], vec![]),
+                Some(DtAnchorLocation::Relative(location)) => (vec![], vec![location.clone()]),
+                None => (vec![], vec![]),
+            };
+
+            measurements.push((
+                anchor.mac_address,
+                DlTdoaRangingMeasurement {
+                    status: local.status,
+                    // Poll messages are sent by the initiator,
+                    // Response messages by the responders.
+                    message_type: match acting_role {
+                        DtAnchorActingRole::Initiator => 0x00,
+                        DtAnchorActingRole::Responder => 0x01,
+                    },
+                    tx_timestamp_type: 0,
+                    block_index: block_index as u16,
+                    round_index,
+                    nlos: local.nlos.into(),
+                    aoa_azimuth: local.azimuth as u16,
+                    aoa_azimuth_fom: local.azimuth_fom,
+                    aoa_elevation: local.elevation as u16,
+                    aoa_elevation_fom: local.elevation_fom,
+                    rssi: local.rssi,
+                    tx_timestamp_40: (!tx_timestamp_64).then_some(tx_timestamp & 0xff_ffff_ffff),
+                    tx_timestamp_64: tx_timestamp_64.then_some(tx_timestamp),
+                    rx_timestamp_40: (!rx_timestamp_64).then_some(rx_timestamp & 0xff_ffff_ffff),
+                    rx_timestamp_64: rx_timestamp_64.then_some(rx_timestamp),
+                    anchor_cfo: 0,
+                    cfo: 0,
+                    initiator_reply_time: 0,
+                    responder_reply_time: match acting_role {
+                        DtAnchorActingRole::Initiator => 0,
+                        DtAnchorActingRole::Responder => {
+                            u32::try_from(reply_time).unwrap_or(u32::MAX)
+                        }
+                    },
+                    initiator_responder_tof: 0,
+                    wgs84_location,
+                    relative_location,
+                    active_ranging_rounds: if anchor.report_active_ranging_rounds {
+                        anchor
+                            .ranging_rounds
+                            .iter()
+                            .map(|round| round.ranging_round_index)
+                            .take(15)
+                            .collect()
+                    } else {
+                        vec![]
+                    },
+                },
+            ));
+        }
+
+        if session.is_session_info_ntf_enabled() {
+            device
+                .tx
+                .send(make_dl_tdoa_session_info_ntf(session, measurements))
+                .unwrap();
+
+            let device = self.get_device_mut(device_handle).unwrap();
+            let session = device.session_mut(session_handle).unwrap();
+
+            session.sequence_number += 1;
+        }
+    }
+
+    /// Generate the OWR AoA measurements for an observer session.
+    /// The observer reports the angle of arrival of the advertisers in range
+    /// listed in its DST_MAC_ADDRESS configuration, or of all advertisers
+    /// if the list is empty. Advertisers are UCI devices with an active
+    /// advertiser session with the same session id, and anchors listed in
+    /// the DST_MAC_ADDRESS configuration.
+    fn owr_aoa_ranging(&mut self, device_handle: usize, session_handle: u32, round_index: u32) {
+        let device = self.get_device(device_handle).unwrap();
+        let session = device.session(session_handle).unwrap();
+
+        // Notifications are generated once every
+        // OWR_AOA_MEASUREMENT_NTF_PERIOD ranging rounds.
+        let ntf_period = session.app_config.owr_aoa_measurement_ntf_period as u32;
+        if !session.is_session_info_ntf_enabled()
+            || round_index.checked_rem(ntf_period).unwrap_or(0) != 0
+        {
+            return;
+        }
+
+        let mut advertisers = Vec::new();
+        for mac_address in session.get_dst_mac_address() {
+            if let Some(anchor) = self.anchors.get(mac_address) {
+                advertisers.push((anchor.handle, *mac_address));
+            }
+        }
+        for peer_device in self.devices.values() {
+            if peer_device.handle == device_handle {
+                continue;
+            }
+            let Some(peer_session) = peer_device.session_by_id(session.id) else {
+                continue;
+            };
+            if peer_session.session_state() == SessionState::SessionStateActive
+                && session.app_config.can_observe(&peer_session.app_config)
+            {
+                advertisers.push((
+                    peer_device.handle,
+                    peer_session.app_config.device_mac_address.unwrap(),
+                ));
+            }
+        }
+
+        let mut measurements = Vec::new();
+        for (handle, mac_address) in advertisers {
+            let Some(local) = self.estimate(
+                &device.handle,
+                &handle,
+                &session.ranging_context(round_index, self.clock.now()),
+            ) else {
+                continue;
+            };
+            measurements.push((mac_address, local));
+        }
+
+        device
+            .tx
+            .send(make_owr_aoa_session_info_ntf(
+                session,
+                round_index,
+                measurements,
+            ))
+            .unwrap();
+
+        let device = self.get_device_mut(device_handle).unwrap();
+        let session = device.session_mut(session_handle).unwrap();
+
+        session.sequence_number += 1;
+    }
+
+    /// Find a peer device with a session of type DEVICE_TEST_MODE configured
+    /// on the same channel as the selected test session, and running a test
+    /// matching the predicate.
+    fn test_peer(
+        &self,
+        device_handle: usize,
+        session: &session::Session,
+        predicate: impl Fn(Option<&test_mode::Test>) -> bool,
+    ) -> Option<(&Device, &session::Session)> {
+        self.devices
+            .values()
+            .filter(|peer_device| peer_device.handle != device_handle)
+            .flat_map(|peer_device| {
+                peer_device
+                    .sessions()
+                    .map(move |peer_session| (peer_device, peer_session))
+            })
+            .find(|(_, peer_session)| {
+                peer_session.session_type == SessionType::DeviceTestMode
+                    && peer_session.app_config.channel_number == session.app_config.channel_number
+                    && predicate(peer_session.test.as_ref())
+            })
+    }
+
+    /// Complete the test running in the selected session, and send the test
+    /// notification. The results are computed from the peer devices running
+    /// tests on the same channel:
+    /// - PER_RX and RX receive the packets sent by a peer running PERIODIC_TX
+    ///   test, if the peer is in range.
+    /// - SS_TWR measures the round trip time to a peer with a test session
+    ///   configured on the same channel, if the peer is in range.
+    /// - LOOPBACK receives the packet sent, with no propagation delay.
+    fn test(&mut self, device_handle: usize, session_handle: u32) {
+        use test_mode::Test;
+
+        log::debug!("[{}] Test event", device_handle);
+        log::debug!("  session_handle={}", session_handle);
+
+        let device = self.get_device(device_handle).unwrap();
+        let Some(session) = device.session(session_handle) else {
+            return;
+        };
+        let Some(test) = &session.test else {
+            return;
+        };
+        let test_config = &session.test_config;
+        let rx_start = test_config.t_start as f64 * TEST_TICKS_PER_US;
+
+        // Find the peer device transmitting packets, and estimate
+        // the reception angle and propagation delay.
+        let transmitter = || {
+            let (peer_device, peer_session) = self.test_peer(device_handle, session, |test| {
+                matches!(test, Some(Test::PeriodicTx { .. }))
+            })?;
+            let Some(Test::PeriodicTx { psdu_data }) = &peer_session.test else {
+                unreachable!()
+            };
+            let measurement = self.estimate(
+                &device.handle,
+                &peer_device.handle,
+                &session.ranging_context(session.round_index, self.clock.now()),
+            )?;
+            Some((psdu_data.clone(), measurement))
+        };
+
+        let ntf: UciPacket = match test {
+            Test::PeriodicTx { .. } => TestPeriodicTxNtf {
+                status: uci::Status::Ok,
+                vendor_data: vec![],
+            }
+            .encode_to_vec()
+            .unwrap(),
+            Test::PerRx { psdu_data } => {
+                let attempts = test_config.num_packets;
+                let (received, bit_errors) = match transmitter() {
+                    Some((tx_psdu_data, _)) => {
+                        (attempts, psdu_bit_errors(&tx_psdu_data, psdu_data))
+                    }
+                    None => (0, 0),
+                };
+                TestPerRxNtf {
+                    status: uci::Status::Ok,
+                    attempts,
+                    acq_detect: received,
+                    acq_reject: 0,
+                    rx_fail: attempts - received,
+                    sync_cir_ready: received,
+                    sfd_fail: 0,
+                    sfd_found: received,
+                    phr_dec_error: 0,
+                    phr_bit_error: 0,
+                    psdu_dec_error: if bit_errors > 0 { received } else { 0 },
+                    psdu_bit_error: bit_errors.saturating_mul(received),
+                    sts_found: received,
+                    eof: received,
+                    vendor_data: vec![],
+                }
+                .encode_to_vec()
+                .unwrap()
+            }
+            Test::Rx => match transmitter() {
+                Some((psdu_data, measurement)) => {
+                    let (rx_done_ts_int, rx_done_ts_frac) = make_test_timestamp(
+                        rx_start + measurement.range as f64 / SPEED_OF_LIGHT_CM_PER_TEST_TICK,
+                    );
+                    TestRxNtf {
+                        status: uci::Status::Ok,
+                        rx_done_ts_int,
+                        rx_done_ts_frac,
+                        aoa_azimuth: measurement.azimuth as u16,
+                        aoa_elevation: measurement.elevation as u16,
+                        toa_gap: 0,
+                        phr: 0,
+                        psdu_data,
+                        vendor_data: vec![],
+                    }
+                    .encode_to_vec()
+                    .unwrap()
+                }
+                None => TestRxNtf {
+                    status: uci::Status::RangingRxTimeout,
+                    rx_done_ts_int: 0,
+                    rx_done_ts_frac: 0,
+                    aoa_azimuth: 0,
+                    aoa_elevation: 0,
+                    toa_gap: 0,
+                    phr: 0,
+                    psdu_data: vec![],
+                    vendor_data: vec![],
+                }
+                .encode_to_vec()
+                .unwrap(),
+            },
+            Test::Loopback { psdu_data } => {
+                let (ts_int, ts_frac) = make_test_timestamp(rx_start);
+                TestLoopbackNtf {
+                    status: uci::Status::Ok,
+                    tx_ts_int: ts_int,
+                    tx_ts_frac: ts_frac,
+                    rx_ts_int: ts_int,
+                    rx_ts_frac: ts_frac,
+                    aoa_azimuth: 0,
+                    aoa_elevation: 0,
+                    phr: 0,
+                    psdu_data: psdu_data.clone(),
+                    vendor_data: vec![],
+                }
+                .encode_to_vec()
+                .unwrap()
+            }
+            Test::SsTwr => {
+                let measurement = self.test_peer(device_handle, session, |_| true).and_then(
+                    |(peer_device, _)| {
+                        self.estimate(
+                            &device.handle,
+                            &peer_device.handle,
+                            &session.ranging_context(session.round_index, self.clock.now()),
+                        )
+                    },
+                );
+                match measurement {
+                    // The measurement is the difference Tround - Treply,
+                    // i.e. twice the time of flight.
+                    Some(measurement) => TestSsTwrNtf {
+                        status: uci::Status::Ok,
+                        measurement: (2.0 * measurement.range as f64 / SPEED_OF_LIGHT_CM_PER_TICK)
+                            .round() as u32,
+                    },
+                    None => TestSsTwrNtf {
+                        status: uci::Status::RangingRxTimeout,
+                        measurement: 0,
+                    },
+                }
+                .encode_to_vec()
+                .unwrap()
+            }
+        };
+
+        device.tx.send(ntf).unwrap();
+        self.get_device_mut(device_handle)
+            .unwrap()
+            .test_stop(session_handle);
+    }
+
+    fn uci_packet(&mut self, device_handle: usize, packet: Vec<u8>) {
+        match self.get_device_mut(device_handle) {
+            Some(device) => device.receive_packet(packet),
+            None => log::error!("Device {} not found", device_handle),
+        }
+    }
+
+    fn pica_command(&mut self, command: PicaCommand) {
         use PicaCommand::*;
         match command {
             Connect(stream, sink) => {
-                let _ = self.add_device(stream, sink);
+                if let Err(err) = self.add_device(stream, sink) {
+                    log::error!("Failed to connect device: {}", err)
+                }
+            }
+            ConnectWithHint(stream, sink, hint) => {
+                if let Err(err) = self.add_device_with_hint(stream, sink, Some(hint)) {
+                    log::error!("Failed to connect device: {}", err)
+                }
             }
             Disconnect(device_handle) => self.disconnect(device_handle),
-            Ranging(device_handle, session_id) => self.ranging(device_handle, session_id),
+            Ranging(device_handle, session_handle) => self.ranging(device_handle, session_handle),
             StopRanging(mac_address, session_id) => {
                 self.stop_controlee_ranging(&mac_address, session_id)
             }
             UciPacket(device_handle, packet) => self.uci_packet(device_handle, packet),
+            Test(device_handle, session_handle) => self.test(device_handle, session_handle),
             CreateAnchor(mac_address, pica_cmd_rsp_tx) => {
                 self.create_anchor(mac_address, pica_cmd_rsp_tx)
             }
             DestroyAnchor(mac_address, pica_cmd_rsp_tx) => {
                 self.destroy_anchor(mac_address, pica_cmd_rsp_tx)
             }
+            Step(rsp_tx) => self.step(rsp_tx),
+            Schedule(time, action) => self.schedule(time, action),
+            SetFaults(mac_address, faults, pica_cmd_rsp_tx) => {
+                self.set_faults(mac_address, faults, pica_cmd_rsp_tx)
+            }
+            SetUwbsClock(mac_address, uwbs_clock, pica_cmd_rsp_tx) => {
+                self.set_uwbs_clock(mac_address, uwbs_clock, pica_cmd_rsp_tx)
+            }
+            SetDeviceError(mac_address, pica_cmd_rsp_tx) => {
+                self.set_device_error(mac_address, pica_cmd_rsp_tx)
+            }
+            DeclareDevice(declaration, pica_cmd_rsp_tx) => {
+                log::debug!("[_] Declare device");
+                log::debug!("  declaration: {:?}", declaration);
+                let status = self.declare_device(declaration);
+                pica_cmd_rsp_tx.send(status).unwrap_or_else(|err| {
+                    log::error!("Failed to send declare-device command response: {:?}", err)
+                })
+            }
+            GetDeviceBindings(pica_cmd_rsp_tx) => pica_cmd_rsp_tx
+                .send(self.device_bindings())
+                .unwrap_or_else(|err| {
+                    log::error!(
+                        "Failed to send get-device-bindings command response: {:?}",
+                        err
+                    )
+                }),
+            InjectNotification(mac_address, notification, pica_cmd_rsp_tx) => {
+                self.inject_notification(mac_address, notification, pica_cmd_rsp_tx)
+            }
         }
     }
 
+    /// Execute the commands of the timers expiring next on the virtual
+    /// clock. Returns false if no timer is pending.
+    fn advance_clock(&mut self) -> bool {
+        let Some(commands) = self.clock.advance() else {
+            return false;
+        };
+        for command in commands {
+            self.pica_command(command)
+        }
+        true
+    }
+
+    /// Advance the clock when it runs as fast as possible, and the
+    /// command queue is empty. Returns false if the clock was not
+    /// advanced.
+    fn fast_forward(&mut self) -> bool {
+        self.clock.mode() == ClockMode::Fast && self.advance_clock()
+    }
+
+    fn step(&mut self, rsp_tx: oneshot::Sender<std::time::Duration>) {
+        log::debug!("[_] Step");
+        self.advance_clock();
+        log::debug!("  now: {:?}", self.clock.now());
+
+        rsp_tx
+            .send(self.clock.now())
+            .unwrap_or_else(|err| log::error!("Failed to send step command response: {:?}", err))
+    }
+
+    fn schedule(
+        &mut self,
+        time: std::time::Duration,
+        action: Box<dyn FnOnce() -> Option<PicaCommand> + Send>,
+    ) {
+        log::debug!("[_] Schedule");
+        log::debug!("  time: {:?}", time);
+
+        let mut action = Some(action);
+        // The timer is never cancelled. Actions scheduled in the past
+        // are run on the next clock expiration.
+        let _ = self.clock.schedule(
+            time.saturating_sub(self.clock.now()),
+            None,
+            Box::new(move || action.take().and_then(|action| action())),
+        );
+    }
+
     /// Run the internal pica event loop.
     pub async fn run(mut self) -> Result<()> {
         let Some(mut command_rx) = self.command_rx.take() else {
             anyhow::bail!("missing pica command receiver")
         };
         loop {
-            if let Some(command) = command_rx.recv().await {
-                self.pica_command(command)
+            match command_rx.try_recv() {
+                Ok(command) => self.pica_command(command),
+                Err(_) if self.fast_forward() => tokio::task::yield_now().await,
+                Err(_) => {
+                    if let Some(command) = command_rx.recv().await {
+                        self.pica_command(command)
+                    }
+                }
             }
         }
     }
@@ -561,7 +1946,7 @@ impl Pica {
     // corresponding mac_address and session_id.
     fn stop_controlee_ranging(&mut self, mac_address: &MacAddress, session_id: u32) {
         for device in self.devices.values_mut() {
-            let Some(session) = device.session_mut(session_id) else {
+            let Some(session) = device.session_by_id(session_id) else {
                 continue;
             };
 
@@ -570,15 +1955,8 @@ impl Pica {
             }
 
             if session.session_state() == SessionState::SessionStateActive {
-                session.stop_ranging_task();
-                session.set_state(
-                    SessionState::SessionStateIdle,
-                    ReasonCode::SessionStoppedDueToInbandSignal,
-                );
-                device.n_active_sessions = device.n_active_sessions.saturating_sub(1);
-                if device.n_active_sessions == 0 {
-                    device.set_state(DeviceState::DeviceStateReady);
-                }
+                let session_handle = session.handle;
+                device.stop_ranging(session_handle, ReasonCode::SessionStoppedDueToInbandSignal);
             } else {
                 log::warn!("stop_controlee_ranging: session is not active !");
             }
@@ -636,6 +2014,125 @@ impl Pica {
             log::error!("Failed to send destroy-anchor command response: {:?}", err)
         })
     }
+
+    fn get_device_by_mac_address(&self, mac_address: &MacAddress) -> Option<&Device> {
+        self.devices
+            .values()
+            .find(|device| device.mac_address == *mac_address)
+    }
+
+    fn set_faults(
+        &mut self,
+        mac_address: MacAddress,
+        faults: Vec<Fault>,
+        rsp_tx: oneshot::Sender<Result<Handle, PicaCommandError>>,
+    ) {
+        log::debug!("[_] Set faults");
+        log::debug!("  mac_address: {}", mac_address);
+        log::debug!("  faults: {:?}", faults);
+
+        let status = match self.get_device_by_mac_address(&mac_address) {
+            None => Err(PicaCommandError::DeviceNotFound(mac_address)),
+            Some(device) => {
+                device.faults.set_faults(faults);
+                Ok(device.handle)
+            }
+        };
+
+        rsp_tx.send(status).unwrap_or_else(|err| {
+            log::error!("Failed to send set-faults command response: {:?}", err)
+        })
+    }
+
+    fn set_uwbs_clock(
+        &mut self,
+        mac_address: MacAddress,
+        uwbs_clock: UwbsClock,
+        rsp_tx: oneshot::Sender<Result<Handle, PicaCommandError>>,
+    ) {
+        log::debug!("[_] Set UWBS clock");
+        log::debug!("  mac_address: {}", mac_address);
+        log::debug!("  uwbs_clock: {:?}", uwbs_clock);
+
+        let device = self
+            .devices
+            .values_mut()
+            .find(|device| device.mac_address == mac_address);
+        let status = match device {
+            None => Err(PicaCommandError::DeviceNotFound(mac_address)),
+            Some(device) => {
+                device.uwbs_clock = uwbs_clock;
+                Ok(device.handle)
+            }
+        };
+
+        rsp_tx.send(status).unwrap_or_else(|err| {
+            log::error!("Failed to send set-uwbs-clock command response: {:?}", err)
+        })
+    }
+
+    fn set_device_error(
+        &mut self,
+        mac_address: MacAddress,
+        rsp_tx: oneshot::Sender<Result<Handle, PicaCommandError>>,
+    ) {
+        log::debug!("[_] Set device error");
+        log::debug!("  mac_address: {}", mac_address);
+
+        let device = self
+            .devices
+            .values_mut()
+            .find(|device| device.mac_address == mac_address);
+        let status = match device {
+            None => Err(PicaCommandError::DeviceNotFound(mac_address)),
+            Some(device) => {
+                device.set_error_state();
+                Ok(device.handle)
+            }
+        };
+
+        rsp_tx.send(status).unwrap_or_else(|err| {
+            log::error!(
+                "Failed to send set-device-error command response: {:?}",
+                err
+            )
+        })
+    }
+
+    fn inject_notification(
+        &mut self,
+        mac_address: MacAddress,
+        notification: FaultNotification,
+        rsp_tx: oneshot::Sender<Result<Handle, PicaCommandError>>,
+    ) {
+        log::debug!("[_] Inject notification");
+        log::debug!("  mac_address: {}", mac_address);
+        log::debug!("  notification: {:?}", notification);
+
+        let status = match self.get_device_by_mac_address(&mac_address) {
+            None => Err(PicaCommandError::DeviceNotFound(mac_address)),
+            Some(device) => {
+                let packet = match notification {
+                    FaultNotification::GenericError(status) => {
+                        CoreGenericErrorNtf { status }.encode_to_vec()
+                    }
+                    FaultNotification::DeviceStateError => CoreDeviceStatusNtf {
+                        device_state: DeviceState::DeviceStateError,
+                    }
+                    .encode_to_vec(),
+                };
+                let _ = device.tx.send(packet.unwrap());
+                Ok(device.handle)
+            }
+        };
+
+        rsp_tx.send(status).unwrap_or_else(|err| {
+            log::error!(
+                "Failed to send inject-notification command response: {:?}",
+                err
+            )
+        })
+    }
 }
 
 /// Run the internal pica event loop.
@@ -649,8 +2146,1117 @@ pub async fn run(this: &std::sync::Mutex<Pica>) -> Result<()> {
     };
 
     loop {
-        if let Some(command) = command_rx.recv().await {
-            this.lock().unwrap().pica_command(command)
+        let command = command_rx.try_recv();
+        match command {
+            Ok(command) => this.lock().unwrap().pica_command(command),
+            Err(_) if this.lock().unwrap().fast_forward() => tokio::task::yield_now().await,
+            Err(_) => {
+                if let Some(command) = command_rx.recv().await {
+                    this.lock().unwrap().pica_command(command)
+                }
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc as channel;
+    use futures::{SinkExt, StreamExt};
+    use std::time::Duration;
+
+    /// Measurement returned by the test ranging estimator
+    /// for all pairs of devices.
+    const TEST_MEASUREMENT: RangingMeasurement = RangingMeasurement {
+        range: 300,
+        azimuth: 30,
+        elevation: 10,
+        nlos: false,
+        azimuth_fom: 90,
+        elevation_fom: 80,
+        rssi: 100,
+        slot_index: 0,
+        status: uci::Status::Ok,
+    };
+
+    /// Ranging estimator placing the device with handle `n`
+    /// at `(100 * n, 0, 0)`.
+    struct TestRangingEstimator;
+
+    impl RangingEstimator for TestRangingEstimator {
+        fn estimate(
+            &self,
+            _left: &Handle,
+            _right: &Handle,
+            _context: &RangingContext,
+        ) -> Option<RangingMeasurement> {
+            Some(TEST_MEASUREMENT)
+        }
+
+        fn location(&self, device: &Handle) -> Option<Location> {
+            Some(Location {
+                x: 100 * *device as i32,
+                y: 0,
+                z: 0,
+            })
+        }
+    }
+
+    fn pica() -> Pica {
+        Pica::new(Box::new(TestRangingEstimator), None)
+    }
+
+    /// UCI host connected to a device of the pica context under test.
+    /// The commands are executed synchronously by the pica context,
+    /// and the virtual clock is advanced by the test.
+    struct TestHost {
+        handle: Handle,
+        /// Keeps the UCI stream of the device open.
+        _uci_tx: channel::UnboundedSender<UciPacket>,
+        uci_rx: channel::UnboundedReceiver<UciPacket>,
+    }
+
+    impl TestHost {
+        /// Connect a new device, and reset it.
+        async fn connect(pica: &mut Pica) -> TestHost {
+            let (uci_tx, stream) = channel::unbounded();
+            let (sink, uci_rx) = channel::unbounded();
+            let handle = pica
+                .add_device(
+                    Box::pin(stream),
+                    Box::pin(sink.sink_map_err(anyhow::Error::from)),
+                )
+                .unwrap();
+            let mut host = TestHost {
+                handle,
+                _uci_tx: uci_tx,
+                uci_rx,
+            };
+            host.send(
+                pica,
+                CoreDeviceResetCmd {
+                    reset_config: ResetConfig::UwbsReset,
+                },
+            );
+            let rsp: CoreDeviceResetRsp = host.expect().await;
+            assert_eq!(rsp.status, uci::Status::Ok);
+            host
+        }
+
+        fn send(&self, pica: &mut Pica, packet: impl Packet) {
+            pica.pica_command(PicaCommand::UciPacket(
+                self.handle,
+                packet.encode_to_vec().unwrap(),
+            ))
+        }
+
+        /// Receive the next complete packet sent by the device.
+        async fn receive(&mut self) -> UciPacket {
+            let mut complete_packet: Option<UciPacket> = None;
+            loop {
+                let packet = tokio::time::timeout(Duration::from_secs(1), self.uci_rx.next())
+                    .await
+                    .expect("no packet received from the device")
+                    .unwrap();
+                let header =
+                    CommonPacketHeader::decode_full(&packet[0..COMMON_HEADER_SIZE]).unwrap();
+                match &mut complete_packet {
+                    Some(complete_packet) => {
+                        complete_packet.extend_from_slice(&packet[HEADER_SIZE..])
+                    }
+                    None => complete_packet = Some(packet),
+                }
+                if header.pbf == PacketBoundaryFlag::Complete {
+                    return complete_packet.unwrap();
+                }
+            }
+        }
+
+        /// Receive packets until a packet of the selected type,
+        /// the other packets are discarded.
+        async fn expect<T: Packet>(&mut self) -> T {
+            loop {
+                if let Ok(packet) = T::decode_full(&self.receive().await) {
+                    return packet;
+                }
+            }
+        }
+
+        /// Initialize, configure and start a session.
+        /// Returns the session handle.
+        async fn start_session(
+            &mut self,
+            pica: &mut Pica,
+            session_id: u32,
+            session_type: SessionType,
+            tlvs: Vec<AppConfigTlv>,
+        ) -> u32 {
+            self.send(
+                pica,
+                SessionInitCmd {
+                    session_id,
+                    session_type,
+                },
+            );
+            let rsp: SessionInitRsp_V2 = self.expect().await;
+            assert_eq!(rsp.status, uci::Status::Ok);
+            let session_handle = rsp.session_handle;
+
+            self.send(
+                pica,
+                SessionSetAppConfigCmd {
+                    session_token: session_handle,
+                    tlvs,
+                },
+            );
+            let rsp: SessionSetAppConfigRsp = self.expect().await;
+            assert_eq!(rsp.status, uci::Status::Ok);
+
+            self.send(
+                pica,
+                SessionStartCmd {
+                    session_id: session_handle,
+                },
+            );
+            let rsp: SessionStartRsp = self.expect().await;
+            assert_eq!(rsp.status, uci::Status::Ok);
+            session_handle
+        }
+    }
+
+    fn tlv(cfg_id: AppConfigTlvType, v: &[u8]) -> AppConfigTlv {
+        AppConfigTlv {
+            cfg_id,
+            v: v.to_vec(),
+        }
+    }
+
+    /// App configuration of a session with the selected role and ranging
+    /// round usage.
+    fn app_config_tlvs(
+        device_role: DeviceRole,
+        device_type: DeviceType,
+        ranging_round_usage: RangingRoundUsage,
+        mac_address: [u8; 2],
+    ) -> Vec<AppConfigTlv> {
+        vec![
+            tlv(AppConfigTlvType::DeviceRole, &[device_role.into()]),
+            tlv(AppConfigTlvType::DeviceType, &[device_type.into()]),
+            tlv(
+                AppConfigTlvType::RangingRoundUsage,
+                &[ranging_round_usage.into()],
+            ),
+            tlv(AppConfigTlvType::DeviceMacAddress, &mac_address),
+            tlv(AppConfigTlvType::MultiNodeMode, &[0]),
+            tlv(AppConfigTlvType::ScheduleMode, &[0]),
+        ]
+    }
+
+    fn create_anchor(pica: &mut Pica, mac_address: MacAddress) -> Handle {
+        let (rsp_tx, mut rsp_rx) = oneshot::channel();
+        pica.create_anchor(mac_address, rsp_tx);
+        rsp_rx.try_recv().unwrap().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_dl_tdoa_ranging() {
+        let mut pica = pica().with_clock_mode(ClockMode::Step);
+        let mut anchor = TestHost::connect(&mut pica).await;
+        let mut tag = TestHost::connect(&mut pica).await;
+        let anchor_handle = create_anchor(&mut pica, MacAddress::Short([0, 0x10]));
+
+        // The UCI DT-Anchor initiates the third ranging round,
+        // at the configured location.
+        let location = RelativeLocation {
+            x: 100,
+            y: 200,
+            z: 30,
+        };
+        let mut anchor_tlvs = app_config_tlvs(
+            DeviceRole::DtAnchor,
+            DeviceType::Controller,
+            RangingRoundUsage::OnWayRangingDlTdoa,
+            [0, 0x11],
+        );
+        anchor_tlvs.push(tlv(
+            AppConfigTlvType::DlTdoaAnchorLocation,
+            &[&[0x01], location.encode_to_vec().unwrap().as_slice()].concat(),
+        ));
+        anchor.send(
+            &mut pica,
+            SessionInitCmd {
+                session_id: 1,
+                session_type: SessionType::FiraRangingSession,
+            },
+        );
+        let session_handle = anchor.expect::<SessionInitRsp_V2>().await.session_handle;
+        anchor.send(
+            &mut pica,
+            SessionSetAppConfigCmd {
+                session_token: session_handle,
+                tlvs: anchor_tlvs,
+            },
+        );
+        anchor.expect::<SessionSetAppConfigRsp>().await;
+        anchor.send(
+            &mut pica,
+            SessionUpdateDtAnchorRangingRoundsCmd {
+                session_token: session_handle,
+                ranging_rounds: vec![DtAnchorRangingRound {
+                    ranging_round_index: 2,
+                    acting_role: DtAnchorActingRole::Initiator,
+                }],
+            },
+        );
+        let rsp: SessionUpdateDtAnchorRangingRoundsRsp = anchor.expect().await;
+        assert_eq!(rsp.status, uci::Status::Ok);
+        anchor.send(
+            &mut pica,
+            SessionStartCmd {
+                session_id: session_handle,
+            },
+        );
+        assert_eq!(
+            anchor.expect::<SessionStartRsp>().await.status,
+            uci::Status::Ok
+        );
+
+        let mut tag_tlvs = app_config_tlvs(
+            DeviceRole::DtTag,
+            DeviceType::Controlee,
+            RangingRoundUsage::OnWayRangingDlTdoa,
+            [0, 0x12],
+        );
+        tag_tlvs.push(tlv(AppConfigTlvType::SlotDuration, &2400u16.to_le_bytes()));
+        tag_tlvs.push(tlv(AppConfigTlvType::SlotsPerRr, &[24]));
+        let session_handle = tag
+            .start_session(&mut pica, 1, SessionType::FiraRangingSession, tag_tlvs)
+            .await;
+
+        assert!(pica.advance_clock());
+        let ntf: ShortMacDlTDoASessionInfoNtf = tag.expect().await;
+        assert_eq!(ntf.session_token, session_handle);
+        assert_eq!(ntf.sequence_number, 0);
+
+        // The anchor created over HTTP initiates the first ranging round,
+        // the UCI DT-Anchor the third.
+        let round_duration = 24 * 2400 * DL_TDOA_TICKS_PER_RSTU;
+        let time_of_flight =
+            (TEST_MEASUREMENT.range as f64 / SPEED_OF_LIGHT_CM_PER_TICK).round() as u64;
+        let measurements = ntf.dl_tdoa_measurements;
+        assert_eq!(measurements.len(), 2);
+
+        assert_eq!(measurements[0].mac_address, 0x1000);
+        let measurement = &measurements[0].measurement;
+        assert_eq!(measurement.status, uci::Status::Ok);
+        assert_eq!(measurement.message_type, 0x00);
+        assert_eq!(measurement.block_index, 0);
+        assert_eq!(measurement.round_index, 0);
+        assert_eq!(measurement.aoa_azimuth, TEST_MEASUREMENT.azimuth as u16);
+        assert_eq!(measurement.aoa_elevation, TEST_MEASUREMENT.elevation as u16);
+        assert_eq!(measurement.rssi, TEST_MEASUREMENT.rssi);
+        assert_eq!(measurement.tx_timestamp_40, Some(0));
+        assert_eq!(measurement.rx_timestamp_40, Some(time_of_flight));
+        assert_eq!(
+            measurement.relative_location,
+            vec![RelativeLocation {
+                x: 100 * anchor_handle as u32,
+                y: 0,
+                z: 0
+            }]
+        );
+
+        assert_eq!(measurements[1].mac_address, 0x1100);
+        let measurement = &measurements[1].measurement;
+        assert_eq!(measurement.message_type, 0x00);
+        assert_eq!(measurement.round_index, 2);
+        assert_eq!(measurement.tx_timestamp_40, Some(2 * round_duration));
+        assert_eq!(
+            measurement.rx_timestamp_40,
+            Some(2 * round_duration + time_of_flight)
+        );
+        assert_eq!(measurement.relative_location, vec![location]);
+
+        // The block index follows the ranging rounds.
+        assert!(pica.advance_clock());
+        let ntf: ShortMacDlTDoASessionInfoNtf = tag.expect().await;
+        assert_eq!(ntf.sequence_number, 1);
+        assert_eq!(ntf.dl_tdoa_measurements[0].measurement.block_index, 1);
+    }
+
+    /// Ranging estimator placing the devices on a line,
+    /// 30 meters apart in order of handle.
+    struct LinearRangingEstimator;
+
+    impl RangingEstimator for LinearRangingEstimator {
+        fn estimate(
+            &self,
+            left: &Handle,
+            right: &Handle,
+            _context: &RangingContext,
+        ) -> Option<RangingMeasurement> {
+            Some(RangingMeasurement {
+                range: 3000 * left.abs_diff(*right) as u16,
+                ..TEST_MEASUREMENT
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dl_tdoa_link_budget() {
+        let mut pica = Pica::new(Box::new(LinearRangingEstimator), None)
+            .with_clock_mode(ClockMode::Step)
+            .with_link_budget(LinkBudget::default());
+        let mut tag = TestHost::connect(&mut pica).await;
+        // The default link budget reaches about 52 meters: the first
+        // anchor is in range, the second is not.
+        create_anchor(&mut pica, MacAddress::Short([0, 0x10]));
+        create_anchor(&mut pica, MacAddress::Short([0, 0x11]));
+
+        tag.start_session(
+            &mut pica,
+            1,
+            SessionType::FiraRangingSession,
+            app_config_tlvs(
+                DeviceRole::DtTag,
+                DeviceType::Controlee,
+                RangingRoundUsage::OnWayRangingDlTdoa,
+                [0, 0x12],
+            ),
+        )
+        .await;
+
+        assert!(pica.advance_clock());
+        let ntf: ShortMacDlTDoASessionInfoNtf = tag.expect().await;
+        let measurements = ntf.dl_tdoa_measurements;
+        assert_eq!(measurements.len(), 1);
+        assert_eq!(measurements[0].mac_address, 0x1000);
+    }
+
+    #[tokio::test]
+    async fn test_owr_aoa_ranging() {
+        let mut pica = pica().with_clock_mode(ClockMode::Step);
+        let mut advertiser = TestHost::connect(&mut pica).await;
+        let mut other_advertiser = TestHost::connect(&mut pica).await;
+        let mut observer = TestHost::connect(&mut pica).await;
+        create_anchor(&mut pica, MacAddress::Short([0, 0x20]));
+
+        for (host, mac_address) in [
+            (&mut advertiser, [0, 0x21]),
+            (&mut other_advertiser, [0, 0x22]),
+        ] {
+            host.start_session(
+                &mut pica,
+                1,
+                SessionType::FiraRangingSession,
+                app_config_tlvs(
+                    DeviceRole::Advertiser,
+                    DeviceType::Controller,
+                    RangingRoundUsage::OwrAoaMeasurement,
+                    mac_address,
+                ),
+            )
+            .await;
+        }
+
+        // The observer selects the anchor and the first advertiser,
+        // and is notified every other ranging round.
+        let mut observer_tlvs = app_config_tlvs(
+            DeviceRole::Observer,
+            DeviceType::Controlee,
+            RangingRoundUsage::OwrAoaMeasurement,
+            [0, 0x23],
+        );
+        observer_tlvs.extend([
+            tlv(AppConfigTlvType::NumberOfControlees, &[2]),
+            tlv(AppConfigTlvType::DstMacAddress, &[0, 0x20, 0, 0x21]),
+            tlv(AppConfigTlvType::OwrAoaMeasurementNtfPeriod, &[2]),
+        ]);
+        let session_handle = observer
+            .start_session(&mut pica, 1, SessionType::FiraRangingSession, observer_tlvs)
+            .await;
+
+        for round_index in [0, 2] {
+            assert!(pica.advance_clock());
+            if round_index > 0 {
+                assert!(pica.advance_clock());
+            }
+            let ntf: ShortMacOwrAoaSessionInfoNtf = observer.expect().await;
+            assert_eq!(ntf.session_token, session_handle);
+            assert_eq!(ntf.sequence_number, round_index / 2);
+
+            let measurements = ntf.owr_aoa_ranging_measurements;
+            assert_eq!(
+                measurements
+                    .iter()
+                    .map(|measurement| measurement.mac_address)
+                    .collect::<Vec<_>>(),
+                vec![0x2000, 0x2100]
+            );
+            for measurement in measurements {
+                assert_eq!(measurement.status, uci::Status::Ok);
+                assert_eq!(measurement.frame_sequence_number, round_index as u8);
+                assert_eq!(measurement.block_index, round_index as u16);
+                assert_eq!(measurement.aoa_azimuth, TEST_MEASUREMENT.azimuth as u16);
+                assert_eq!(measurement.aoa_azimuth_fom, TEST_MEASUREMENT.azimuth_fom);
+                assert_eq!(measurement.aoa_elevation, TEST_MEASUREMENT.elevation as u16);
+                assert_eq!(
+                    measurement.aoa_elevation_fom,
+                    TEST_MEASUREMENT.elevation_fom
+                );
+            }
+        }
+
+        // The advertisers do not report measurements.
+        advertiser.send(&mut pica, CoreGetCapsInfoCmd {});
+        advertiser.expect::<CoreGetCapsInfoRsp>().await;
+        assert!(advertiser.uci_rx.try_next().is_err());
+    }
+
+    /// Read the value of an app configuration parameter of a session.
+    async fn get_app_config(
+        host: &mut TestHost,
+        pica: &mut Pica,
+        session_handle: u32,
+        cfg_id: AppConfigTlvType,
+    ) -> Vec<u8> {
+        host.send(
+            pica,
+            SessionGetAppConfigCmd {
+                session_token: session_handle,
+                app_cfg: vec![cfg_id],
+            },
+        );
+        let rsp: SessionGetAppConfigRsp = host.expect().await;
+        assert_eq!(rsp.status, uci::Status::Ok);
+        rsp.tlvs[0].v.clone()
+    }
+
+    #[tokio::test]
+    async fn test_ccc_ranging() {
+        let mut pica = pica().with_clock_mode(ClockMode::Step);
+        let mut controller = TestHost::connect(&mut pica).await;
+        let mut controlee = TestHost::connect(&mut pica).await;
+        let mut fira = TestHost::connect(&mut pica).await;
+
+        // FiRa sessions do not range with CCC sessions.
+        fira.start_session(
+            &mut pica,
+            1,
+            SessionType::FiraRangingSession,
+            app_config_tlvs(
+                DeviceRole::Responder,
+                DeviceType::Controlee,
+                RangingRoundUsage::DsTwrDeferredMode,
+                [0, 0x31],
+            ),
+        )
+        .await;
+
+        let ccc_tlvs = |device_type: DeviceType| {
+            vec![
+                tlv(AppConfigTlvType::DeviceType, &[device_type.into()]),
+                tlv(AppConfigTlvType::HoppingMode, &[HoppingMode::Enable.into()]),
+                tlv(AppConfigTlvType::CccHopModeKey, &0x1234u32.to_le_bytes()),
+                tlv(AppConfigTlvType::StsIndex, &10u32.to_le_bytes()),
+            ]
+        };
+
+        // The first ranging block of the controller starts at the
+        // configured UWB_TIME0, the first ranging block of the controlee
+        // one ranging interval after the session start.
+        let mut controller_tlvs = ccc_tlvs(DeviceType::Controller);
+        controller_tlvs.push(tlv(
+            AppConfigTlvType::CccUwbTime0,
+            &500_000u64.to_le_bytes(),
+        ));
+        let controller_session_handle = controller
+            .start_session(&mut pica, 1, SessionType::Ccc, controller_tlvs)
+            .await;
+        let controlee_session_handle = controlee
+            .start_session(
+                &mut pica,
+                1,
+                SessionType::Ccc,
+                ccc_tlvs(DeviceType::Controlee),
+            )
+            .await;
+        assert_eq!(
+            get_app_config(
+                &mut controlee,
+                &mut pica,
+                controlee_session_handle,
+                AppConfigTlvType::CccUwbTime0
+            )
+            .await,
+            200_000u64.to_le_bytes()
+        );
+
+        let controller_mac_address = u16::from_le_bytes(
+            pica.get_device(controller.handle)
+                .unwrap()
+                .mac_address
+                .short_address(),
+        );
+        let controlee_mac_address = u16::from_le_bytes(
+            pica.get_device(controlee.handle)
+                .unwrap()
+                .mac_address
+                .short_address(),
+        );
+        let app_config = pica
+            .get_device(controlee.handle)
+            .unwrap()
+            .session(controlee_session_handle)
+            .unwrap()
+            .app_config
+            .clone();
+
+        for block_index in [0, 1] {
+            assert!(pica.advance_clock());
+            assert_eq!(
+                pica.clock.now(),
+                Duration::from_millis(200 * (block_index as u64 + 1))
+            );
+            let ntf: ShortMacTwoWaySessionInfoNtf = controlee.expect().await;
+            assert_eq!(ntf.session_token, controlee_session_handle);
+            assert_eq!(ntf.sequence_number, block_index);
+
+            // Only the distance is measured, and the ranging round
+            // selected by the hopping sequence is reported.
+            let measurements = ntf.two_way_ranging_measurements;
+            assert_eq!(measurements.len(), 1);
+            let measurement = &measurements[0];
+            assert_eq!(measurement.mac_address, controller_mac_address);
+            assert_eq!(measurement.status, uci::Status::Ok);
+            assert_eq!(measurement.distance, TEST_MEASUREMENT.range);
+            assert_eq!(measurement.aoa_azimuth, 0);
+            assert_eq!(measurement.aoa_azimuth_fom, 0);
+            assert_eq!(measurement.aoa_elevation, 0);
+            assert_eq!(measurement.aoa_elevation_fom, 0);
+            assert_eq!(measurement.aoa_destination_azimuth_fom, 0);
+            assert_eq!(measurement.aoa_destination_elevation_fom, 0);
+            assert_eq!(
+                measurement.slot_index,
+                app_config.ccc_ranging_round_index(block_index)
+            );
+
+            // Each ranging block consumes one STS index.
+            assert_eq!(
+                get_app_config(
+                    &mut controlee,
+                    &mut pica,
+                    controlee_session_handle,
+                    AppConfigTlvType::CccLastIndexUsed
+                )
+                .await,
+                (10 + block_index).to_le_bytes()
+            );
+        }
+
+        // The controller selects the same ranging round in the first block.
+        controller.send(&mut pica, CoreGetCapsInfoCmd {});
+        controller.expect::<CoreGetCapsInfoRsp>().await;
+        assert!(controller.uci_rx.try_next().is_err());
+
+        assert!(pica.advance_clock());
+        assert_eq!(pica.clock.now(), Duration::from_millis(500));
+        let ntf: ShortMacTwoWaySessionInfoNtf = controller.expect().await;
+        assert_eq!(ntf.session_token, controller_session_handle);
+        assert_eq!(ntf.sequence_number, 0);
+        let measurements = ntf.two_way_ranging_measurements;
+        assert_eq!(measurements.len(), 1);
+        assert_eq!(measurements[0].mac_address, controlee_mac_address);
+        assert_eq!(
+            measurements[0].slot_index,
+            app_config.ccc_ranging_round_index(0)
+        );
+    }
+
+    /// Initialize and configure the session of type DEVICE_TEST_MODE,
+    /// with tests lasting for the selected number of packets of 1ms.
+    async fn init_test_session(host: &mut TestHost, pica: &mut Pica, num_packets: u32) -> u32 {
+        host.send(
+            pica,
+            SessionInitCmd {
+                session_id: 1,
+                session_type: SessionType::DeviceTestMode,
+            },
+        );
+        let session_handle = host.expect::<SessionInitRsp_V2>().await.session_handle;
+        host.send(
+            pica,
+            SessionSetAppConfigCmd {
+                session_token: session_handle,
+                tlvs: vec![tlv(AppConfigTlvType::ChannelNumber, &[9])],
+            },
+        );
+        host.expect::<SessionSetAppConfigRsp>().await;
+        host.send(
+            pica,
+            TestConfigSetCmd {
+                session_token: session_handle,
+                tlvs: vec![
+                    TestConfigTlv {
+                        cfg_id: TestConfigTlvType::NumPackets,
+                        v: num_packets.to_le_bytes().to_vec(),
+                    },
+                    TestConfigTlv {
+                        cfg_id: TestConfigTlvType::TGap,
+                        v: 1000u32.to_le_bytes().to_vec(),
+                    },
+                ],
+            },
+        );
+        let rsp: TestConfigSetRsp = host.expect().await;
+        assert_eq!(rsp.status, uci::Status::Ok);
+        session_handle
+    }
+
+    #[tokio::test]
+    async fn test_periodic_tx_per_rx() {
+        let mut pica = pica().with_clock_mode(ClockMode::Step);
+        let mut transmitter = TestHost::connect(&mut pica).await;
+        let mut receiver = TestHost::connect(&mut pica).await;
+        let tx_session_handle = init_test_session(&mut transmitter, &mut pica, 20).await;
+        let rx_session_handle = init_test_session(&mut receiver, &mut pica, 10).await;
+
+        // Only one test session can exist.
+        receiver.send(
+            &mut pica,
+            SessionInitCmd {
+                session_id: 2,
+                session_type: SessionType::DeviceTestMode,
+            },
+        );
+        let rsp: SessionInitRsp_V2 = receiver.expect().await;
+        assert_eq!(rsp.status, uci::Status::Rejected);
+
+        transmitter.send(
+            &mut pica,
+            TestPeriodicTxCmd {
+                psdu_data: vec![0x01, 0x02, 0x03, 0x04],
+            },
+        );
+        let rsp: TestPeriodicTxRsp = transmitter.expect().await;
+        assert_eq!(rsp.status, uci::Status::Ok);
+        let ntf: SessionStatusNtf = transmitter.expect().await;
+        assert_eq!(ntf.session_token, tx_session_handle);
+        assert_eq!(ntf.session_state, SessionState::SessionStateActive);
+
+        // The last octet of the expected PSDU differs by one bit.
+        receiver.send(
+            &mut pica,
+            TestPerRxCmd {
+                psdu_data: vec![0x01, 0x02, 0x03, 0x05],
+            },
+        );
+        let rsp: TestPerRxRsp = receiver.expect().await;
+        assert_eq!(rsp.status, uci::Status::Ok);
+
+        // The PER_RX test completes first, while the peer is transmitting.
+        assert!(pica.advance_clock());
+        let ntf: TestPerRxNtf = receiver.expect().await;
+        assert_eq!(ntf.status, uci::Status::Ok);
+        assert_eq!(ntf.attempts, 10);
+        assert_eq!(ntf.acq_detect, 10);
+        assert_eq!(ntf.rx_fail, 0);
+        assert_eq!(ntf.psdu_dec_error, 10);
+        assert_eq!(ntf.psdu_bit_error, 10);
+        let ntf: SessionStatusNtf = receiver.expect().await;
+        assert_eq!(ntf.session_token, rx_session_handle);
+        assert_eq!(ntf.session_state, SessionState::SessionStateIdle);
+
+        // Stopping the PERIODIC_TX test cancels its notification.
+        transmitter.send(&mut pica, TestStopSessionCmd {});
+        let rsp: TestStopSessionRsp = transmitter.expect().await;
+        assert_eq!(rsp.status, uci::Status::Ok);
+        let ntf: SessionStatusNtf = transmitter.expect().await;
+        assert_eq!(ntf.session_state, SessionState::SessionStateIdle);
+        assert!(!pica.advance_clock());
+
+        transmitter.send(&mut pica, TestStopSessionCmd {});
+        let rsp: TestStopSessionRsp = transmitter.expect().await;
+        assert_eq!(rsp.status, uci::Status::Rejected);
+
+        // The test is dropped when the device enters the error state,
+        // and a test completion already queued is ignored.
+        transmitter.send(&mut pica, TestPeriodicTxCmd { psdu_data: vec![] });
+        transmitter.expect::<TestPeriodicTxRsp>().await;
+        transmitter.expect::<CoreDeviceStatusNtf>().await;
+        let (rsp_tx, _rsp_rx) = oneshot::channel();
+        pica.set_device_error(MacAddress::Short([0, 0]), rsp_tx);
+        pica.pica_command(PicaCommand::Test(transmitter.handle, tx_session_handle));
+        let ntf: CoreDeviceStatusNtf = transmitter.expect().await;
+        assert_eq!(ntf.device_state, DeviceState::DeviceStateError);
+        assert!(transmitter.uci_rx.try_next().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_app_config() {
+        // The device only supports the channel 9,
+        // the responder role, and no hopping.
+        let profile = DeviceProfile {
+            caps: vec![
+                ProfileCap {
+                    id: CapTlvType::SupportedChannels,
+                    value: vec![0x08],
+                },
+                ProfileCap {
+                    id: CapTlvType::SupportedHoppingMode,
+                    value: vec![0x00],
+                },
+            ],
+            device_roles: Some(vec![SupportedDeviceRole::Responder]),
+            ..Default::default()
+        };
+        let mut pica = pica()
+            .with_clock_mode(ClockMode::Step)
+            .with_device_profiles([(DEFAULT_PROFILE.to_owned(), profile)]);
+        let mut host = TestHost::connect(&mut pica).await;
+
+        // Active sessions reconfigured with unsupported values are stopped,
+        // with the reason code of the unsupported parameter.
+        for (session_id, cfg, reason_code) in [
+            (
+                1,
+                tlv(AppConfigTlvType::ChannelNumber, &[5]),
+                ReasonCode::ErrorChannelNotSupported,
+            ),
+            (
+                2,
+                tlv(
+                    AppConfigTlvType::DeviceRole,
+                    &[DeviceRole::Initiator.into()],
+                ),
+                ReasonCode::ErrorDeviceRoleNotSupported,
+            ),
+            (
+                3,
+                tlv(AppConfigTlvType::HoppingMode, &[HoppingMode::Enable.into()]),
+                ReasonCode::ErrorHoppingModeNotSupported,
+            ),
+        ] {
+            let session_handle = host
+                .start_session(
+                    &mut pica,
+                    session_id,
+                    SessionType::FiraRangingSession,
+                    app_config_tlvs(
+                        DeviceRole::Responder,
+                        DeviceType::Controlee,
+                        RangingRoundUsage::DsTwrDeferredMode,
+                        [0, 0x51],
+                    ),
+                )
+                .await;
+            let ntf: SessionStatusNtf = host.expect().await;
+            assert_eq!(ntf.session_state, SessionState::SessionStateActive);
+
+            let cfg_id = cfg.cfg_id;
+            host.send(
+                &mut pica,
+                SessionSetAppConfigCmd {
+                    session_token: session_handle,
+                    tlvs: vec![cfg],
+                },
+            );
+            let rsp: SessionSetAppConfigRsp = host.expect().await;
+            assert_eq!(rsp.status, uci::Status::InvalidParam);
+            assert_eq!(
+                rsp.cfg_status,
+                vec![AppConfigStatus {
+                    cfg_id,
+                    status: uci::Status::InvalidParam,
+                }]
+            );
+            let ntf: SessionStatusNtf = host.expect().await;
+            assert_eq!(ntf.session_token, session_handle);
+            assert_eq!(ntf.session_state, SessionState::SessionStateIdle);
+            assert_eq!(ntf.reason_code, u8::from(reason_code));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_uci_v1_1() {
+        let profile = DeviceProfile {
+            uci_version: UciVersion::V1_1,
+            ..Default::default()
+        };
+        let mut pica = pica()
+            .with_clock_mode(ClockMode::Step)
+            .with_device_profiles([(DEFAULT_PROFILE.to_owned(), profile)]);
+        let mut host = TestHost::connect(&mut pica).await;
+        host.expect::<CoreDeviceStatusNtf>().await;
+
+        // The commands introduced in UCI 2.0 are unknown.
+        host.send(&mut pica, CoreQueryTimeStampCmd {});
+        assert_eq!(
+            host.receive().await,
+            vec![0x40, 0x08, 0x00, 0x01, uci::Status::UnknownOid.into()]
+        );
+
+        // The session ID is used as session handle,
+        // and is not returned in the response.
+        let session_id = 0x1234;
+        host.send(
+            &mut pica,
+            SessionInitCmd {
+                session_id,
+                session_type: SessionType::FiraRangingSession,
+            },
+        );
+        let rsp: SessionInitRsp = host.expect().await;
+        assert_eq!(rsp.status, uci::Status::Ok);
+
+        // The UWB_INITIATION_TIME is relative to the session start,
+        // in milliseconds.
+        let mut tlvs = app_config_tlvs(
+            DeviceRole::Initiator,
+            DeviceType::Controller,
+            RangingRoundUsage::DsTwrDeferredMode,
+            [0, 0x61],
+        );
+        tlvs.extend([
+            tlv(
+                AppConfigTlvType::MultiNodeMode,
+                &[MultiNodeMode::OneToMany.into()],
+            ),
+            tlv(AppConfigTlvType::UwbInitiationTime, &500u32.to_le_bytes()),
+        ]);
+        host.send(
+            &mut pica,
+            SessionSetAppConfigCmd {
+                session_token: session_id,
+                tlvs,
+            },
+        );
+        let rsp: SessionSetAppConfigRsp = host.expect().await;
+        assert_eq!(rsp.status, uci::Status::Ok);
+
+        // The multicast list update response only includes the status,
+        // the status of the controlees is reported in the notification.
+        host.send(
+            &mut pica,
+            SessionUpdateControllerMulticastListCmd {
+                session_token: session_id,
+                action: UpdateMulticastListAction::AddControlee,
+                payload: SessionUpdateControllerMulticastListCmdPayload {
+                    controlees: vec![Controlee {
+                        short_address: [0, 0x62],
+                        subsession_id: 7,
+                    }],
+                }
+                .encode_to_vec()
+                .unwrap(),
+            },
+        );
+        let rsp: SessionUpdateControllerMulticastListRsp_V1 = host.expect().await;
+        assert_eq!(rsp.status, uci::Status::Ok);
+        let ntf: SessionUpdateControllerMulticastListNtf_V1 = host.expect().await;
+        assert_eq!(ntf.session_token, session_id);
+        assert_eq!(
+            ntf.remaining_multicast_list_size as usize,
+            device::MAX_NUMBER_OF_CONTROLEES - 1
+        );
+        assert_eq!(
+            ntf.controlee_status,
+            vec![ControleeStatus_V1 {
+                mac_address: [0, 0x62],
+                subsession_id: 7,
+                status: MulticastUpdateStatus::OkMulticastListUpdate,
+            }]
+        );
+
+        host.send(&mut pica, SessionStartCmd { session_id });
+        let rsp: SessionStartRsp = host.expect().await;
+        assert_eq!(rsp.status, uci::Status::Ok);
+        assert!(pica.advance_clock());
+        assert_eq!(pica.clock.now(), Duration::from_millis(500));
+        let ntf: ShortMacTwoWaySessionInfoNtf = host.expect().await;
+        assert_eq!(ntf.session_token, session_id);
+    }
+
+    #[tokio::test]
+    async fn test_device_error_state() {
+        let mut pica = pica().with_clock_mode(ClockMode::Step);
+        let mut host = TestHost::connect(&mut pica).await;
+        let mac_address = pica.get_device(host.handle).unwrap().mac_address;
+        host.start_session(
+            &mut pica,
+            1,
+            SessionType::FiraRangingSession,
+            app_config_tlvs(
+                DeviceRole::Responder,
+                DeviceType::Controlee,
+                RangingRoundUsage::DsTwrDeferredMode,
+                [0, 0x41],
+            ),
+        )
+        .await;
+        host.expect::<CoreDeviceStatusNtf>().await;
+
+        // The ranging is stopped, and the commands are rejected.
+        let (rsp_tx, mut rsp_rx) = oneshot::channel();
+        pica.pica_command(PicaCommand::SetDeviceError(mac_address, rsp_tx));
+        assert_eq!(rsp_rx.try_recv().unwrap().unwrap(), host.handle);
+        let ntf: CoreDeviceStatusNtf = host.expect().await;
+        assert_eq!(ntf.device_state, DeviceState::DeviceStateError);
+        assert!(!pica.advance_clock());
+
+        host.send(&mut pica, SessionGetCountCmd {});
+        assert_eq!(
+            host.receive().await,
+            vec![0x41, 0x05, 0x00, 0x01, uci::Status::Rejected.into()]
+        );
+
+        // The device recovers when reset by the host,
+        // and the sessions are deinitialized.
+        host.send(
+            &mut pica,
+            CoreDeviceResetCmd {
+                reset_config: ResetConfig::UwbsReset,
+            },
+        );
+        let rsp: CoreDeviceResetRsp = host.expect().await;
+        assert_eq!(rsp.status, uci::Status::Ok);
+        let ntf: CoreDeviceStatusNtf = host.expect().await;
+        assert_eq!(ntf.device_state, DeviceState::DeviceStateReady);
+
+        host.send(&mut pica, SessionGetCountCmd {});
+        let rsp: SessionGetCountRsp = host.expect().await;
+        assert_eq!(rsp.status, uci::Status::Ok);
+        assert_eq!(rsp.session_count, 0);
+
+        host.start_session(
+            &mut pica,
+            1,
+            SessionType::FiraRangingSession,
+            app_config_tlvs(
+                DeviceRole::Responder,
+                DeviceType::Controlee,
+                RangingRoundUsage::DsTwrDeferredMode,
+                [0, 0x41],
+            ),
+        )
+        .await;
+        assert!(pica.advance_clock());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_data_message_snd() {
+        let mut pica = pica().with_clock_mode(ClockMode::Step);
+        let mut host = TestHost::connect(&mut pica).await;
+        let session_handle = host
+            .start_session(
+                &mut pica,
+                1,
+                SessionType::FiraRangingSession,
+                app_config_tlvs(
+                    DeviceRole::Initiator,
+                    DeviceType::Controller,
+                    RangingRoundUsage::DsTwrDeferredMode,
+                    [0, 0x51],
+                ),
+            )
+            .await;
+
+        // Corrupt the application data length, as done by the corrupt
+        // fault on packets received from the host.
+        let mut packet = DataMessageSnd {
+            pbf: PacketBoundaryFlag::Complete,
+            session_handle,
+            destination_address: 0x52,
+            data_sequence_number: 1,
+            application_data: vec![1, 2, 3, 4],
+        }
+        .encode_to_vec()
+        .unwrap();
+        packet[HEADER_SIZE + 14] ^= 0x80;
+        pica.pica_command(PicaCommand::UciPacket(host.handle, packet));
+
+        let ntf: SessionDataTransferStatusNtf = host.expect().await;
+        assert_eq!(ntf.session_token, session_handle);
+        assert_eq!(
+            ntf.status,
+            DataTransferNtfStatusCode::UciDataTransferStatusInvalidFormat
+        );
+
+        // The device keeps processing the commands.
+        host.send(&mut pica, CoreGetCapsInfoCmd {});
+        let rsp: CoreGetCapsInfoRsp = host.expect().await;
+        assert_eq!(rsp.status, uci::Status::Ok);
+    }
+
+    #[test]
+    fn test_data_message_rcv_segmentation() {
+        let message = session::DataMessage {
+            destination_address: 0x1234,
+            sequence_number: 7,
+            application_data: (0..2000).map(|index| index as u8).collect(),
+            tx_count: 0,
+        };
+        let segments: Vec<DataMessageRcv> = make_data_message_rcv(1, 0x5678, &message)
+            .iter()
+            .map(|packet| {
+                assert!(packet.len() <= HEADER_SIZE + MAX_DATA_PACKET_PAYLOAD_SIZE);
+                DataMessageRcv::decode_full(packet).unwrap()
+            })
+            .collect();
+        assert_eq!(
+            segments
+                .iter()
+                .map(|segment| segment.pbf)
+                .collect::<Vec<_>>(),
+            vec![
+                PacketBoundaryFlag::NotComplete,
+                PacketBoundaryFlag::Complete
+            ]
+        );
+        for segment in segments.iter() {
+            assert_eq!(segment.session_handle, 1);
+            assert_eq!(segment.source_address, 0x5678);
+            assert_eq!(segment.data_sequence_number, 7);
+        }
+        assert_eq!(
+            segments
+                .iter()
+                .flat_map(|segment| segment.application_data.clone())
+                .collect::<Vec<_>>(),
+            message.application_data
+        );
+
+        // Empty messages are delivered in a single packet.
+        let message = session::DataMessage {
+            application_data: vec![],
+            ..message
+        };
+        let segments = make_data_message_rcv(1, 0x5678, &message);
+        assert_eq!(segments.len(), 1);
+        assert_eq!(
+            DataMessageRcv::decode_full(&segments[0]).unwrap().pbf,
+            PacketBoundaryFlag::Complete
+        );
+    }
+
+    #[test]
+    fn test_assign_mac_address() {
+        let declared = MacAddress::new("00:02".to_owned()).unwrap();
+        let pica = pica()
+            .with_devices([DeviceDeclaration {
+                mac_address: declared,
+                hint: Some(ConnectionHint::Port(7001)),
+                profile: None,
+            }])
+            .unwrap();
+        assert_eq!(pica.assign_mac_address(1, None), MacAddress::Short([0, 1]));
+        assert_eq!(pica.assign_mac_address(2, None), MacAddress::Short([0, 3]));
+
+        let pica = pica.with_extended_mac_addresses();
+        assert_eq!(
+            pica.assign_mac_address(1, None),
+            MacAddress::Extended([0, 0, 0, 0, 0, 0, 0, 1])
+        );
+    }
+}
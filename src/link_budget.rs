@@ -0,0 +1,116 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::packets::uci;
+use crate::AppConfig;
+use anyhow::Context;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Link budget limiting the range at which two devices can measure
+/// each other. The maximum range is derived from the maximum path loss
+/// with a log-distance path loss model, and depends on the channel,
+/// PRF mode and preamble duration configured for the session.
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct LinkBudget {
+    /// Maximum path loss, in dB, with BPRF and a preamble
+    /// of 64 symbols.
+    pub max_path_loss: f64,
+    /// Path loss exponent.
+    pub path_loss_exponent: f64,
+    /// Sensitivity loss of the HPRF modes, in dB.
+    pub hprf_loss: f64,
+    /// Sensitivity loss of the preamble of 32 symbols, in dB.
+    pub short_preamble_loss: f64,
+}
+
+impl Default for LinkBudget {
+    fn default() -> Self {
+        LinkBudget {
+            max_path_loss: 85.0,
+            path_loss_exponent: 2.0,
+            hprf_loss: 3.0,
+            short_preamble_loss: 3.0,
+        }
+    }
+}
+
+/// Center frequency of the UWB channel, in MHz.
+fn center_frequency(channel_number: uci::ChannelNumber) -> f64 {
+    match channel_number {
+        uci::ChannelNumber::ChannelNumber5 => 6489.6,
+        uci::ChannelNumber::ChannelNumber6 => 6988.8,
+        uci::ChannelNumber::ChannelNumber8 => 7488.0,
+        uci::ChannelNumber::ChannelNumber9 => 7987.2,
+        uci::ChannelNumber::ChannelNumber10 => 8486.4,
+        uci::ChannelNumber::ChannelNumber12 => 9484.8,
+        uci::ChannelNumber::ChannelNumber13 => 9984.0,
+        uci::ChannelNumber::ChannelNumber14 => 10483.2,
+    }
+}
+
+impl LinkBudget {
+    /// Load the link budget from a JSON file. Missing parameters
+    /// take their default values.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read link budget file {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("failed to parse link budget file {}", path.display()))
+    }
+
+    /// Maximum range, in centimeters, for the session configuration.
+    pub fn max_range(&self, app_config: &AppConfig) -> u16 {
+        let mut max_path_loss = self.max_path_loss;
+        if app_config.prf_mode != uci::PrfMode::BprfMode {
+            max_path_loss -= self.hprf_loss;
+        }
+        if app_config.preamble_duration == uci::PreambleDuration::Duration32Symbols {
+            max_path_loss -= self.short_preamble_loss;
+        }
+
+        // Free space path loss at one meter.
+        let wavelength = 299.792458 / center_frequency(app_config.channel_number);
+        let reference_path_loss = 20.0 * (4.0 * std::f64::consts::PI / wavelength).log10();
+
+        let max_range_m =
+            10f64.powf((max_path_loss - reference_path_loss) / (10.0 * self.path_loss_exponent));
+        (100.0 * max_range_m).round().clamp(0.0, u16::MAX as f64) as u16
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_range() {
+        let link_budget = LinkBudget::default();
+        let mut app_config = AppConfig::default();
+
+        let max_range = link_budget.max_range(&app_config);
+        assert!((5000..5500).contains(&max_range), "max_range {}", max_range);
+
+        app_config.channel_number = uci::ChannelNumber::ChannelNumber5;
+        assert!(link_budget.max_range(&app_config) > max_range);
+
+        // 6 dB less of link budget halves the range with the free space model.
+        app_config.channel_number = uci::ChannelNumber::ChannelNumber9;
+        app_config.prf_mode = uci::PrfMode::HprfMode124m8;
+        app_config.preamble_duration = uci::PreambleDuration::Duration32Symbols;
+        let reduced_range = link_budget.max_range(&app_config);
+        assert!(reduced_range.abs_diff(max_range / 2) < 50);
+    }
+}
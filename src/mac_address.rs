@@ -41,6 +41,19 @@ impl MacAddress {
     pub fn new(mac_address: String) -> Result<Self, Error> {
         mac_address.try_into()
     }
+
+    /// Return the address in the short (2 bytes) format.
+    /// Extended addresses are truncated to their two least
+    /// significant bytes.
+    pub fn short_address(&self) -> [u8; SHORT_MAC_ADDRESS_SIZE] {
+        (u64::from(self) as u16).to_le_bytes()
+    }
+
+    /// Return the address in the extended (8 bytes) format.
+    /// Short addresses are zero extended.
+    pub fn extended_address(&self) -> [u8; EXTENDED_MAC_ADDRESS_SIZE] {
+        u64::from(self).to_le_bytes()
+    }
 }
 
 impl From<&MacAddress> for u64 {
@@ -175,6 +188,22 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_short_and_extended_address() {
+        let short_mac = MacAddress::Short([0x01, 0x02]);
+        assert_eq!(short_mac.short_address(), [0x01, 0x02]);
+        assert_eq!(
+            short_mac.extended_address(),
+            [0x01, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]
+        );
+        let extended_mac = MacAddress::Extended([0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08]);
+        assert_eq!(extended_mac.short_address(), [0x01, 0x02]);
+        assert_eq!(
+            extended_mac.extended_address(),
+            [0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08]
+        );
+    }
+
     #[test]
     fn test_extended_mac_to_u64() {
         let extended_mac = MacAddress::Extended([0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08]);
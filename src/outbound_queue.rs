@@ -0,0 +1,111 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::UciPacket;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+/// Ordered queue of the packets sent by a device to the host.
+/// The queue is shared by the device and its sessions.
+///
+/// [UCI] The response to a command is sent before the notifications
+/// generated while handling the command. The notifications are
+/// held in the queue until the response is sent.
+#[derive(Clone)]
+pub(crate) struct OutboundQueue {
+    tx: mpsc::UnboundedSender<UciPacket>,
+    held: Arc<Mutex<Option<Vec<UciPacket>>>>,
+}
+
+impl OutboundQueue {
+    pub fn new(tx: mpsc::UnboundedSender<UciPacket>) -> Self {
+        OutboundQueue {
+            tx,
+            held: Default::default(),
+        }
+    }
+
+    /// Send a packet to the host. The packet is held if a command
+    /// is being handled.
+    pub fn send(&self, packet: UciPacket) -> Result<(), mpsc::error::SendError<UciPacket>> {
+        match self.held.lock().unwrap().as_mut() {
+            Some(held) => {
+                held.push(packet);
+                Ok(())
+            }
+            None => self.tx.send(packet),
+        }
+    }
+
+    /// Hold the packets sent until the response to the command
+    /// being handled is sent.
+    pub fn hold(&self) {
+        self.held.lock().unwrap().get_or_insert_with(Vec::new);
+    }
+
+    /// Send the response to the command being handled,
+    /// followed by the held packets.
+    pub fn send_response(
+        &self,
+        packet: UciPacket,
+    ) -> Result<(), mpsc::error::SendError<UciPacket>> {
+        let held = self.held.lock().unwrap().take().unwrap_or_default();
+        self.tx.send(packet)?;
+        for packet in held {
+            self.tx.send(packet)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_first() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let queue = OutboundQueue::new(tx);
+        let session_queue = queue.clone();
+
+        queue.hold();
+        queue.send(vec![1]).unwrap();
+        session_queue.send(vec![2]).unwrap();
+        assert!(rx.try_recv().is_err());
+
+        queue.send_response(vec![0]).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), vec![0]);
+        assert_eq!(rx.try_recv().unwrap(), vec![1]);
+        assert_eq!(rx.try_recv().unwrap(), vec![2]);
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_notifications_only() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let queue = OutboundQueue::new(tx);
+
+        // The notifications sent outside of a command are not held.
+        queue.send(vec![1]).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), vec![1]);
+
+        // The notifications are not held after the response is sent.
+        queue.hold();
+        queue.send_response(vec![0]).unwrap();
+        queue.send(vec![2]).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), vec![0]);
+        assert_eq!(rx.try_recv().unwrap(), vec![2]);
+        assert!(rx.try_recv().is_err());
+    }
+}
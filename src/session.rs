@@ -16,47 +16,103 @@
 //! - [MAC] FiRa Consortium UWB MAC Technical Requirements
 //! - [UCI] FiRa Consortium UWB Command Interface Generic Technical specification
 
+use crate::clock::Timer;
+use crate::outbound_queue::OutboundQueue;
 use crate::packets::uci::{self, *};
-use crate::{AppConfig, MacAddress};
-use bytes::BytesMut;
+use crate::test_mode::{Test, TestConfig};
+use crate::{AppConfig, MacAddress, RangingContext};
 use pdl_runtime::Packet;
+use std::collections::VecDeque;
 use std::time::Duration;
-use tokio::sync::mpsc;
-use tokio::task::JoinHandle;
-use tokio::time;
 
-use super::UciPacket;
+/// Maximum number of transmissions of an application data message.
+/// The transfer fails with the status UCI_DATA_TRANSFER_STATUS_ERROR_DATA_TRANSFER
+/// when the message could not be delivered after the last attempt.
+pub const MAX_DATA_TX_COUNT: u8 = 4;
+
+/// Application data message received from the host, cf. [UCI] DATA_MESSAGE_SND.
+pub struct DataMessage {
+    pub destination_address: u64,
+    pub sequence_number: u16,
+    pub application_data: Vec<u8>,
+    /// Number of transmissions of the message.
+    pub tx_count: u8,
+}
 
 pub struct Session {
     /// cf. [UCI] 7.1
     pub state: SessionState,
-    /// cf. [UCI] 7.2 Table 13: 4 octets unique random number generated by application
-    id: u32,
+    /// cf. [UCI] 7.2 Table 13: 4 octets unique random number generated by application.
+    /// The session ID is shared by the devices taking part in the session.
+    pub id: u32,
+    /// Session handle generated by the UWBS in SESSION_INIT_RSP, used
+    /// by the host to refer to the session in all subsequent commands.
+    pub handle: u32,
     device_handle: usize,
-    data: BytesMut,
+    /// Application data message being reassembled from the segments
+    /// received from the host.
+    data_message: Option<DataMessage>,
+    /// Set when the application data message being received is rejected,
+    /// the following segments of the message are discarded.
+    data_message_rejected: bool,
+    /// Application data messages waiting for transmission.
+    data_queue: VecDeque<DataMessage>,
+    /// Set when the host is allowed to send data packets,
+    /// cf. [UCI] SESSION_DATA_CREDIT_NTF.
+    data_credit: bool,
 
     pub session_type: SessionType,
     pub sequence_number: u32,
+    /// Number of ranging rounds elapsed since the session was created.
+    pub round_index: u32,
+    /// Number of consecutive failed ranging rounds, cf. [UCI] MAX_RR_RETRY.
+    pub failed_round_count: u16,
+    /// UWBS time of the first ranging round, in microseconds,
+    /// set when the session is started.
+    pub first_round_timestamp: Option<u64>,
     pub app_config: AppConfig,
-    pub ranging_task: Option<JoinHandle<()>>,
-    tx: mpsc::UnboundedSender<UciPacket>,
+    /// Ranging rounds configured for DT-Anchor sessions,
+    /// cf. [UCI] SESSION_UPDATE_DT_ANCHOR_RANGING_ROUNDS.
+    pub dt_anchor_ranging_rounds: Vec<DtAnchorRangingRound>,
+    /// Ranging rounds listened to by DT-Tag sessions; all ranging
+    /// rounds are listened to when empty,
+    /// cf. [UCI] SESSION_UPDATE_DT_TAG_RANGING_ROUNDS.
+    pub dt_tag_ranging_rounds: Vec<u8>,
+    /// Test configuration for sessions of type DEVICE_TEST_MODE.
+    pub test_config: TestConfig,
+    /// Test currently running in the session.
+    pub test: Option<Test>,
+    pub ranging_task: Option<Timer>,
+    tx: OutboundQueue,
 }
 
 impl Session {
     pub fn new(
         id: u32,
+        handle: u32,
         session_type: SessionType,
         device_handle: usize,
-        tx: mpsc::UnboundedSender<UciPacket>,
+        tx: OutboundQueue,
     ) -> Self {
         Self {
             state: SessionState::SessionStateDeinit,
             id,
+            handle,
             device_handle,
-            data: BytesMut::new(),
+            data_message: None,
+            data_message_rejected: false,
+            data_queue: VecDeque::new(),
+            data_credit: true,
             session_type,
             sequence_number: 0,
+            round_index: 0,
+            failed_round_count: 0,
+            first_round_timestamp: None,
             app_config: AppConfig::default(),
+            dt_anchor_ranging_rounds: vec![],
+            dt_tag_ranging_rounds: vec![],
+            test_config: TestConfig::default(),
+            test: None,
             ranging_task: None,
             tx,
         }
@@ -70,21 +126,27 @@ impl Session {
 
         // Send status notification
         self.state = session_state;
-        let tx = self.tx.clone();
-        let session_id = self.id;
-        tokio::spawn(async move {
-            time::sleep(Duration::from_millis(1)).await;
-            tx.send(
-                SessionStatusNtf {
-                    session_token: session_id,
-                    session_state,
-                    reason_code: reason_code.into(),
-                }
-                .encode_to_vec()
-                .unwrap(),
-            )
-            .unwrap()
-        });
+        let _ = self.tx.send(
+            SessionStatusNtf {
+                session_token: self.handle,
+                session_state,
+                reason_code: reason_code.into(),
+            }
+            .encode_to_vec()
+            .unwrap(),
+        );
+    }
+
+    /// Return the context provided to the ranging estimator for
+    /// measurements made in the selected ranging round of the session.
+    pub fn ranging_context(&self, round_index: u32, time: Duration) -> RangingContext<'_> {
+        RangingContext {
+            session_id: self.id,
+            session_type: self.session_type,
+            app_config: &self.app_config,
+            round_index,
+            time,
+        }
     }
 
     pub fn get_dst_mac_address(&self) -> &[MacAddress] {
@@ -95,20 +157,11 @@ impl Session {
         self.app_config.session_info_ntf_config != uci::SessionInfoNtfConfig::Disable
     }
 
-    #[allow(unused)]
     pub fn is_session_data_transfer_status_ntf_enabled(&self) -> bool {
         self.app_config.session_data_transfer_status_ntf_config
             != uci::SessionDataTransferStatusNtfConfig::Disable
     }
 
-    pub fn data(&self) -> &BytesMut {
-        &self.data
-    }
-
-    pub fn clear_data(&mut self) {
-        self.data.clear()
-    }
-
     pub fn session_type(&self) -> SessionType {
         self.session_type
     }
@@ -125,39 +178,199 @@ impl Session {
     }
 
     pub fn stop_ranging_task(&mut self) {
-        if let Some(handle) = &self.ranging_task {
-            handle.abort();
-            self.ranging_task = None;
+        if let Some(timer) = self.ranging_task.take() {
+            timer.cancel();
         }
     }
 
+    /// Return the size of the application data buffered for transmission.
+    fn buffered_data_size(&self) -> usize {
+        self.data_queue
+            .iter()
+            .chain(self.data_message.iter())
+            .map(|message| message.application_data.len())
+            .sum()
+    }
+
+    fn data_transfer_status_ntf(
+        &self,
+        sequence_number: u16,
+        status: DataTransferNtfStatusCode,
+        tx_count: u8,
+    ) -> SessionDataTransferStatusNtf {
+        SessionDataTransferStatusNtf {
+            session_token: self.handle,
+            uci_sequence_number: sequence_number,
+            status,
+            tx_count,
+        }
+    }
+
+    /// Receive a segment of an application data message from the host.
+    /// The segments of a message are reassembled until the segment with
+    /// the packet boundary flag set to complete, and the message is
+    /// queued for transmission in the next ranging rounds.
+    ///
+    /// The UWBS buffers the data transmitted in one ranging round: the
+    /// data credit is withdrawn while the buffer is full, and the packets
+    /// received without credit are rejected. Messages larger than the
+    /// maximum data size reported by SESSION_QUERY_DATA_SIZE_IN_RANGING
+    /// are rejected.
     pub fn data_message_snd(&mut self, data: DataMessageSnd) -> ControlPacket {
         log::debug!("[{}] data_message_snd", self.device_handle);
         let session_token = data.session_handle;
-        let uci_sequence_number = data.data_sequence_number as u8;
+        let sequence_number = data.data_sequence_number;
 
         if self.session_type != SessionType::FiraRangingAndInBandDataSession {
-            return SessionDataTransferStatusNtf {
-                session_token,
-                status: DataTransferNtfStatusCode::UciDataTransferStatusSessionTypeNotSupported,
-                tx_count: 1, // TODO: support for retries?
-                uci_sequence_number,
-            }
-            .try_into()
-            .unwrap();
+            return self
+                .data_transfer_status_ntf(
+                    sequence_number,
+                    DataTransferNtfStatusCode::UciDataTransferStatusSessionTypeNotSupported,
+                    0,
+                )
+                .try_into()
+                .unwrap();
         }
 
-        assert_eq!(self.id, session_token);
+        assert_eq!(self.handle, session_token);
 
-        self.data.extend_from_slice(&data.application_data);
+        if !self.data_credit {
+            log::debug!(
+                "[{}:0x{:x}] data packet received without credit",
+                self.device_handle,
+                self.handle
+            );
+            return self
+                .data_transfer_status_ntf(
+                    sequence_number,
+                    DataTransferNtfStatusCode::UciDataTransferStatusErrorNoCreditAvailable,
+                    0,
+                )
+                .try_into()
+                .unwrap();
+        }
 
+        let complete = data.pbf == PacketBoundaryFlag::Complete;
+        let max_data_size = self.app_config.max_data_size_in_ranging() as usize;
+        let message_size = self
+            .data_message
+            .as_ref()
+            .map_or(0, |message| message.application_data.len())
+            + data.application_data.len();
+        if self.data_message_rejected || message_size > max_data_size {
+            log::debug!(
+                "[{}:0x{:x}] data message rejected",
+                self.device_handle,
+                self.handle
+            );
+            self.data_message = None;
+            self.data_message_rejected = !complete;
+            return self
+                .data_transfer_status_ntf(
+                    sequence_number,
+                    DataTransferNtfStatusCode::UciDataTransferStatusErrorRejected,
+                    0,
+                )
+                .try_into()
+                .unwrap();
+        }
+
+        // The destination address and sequence number of the message
+        // are taken from the first segment.
+        self.data_message
+            .get_or_insert_with(|| DataMessage {
+                destination_address: data.destination_address,
+                sequence_number,
+                application_data: vec![],
+                tx_count: 0,
+            })
+            .application_data
+            .extend_from_slice(&data.application_data);
+        if complete {
+            self.data_queue.push_back(self.data_message.take().unwrap());
+        }
+
+        self.data_credit = self.buffered_data_size() < max_data_size;
         SessionDataCreditNtf {
-            credit_availability: CreditAvailability::CreditAvailable,
+            credit_availability: if self.data_credit {
+                CreditAvailability::CreditAvailable
+            } else {
+                CreditAvailability::CreditNotAvailable
+            },
             session_token,
         }
         .try_into()
         .unwrap()
     }
+
+    /// Return the application data messages transmitted in the next ranging
+    /// round: the messages at the front of the queue whose cumulated size
+    /// does not exceed the maximum data size.
+    pub fn pending_data_messages(&self) -> impl Iterator<Item = &DataMessage> {
+        let max_data_size = self.app_config.max_data_size_in_ranging() as usize;
+        let mut data_size = 0;
+        self.data_queue
+            .iter()
+            .enumerate()
+            .take_while(move |(index, message)| {
+                data_size += message.application_data.len();
+                *index == 0 || data_size <= max_data_size
+            })
+            .map(|(_, message)| message)
+    }
+
+    /// Complete the transmission of the pending application data messages,
+    /// with the delivery result of each message. Delivered messages are
+    /// reported with SESSION_DATA_TRANSFER_STATUS_NTF if enabled, the other
+    /// messages are transmitted again in the next ranging round, until
+    /// MAX_DATA_TX_COUNT transmissions have failed. The data credit is
+    /// granted again when the buffer is no longer full.
+    pub fn data_messages_transmitted(&mut self, delivered: &[bool]) {
+        let mut retried = vec![];
+        for delivered in delivered {
+            let mut message = self.data_queue.pop_front().unwrap();
+            message.tx_count += 1;
+            let status = if *delivered {
+                DataTransferNtfStatusCode::UciDataTransferStatusOk
+            } else if message.tx_count < MAX_DATA_TX_COUNT {
+                retried.push(message);
+                continue;
+            } else {
+                DataTransferNtfStatusCode::UciDataTransferStatusErrorDataTransfer
+            };
+            log::debug!(
+                "[{}:0x{:x}] data message {} transmitted: {:?}",
+                self.device_handle,
+                self.handle,
+                message.sequence_number,
+                status
+            );
+            if !*delivered || self.is_session_data_transfer_status_ntf_enabled() {
+                let ntf = self.data_transfer_status_ntf(
+                    message.sequence_number,
+                    status,
+                    message.tx_count,
+                );
+                let _ = self.tx.send(ntf.encode_to_vec().unwrap());
+            }
+        }
+        for message in retried.into_iter().rev() {
+            self.data_queue.push_front(message);
+        }
+
+        let max_data_size = self.app_config.max_data_size_in_ranging() as usize;
+        if !self.data_credit && self.buffered_data_size() < max_data_size {
+            self.data_credit = true;
+            let _ = self.tx.send(
+                SessionDataCreditNtf {
+                    credit_availability: CreditAvailability::CreditAvailable,
+                    session_token: self.handle,
+                }
+                .encode_to_vec()
+                .unwrap(),
+            );
+        }
+    }
 }
 
 impl Drop for Session {
@@ -172,3 +385,172 @@ impl Drop for Session {
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    /// Create a data session, and the receiver of the packets sent
+    /// to the host.
+    fn session() -> (Session, mpsc::UnboundedReceiver<Vec<u8>>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut session = Session::new(
+            1,
+            1,
+            SessionType::FiraRangingAndInBandDataSession,
+            0,
+            OutboundQueue::new(tx),
+        );
+        session.app_config.session_data_transfer_status_ntf_config =
+            uci::SessionDataTransferStatusNtfConfig::Enable;
+        (session, rx)
+    }
+
+    fn data_message_snd(
+        session: &mut Session,
+        sequence_number: u16,
+        size: usize,
+        pbf: PacketBoundaryFlag,
+    ) -> Vec<u8> {
+        session
+            .data_message_snd(DataMessageSnd {
+                session_handle: 1,
+                destination_address: 0x1234,
+                data_sequence_number: sequence_number,
+                application_data: vec![0; size],
+                pbf,
+            })
+            .encode_to_vec()
+            .unwrap()
+    }
+
+    fn decode<T: Packet>(packet: &[u8]) -> T {
+        T::decode_full(packet).unwrap()
+    }
+
+    /// Receive the next packet sent to the host.
+    fn next<T: Packet>(rx: &mut mpsc::UnboundedReceiver<Vec<u8>>) -> T {
+        decode(&rx.try_recv().unwrap())
+    }
+
+    #[test]
+    fn test_data_credit() {
+        let (mut session, mut rx) = session();
+        let max_data_size = session.app_config.max_data_size_in_ranging() as usize;
+
+        // The credit is withdrawn when the buffer is full,
+        // and the packets received without credit are rejected.
+        let ntf: SessionDataCreditNtf = decode(&data_message_snd(
+            &mut session,
+            1,
+            max_data_size,
+            PacketBoundaryFlag::Complete,
+        ));
+        assert_eq!(
+            ntf.credit_availability,
+            CreditAvailability::CreditNotAvailable
+        );
+        let ntf: SessionDataTransferStatusNtf = decode(&data_message_snd(
+            &mut session,
+            2,
+            1,
+            PacketBoundaryFlag::Complete,
+        ));
+        assert_eq!(ntf.uci_sequence_number, 2);
+        assert_eq!(
+            ntf.status,
+            DataTransferNtfStatusCode::UciDataTransferStatusErrorNoCreditAvailable
+        );
+
+        // The credit is granted again when the message is transmitted.
+        assert_eq!(session.pending_data_messages().count(), 1);
+        session.data_messages_transmitted(&[true]);
+        let ntf: SessionDataTransferStatusNtf = next(&mut rx);
+        assert_eq!(ntf.uci_sequence_number, 1);
+        assert_eq!(
+            ntf.status,
+            DataTransferNtfStatusCode::UciDataTransferStatusOk
+        );
+        assert_eq!(ntf.tx_count, 1);
+        let ntf: SessionDataCreditNtf = next(&mut rx);
+        assert_eq!(ntf.credit_availability, CreditAvailability::CreditAvailable);
+        assert!(rx.try_recv().is_err());
+
+        // The UCI sequence number is reported unmodified.
+        let ntf: SessionDataCreditNtf = decode(&data_message_snd(
+            &mut session,
+            0x1234,
+            1,
+            PacketBoundaryFlag::Complete,
+        ));
+        assert_eq!(ntf.credit_availability, CreditAvailability::CreditAvailable);
+        session.data_messages_transmitted(&[true]);
+        let ntf: SessionDataTransferStatusNtf = next(&mut rx);
+        assert_eq!(ntf.uci_sequence_number, 0x1234);
+    }
+
+    #[test]
+    fn test_data_retransmission() {
+        let (mut session, mut rx) = session();
+        data_message_snd(&mut session, 1, 10, PacketBoundaryFlag::Complete);
+
+        // The message is transmitted again until MAX_DATA_TX_COUNT
+        // transmissions have failed.
+        for _ in 1..MAX_DATA_TX_COUNT {
+            session.data_messages_transmitted(&[false]);
+            assert!(rx.try_recv().is_err());
+            assert_eq!(session.pending_data_messages().count(), 1);
+        }
+        session.data_messages_transmitted(&[false]);
+        let ntf: SessionDataTransferStatusNtf = next(&mut rx);
+        assert_eq!(ntf.uci_sequence_number, 1);
+        assert_eq!(
+            ntf.status,
+            DataTransferNtfStatusCode::UciDataTransferStatusErrorDataTransfer
+        );
+        assert_eq!(ntf.tx_count, MAX_DATA_TX_COUNT);
+        assert_eq!(session.pending_data_messages().count(), 0);
+    }
+
+    #[test]
+    fn test_oversized_data_message() {
+        let (mut session, _rx) = session();
+        let max_data_size = session.app_config.max_data_size_in_ranging() as usize;
+
+        // The message is rejected when its size exceeds the maximum data
+        // size, and the following segments of the message are discarded.
+        data_message_snd(&mut session, 1, 100, PacketBoundaryFlag::NotComplete);
+        let ntf: SessionDataTransferStatusNtf = decode(&data_message_snd(
+            &mut session,
+            1,
+            max_data_size,
+            PacketBoundaryFlag::NotComplete,
+        ));
+        assert_eq!(
+            ntf.status,
+            DataTransferNtfStatusCode::UciDataTransferStatusErrorRejected
+        );
+        let ntf: SessionDataTransferStatusNtf = decode(&data_message_snd(
+            &mut session,
+            1,
+            1,
+            PacketBoundaryFlag::Complete,
+        ));
+        assert_eq!(
+            ntf.status,
+            DataTransferNtfStatusCode::UciDataTransferStatusErrorRejected
+        );
+        assert_eq!(session.pending_data_messages().count(), 0);
+
+        // The next message is accepted.
+        let ntf: SessionDataCreditNtf = decode(&data_message_snd(
+            &mut session,
+            2,
+            1,
+            PacketBoundaryFlag::Complete,
+        ));
+        assert_eq!(ntf.credit_availability, CreditAvailability::CreditAvailable);
+        assert_eq!(session.pending_data_messages().count(), 1);
+    }
+}
@@ -0,0 +1,313 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::packets::uci;
+use crate::{Handle, Location, RangingContext, RangingEstimator, RangingMeasurement};
+use std::sync::Mutex;
+
+/// Error model applied by the [`StochasticRangingEstimator`] to the
+/// ideal measurements.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NoiseModel {
+    /// Standard deviation of the range error, in centimeters.
+    pub range_std_dev: f64,
+    /// Additional standard deviation of the range error, in centimeters
+    /// per meter of distance between the devices.
+    pub range_std_dev_per_meter: f64,
+    /// Standard deviation of the azimuth error, in degrees.
+    pub azimuth_std_dev: f64,
+    /// Standard deviation of the elevation error, in degrees.
+    pub elevation_std_dev: f64,
+    /// Probability for a measurement to be in Non Line Of Sight.
+    pub nlos_probability: f64,
+    /// Positive bias added to the range of NLOS measurements,
+    /// in centimeters.
+    pub nlos_range_bias: f64,
+    /// Factor applied to the angle errors of NLOS measurements.
+    pub nlos_angle_error_factor: f64,
+    /// Received signal strength at 1 meter, in dBm.
+    pub rssi_at_one_meter: f64,
+    /// Path loss exponent used to derive the RSSI from the range.
+    pub path_loss_exponent: f64,
+    /// Attenuation of NLOS measurements, in dB.
+    pub nlos_attenuation: f64,
+    /// Standard deviation of the RSSI, in dB.
+    pub rssi_std_dev: f64,
+}
+
+impl Default for NoiseModel {
+    fn default() -> Self {
+        NoiseModel {
+            range_std_dev: 5.0,
+            range_std_dev_per_meter: 0.5,
+            azimuth_std_dev: 3.0,
+            elevation_std_dev: 5.0,
+            nlos_probability: 0.05,
+            nlos_range_bias: 50.0,
+            nlos_angle_error_factor: 4.0,
+            rssi_at_one_meter: -55.0,
+            path_loss_exponent: 2.0,
+            nlos_attenuation: 10.0,
+            rssi_std_dev: 2.0,
+        }
+    }
+}
+
+/// Ranging estimator adding noise to the measurements of an ideal
+/// estimator, according to the configured [`NoiseModel`].
+/// The random sequence is seeded at creation, so that the measurements
+/// are reproducible for identical sequences of estimations.
+pub struct StochasticRangingEstimator {
+    estimator: Box<dyn RangingEstimator>,
+    model: NoiseModel,
+    rng: Mutex<Rng>,
+}
+
+impl StochasticRangingEstimator {
+    pub fn new(estimator: Box<dyn RangingEstimator>, model: NoiseModel, seed: u64) -> Self {
+        StochasticRangingEstimator {
+            estimator,
+            model,
+            rng: Mutex::new(Rng(seed)),
+        }
+    }
+}
+
+impl RangingEstimator for StochasticRangingEstimator {
+    fn estimate(
+        &self,
+        left: &Handle,
+        right: &Handle,
+        context: &RangingContext,
+    ) -> Option<RangingMeasurement> {
+        let ideal = self.estimator.estimate(left, right, context)?;
+        if ideal.status != uci::Status::Ok {
+            return Some(ideal);
+        }
+
+        let model = &self.model;
+        let mut rng = self.rng.lock().unwrap();
+
+        let distance_m = ideal.range as f64 / 100.0;
+        // The measurement may already be in NLOS in the ideal model,
+        // e.g. when obstructed; the range bias of the ideal measurement
+        // is then preserved by the noise added to the range.
+        let random_nlos = rng.uniform() < model.nlos_probability;
+        let nlos = ideal.nlos || random_nlos;
+        let angle_error_factor = if nlos {
+            model.nlos_angle_error_factor
+        } else {
+            1.0
+        };
+
+        let range_std_dev = model.range_std_dev + model.range_std_dev_per_meter * distance_m;
+        let range_bias = if random_nlos {
+            model.nlos_range_bias
+        } else {
+            0.0
+        };
+        let range = ideal.range as f64 + range_bias + range_std_dev * rng.gaussian();
+
+        let azimuth_std_dev = model.azimuth_std_dev * angle_error_factor;
+        let azimuth = ideal.azimuth as f64 + azimuth_std_dev * rng.gaussian();
+        let elevation_std_dev = model.elevation_std_dev * angle_error_factor;
+        let elevation = ideal.elevation as f64 + elevation_std_dev * rng.gaussian();
+
+        // The attenuation of the ideal measurement is the difference
+        // between the modelled free-space RSSI and the ideal RSSI. The
+        // default ideal RSSI (u8::MAX) denotes an unknown signal strength.
+        let free_space_rssi =
+            model.rssi_at_one_meter - 10.0 * model.path_loss_exponent * distance_m.max(0.1).log10();
+        let ideal_attenuation = if ideal.rssi != u8::MAX {
+            // [UCI] The RSSI is reported in -dBm, Q7.1 format.
+            (free_space_rssi + ideal.rssi as f64 / 2.0).max(0.0)
+        } else {
+            0.0
+        };
+        let attenuation = if random_nlos {
+            ideal_attenuation + model.nlos_attenuation
+        } else {
+            ideal_attenuation
+        };
+        let rssi = free_space_rssi - attenuation + model.rssi_std_dev * rng.gaussian();
+
+        Some(RangingMeasurement {
+            range: range.round().clamp(0.0, u16::MAX as f64) as u16,
+            azimuth: wrap_azimuth(azimuth.round() as i16),
+            elevation: elevation.round().clamp(-90.0, 90.0) as i8,
+            nlos,
+            azimuth_fom: figure_of_merit(azimuth_std_dev).min(ideal.azimuth_fom),
+            elevation_fom: figure_of_merit(elevation_std_dev).min(ideal.elevation_fom),
+            // [UCI] The RSSI is reported in -dBm, Q7.1 format.
+            rssi: (-2.0 * rssi).round().clamp(0.0, u8::MAX as f64) as u8,
+            ..ideal
+        })
+    }
+
+    fn location(&self, device: &Handle) -> Option<Location> {
+        self.estimator.location(device)
+    }
+}
+
+/// Wrap the azimuth in the range ]-180, 180].
+fn wrap_azimuth(azimuth: i16) -> i16 {
+    let azimuth = azimuth.rem_euclid(360);
+    if azimuth > 180 {
+        azimuth - 360
+    } else {
+        azimuth
+    }
+}
+
+/// Derive the figure of merit (0-100) of an angle estimate from the
+/// standard deviation of the error, in degrees. The figure of merit
+/// drops to 0 for a standard deviation of 45 degrees or more.
+fn figure_of_merit(std_dev: f64) -> u8 {
+    (100.0 * (1.0 - std_dev / 45.0)).round().clamp(0.0, 100.0) as u8
+}
+
+/// SplitMix64 pseudo random number generator.
+pub(crate) struct Rng(pub u64);
+
+impl Rng {
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform sample in the range ]0, 1].
+    pub fn uniform(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Standard normal sample, generated with the Box-Muller transform.
+    fn gaussian(&mut self) -> f64 {
+        let u1 = self.uniform();
+        let u2 = self.uniform();
+        (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppConfig;
+
+    struct FixedRangingEstimator(RangingMeasurement);
+
+    impl RangingEstimator for FixedRangingEstimator {
+        fn estimate(
+            &self,
+            _left: &Handle,
+            _right: &Handle,
+            _context: &RangingContext,
+        ) -> Option<RangingMeasurement> {
+            Some(self.0)
+        }
+    }
+
+    fn estimator(model: NoiseModel, seed: u64) -> StochasticRangingEstimator {
+        let ideal = RangingMeasurement {
+            range: 500,
+            azimuth: 30,
+            elevation: -10,
+            ..Default::default()
+        };
+        StochasticRangingEstimator::new(Box::new(FixedRangingEstimator(ideal)), model, seed)
+    }
+
+    fn estimate(estimator: &StochasticRangingEstimator) -> RangingMeasurement {
+        let app_config = AppConfig::default();
+        let context = RangingContext {
+            session_id: 0,
+            session_type: uci::SessionType::FiraRangingSession,
+            app_config: &app_config,
+            round_index: 0,
+            time: Default::default(),
+        };
+        estimator.estimate(&0, &1, &context).unwrap()
+    }
+
+    #[test]
+    fn test_reproducible() {
+        let a = estimator(NoiseModel::default(), 42);
+        let b = estimator(NoiseModel::default(), 42);
+        for _ in 0..100 {
+            let a = estimate(&a);
+            let b = estimate(&b);
+            assert_eq!(
+                (a.range, a.azimuth, a.nlos, a.rssi),
+                (b.range, b.azimuth, b.nlos, b.rssi)
+            );
+        }
+    }
+
+    #[test]
+    fn test_noise_statistics() {
+        let model = NoiseModel {
+            nlos_probability: 0.0,
+            ..Default::default()
+        };
+        let estimator = estimator(model.clone(), 7);
+        let n = 10000;
+        let ranges: Vec<f64> = (0..n)
+            .map(|_| estimate(&estimator))
+            .inspect(|measurement| assert!(!measurement.nlos))
+            .map(|measurement| measurement.range as f64)
+            .collect();
+        let mean = ranges.iter().sum::<f64>() / n as f64;
+        let std_dev = (ranges.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / n as f64).sqrt();
+        let expected_std_dev = model.range_std_dev + model.range_std_dev_per_meter * 5.0;
+        assert!((mean - 500.0).abs() < 1.0, "mean {}", mean);
+        assert!(
+            (std_dev - expected_std_dev).abs() < 0.5,
+            "std_dev {}",
+            std_dev
+        );
+    }
+
+    #[test]
+    fn test_ideal_attenuation() {
+        let model = NoiseModel {
+            nlos_probability: 0.0,
+            rssi_std_dev: 0.0,
+            ..Default::default()
+        };
+        let ideal = RangingMeasurement {
+            range: 1000,
+            ..Default::default()
+        };
+        let free_space = StochasticRangingEstimator::new(
+            Box::new(FixedRangingEstimator(ideal)),
+            model.clone(),
+            3,
+        );
+        // -75 dBm at 10 meters in free space.
+        assert_eq!(estimate(&free_space).rssi, 150);
+
+        // Ideal measurement attenuated by 10 dB.
+        let attenuated = StochasticRangingEstimator::new(
+            Box::new(FixedRangingEstimator(RangingMeasurement {
+                rssi: 170,
+                ..ideal
+            })),
+            model,
+            3,
+        );
+        assert_eq!(estimate(&attenuated).rssi, 170);
+    }
+}
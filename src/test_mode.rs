@@ -0,0 +1,129 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::packets::uci;
+use std::time::Duration;
+
+/// [UCI] Test Configuration Parameters.
+/// The configuration is initially filled with default values from the
+/// specification, and applies to the tests started for the session
+/// of type DEVICE_TEST_MODE.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TestConfig {
+    /// Number of packets transmitted or received by the test.
+    pub num_packets: u32,
+    /// Gap between two consecutive packets, in microseconds.
+    pub t_gap: u32,
+    /// Start of the reception window, in microseconds.
+    pub t_start: u32,
+    /// Duration of the reception window, in microseconds.
+    pub t_win: u32,
+    randomize_psdu: u8,
+    phr_ranging_bit: u8,
+    rmarker_tx_start: u32,
+    rmarker_rx_start: u32,
+    sts_index_auto_incr: u8,
+    sts_detect_bitmap: u8,
+}
+
+impl Default for TestConfig {
+    fn default() -> Self {
+        TestConfig {
+            num_packets: 1000,
+            t_gap: 2000,
+            t_start: 450,
+            t_win: 750,
+            randomize_psdu: 0,
+            phr_ranging_bit: 0,
+            rmarker_tx_start: 0,
+            rmarker_rx_start: 0,
+            sts_index_auto_incr: 0,
+            sts_detect_bitmap: 0,
+        }
+    }
+}
+
+impl TestConfig {
+    /// Set the test configuration value with the selected identifier
+    /// and value. Returns `Ok` if the value is well formatted,
+    /// `Err` otherwise.
+    pub fn set(&mut self, id: uci::TestConfigTlvType, value: &[u8]) -> anyhow::Result<()> {
+        fn try_parse_u8(value: &[u8]) -> anyhow::Result<u8> {
+            Ok(u8::from_le_bytes(value.try_into()?))
+        }
+
+        fn try_parse_u32(value: &[u8]) -> anyhow::Result<u32> {
+            Ok(u32::from_le_bytes(value.try_into()?))
+        }
+
+        match id {
+            uci::TestConfigTlvType::NumPackets => self.num_packets = try_parse_u32(value)?,
+            uci::TestConfigTlvType::TGap => self.t_gap = try_parse_u32(value)?,
+            uci::TestConfigTlvType::TStart => self.t_start = try_parse_u32(value)?,
+            uci::TestConfigTlvType::TWin => self.t_win = try_parse_u32(value)?,
+            uci::TestConfigTlvType::RandomizePsdu => self.randomize_psdu = try_parse_u8(value)?,
+            uci::TestConfigTlvType::PhrRangingBit => self.phr_ranging_bit = try_parse_u8(value)?,
+            uci::TestConfigTlvType::RmarkerTxStart => self.rmarker_tx_start = try_parse_u32(value)?,
+            uci::TestConfigTlvType::RmarkerRxStart => self.rmarker_rx_start = try_parse_u32(value)?,
+            uci::TestConfigTlvType::StsIndexAutoIncr => {
+                self.sts_index_auto_incr = try_parse_u8(value)?
+            }
+            uci::TestConfigTlvType::StsDetectBitmap => {
+                self.sts_detect_bitmap = try_parse_u8(value)?
+            }
+        }
+        Ok(())
+    }
+
+    /// Retrieve the test configuration value with the selected identifier.
+    pub fn get(&self, id: uci::TestConfigTlvType) -> Vec<u8> {
+        match id {
+            uci::TestConfigTlvType::NumPackets => self.num_packets.to_le_bytes().to_vec(),
+            uci::TestConfigTlvType::TGap => self.t_gap.to_le_bytes().to_vec(),
+            uci::TestConfigTlvType::TStart => self.t_start.to_le_bytes().to_vec(),
+            uci::TestConfigTlvType::TWin => self.t_win.to_le_bytes().to_vec(),
+            uci::TestConfigTlvType::RandomizePsdu => vec![self.randomize_psdu],
+            uci::TestConfigTlvType::PhrRangingBit => vec![self.phr_ranging_bit],
+            uci::TestConfigTlvType::RmarkerTxStart => self.rmarker_tx_start.to_le_bytes().to_vec(),
+            uci::TestConfigTlvType::RmarkerRxStart => self.rmarker_rx_start.to_le_bytes().to_vec(),
+            uci::TestConfigTlvType::StsIndexAutoIncr => vec![self.sts_index_auto_incr],
+            uci::TestConfigTlvType::StsDetectBitmap => vec![self.sts_detect_bitmap],
+        }
+    }
+}
+
+/// Test started with one of the TEST group commands.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Test {
+    PeriodicTx { psdu_data: Vec<u8> },
+    PerRx { psdu_data: Vec<u8> },
+    Rx,
+    Loopback { psdu_data: Vec<u8> },
+    SsTwr,
+}
+
+impl Test {
+    /// Duration of the test, after which the test notification is sent.
+    /// PERIODIC_TX and PER_RX tests last for NUM_PACKETS packets,
+    /// the other tests for a single packet.
+    pub fn duration(&self, config: &TestConfig) -> Duration {
+        let t_gap = config.t_gap as u64;
+        match self {
+            Test::PeriodicTx { .. } | Test::PerRx { .. } => {
+                Duration::from_micros(config.num_packets as u64 * t_gap)
+            }
+            Test::Rx | Test::Loopback { .. } | Test::SsTwr => Duration::from_micros(t_gap),
+        }
+    }
+}
@@ -0,0 +1,79 @@
+// Copyright 2022 Google LLC
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     https://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Emulated UWBS clock of a device, derived from the pica clock.
+/// The UWBS time is expressed in microseconds, and starts at the offset
+/// when the pica clock starts. The drift is the deviation of the UWBS
+/// clock frequency relative to the pica clock, in parts per million.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct UwbsClock {
+    /// UWBS time at the start of the pica clock, in microseconds.
+    pub offset_us: u64,
+    /// Clock drift, in parts per million.
+    pub drift_ppm: f64,
+}
+
+impl UwbsClock {
+    fn rate(&self) -> f64 {
+        1.0 + self.drift_ppm * 1e-6
+    }
+
+    /// Return the UWBS timestamp at the selected pica time.
+    pub fn timestamp(&self, time: Duration) -> u64 {
+        let elapsed_us = (time.as_micros() as f64 * self.rate()).round() as u64;
+        self.offset_us.saturating_add(elapsed_us)
+    }
+
+    /// Return the pica time at which the UWBS clock reaches the selected
+    /// timestamp. Timestamps preceding the start of the pica clock
+    /// are mapped to the start of the pica clock.
+    pub fn time(&self, timestamp: u64) -> Duration {
+        let elapsed_us = timestamp.saturating_sub(self.offset_us);
+        self.duration(Duration::from_micros(elapsed_us))
+    }
+
+    /// Convert a duration measured by the UWBS clock to the pica clock.
+    pub fn duration(&self, duration: Duration) -> Duration {
+        Duration::from_micros((duration.as_micros() as f64 / self.rate()).round() as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uwbs_clock() {
+        let clock = UwbsClock::default();
+        assert_eq!(clock.timestamp(Duration::from_millis(5)), 5_000);
+        assert_eq!(clock.time(5_000), Duration::from_millis(5));
+
+        let clock = UwbsClock {
+            offset_us: 1_000_000,
+            drift_ppm: 100.0,
+        };
+        assert_eq!(clock.timestamp(Duration::ZERO), 1_000_000);
+        assert_eq!(clock.timestamp(Duration::from_secs(10)), 11_001_000);
+        assert_eq!(clock.time(11_001_000), Duration::from_secs(10));
+        assert_eq!(clock.time(0), Duration::ZERO);
+        assert_eq!(
+            clock.duration(Duration::from_micros(100_010)).as_micros(),
+            100_000
+        );
+    }
+}
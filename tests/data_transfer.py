@@ -35,11 +35,13 @@ async def controller(host: Host, peer: Host, file: Path):
         )
     )
 
-    await host.expect_control(uci.SessionInitRsp(status=uci.Status.OK))
+    rsp = await host.expect_control(uci.SessionInitRsp_V2)
+    assert rsp.status == uci.Status.OK
+    session_handle = rsp.session_handle
 
     await host.expect_control(
         uci.SessionStatusNtf(
-            session_token=0,
+            session_token=session_handle,
             session_state=uci.SessionState.SESSION_STATE_INIT,
             reason_code=0,
         )
@@ -50,7 +52,7 @@ async def controller(host: Host, peer: Host, file: Path):
 
     host.send_control(
         uci.SessionSetAppConfigCmd(
-            session_token=0,
+            session_token=session_handle,
             tlvs=[
                 uci.AppConfigTlv(
                     cfg_id=uci.AppConfigTlvType.DEVICE_ROLE,
@@ -88,6 +90,10 @@ async def controller(host: Host, peer: Host, file: Path):
                 uci.AppConfigTlv(
                     cfg_id=uci.AppConfigTlvType.DST_MAC_ADDRESS, v=peer.mac_address
                 ),
+                uci.AppConfigTlv(
+                    cfg_id=uci.AppConfigTlvType.SESSION_DATA_TRANSFER_STATUS_NTF_CONFIG,
+                    v=bytes([uci.SessionDataTransferStatusNtfConfig.ENABLE]),
+                ),
             ],
         )
     )
@@ -98,22 +104,26 @@ async def controller(host: Host, peer: Host, file: Path):
 
     await host.expect_control(
         uci.SessionStatusNtf(
-            session_token=0,
+            session_token=session_handle,
             session_state=uci.SessionState.SESSION_STATE_IDLE,
             reason_code=0,
         )
     )
 
-    await data_transfer(host, peer.mac_address, file, 0)
+    max_data_size = await query_max_data_size(host, session_handle)
+    seq_num = 0
+    await data_transfer(
+        host, peer.mac_address, file, session_handle, max_data_size, seq_num
+    )
 
     # START SESSION CMD
-    host.send_control(uci.SessionStartCmd(session_id=0))
+    host.send_control(uci.SessionStartCmd(session_id=session_handle))
 
     await host.expect_control(uci.SessionStartRsp(status=uci.Status.OK))
 
     await host.expect_control(
         uci.SessionStatusNtf(
-            session_token=0,
+            session_token=session_handle,
             session_state=uci.SessionState.SESSION_STATE_ACTIVE,
             reason_code=0,
         )
@@ -126,17 +136,35 @@ async def controller(host: Host, peer: Host, file: Path):
     event = await host.expect_control(uci.ShortMacTwoWaySessionInfoNtf, timeout=2.0)
     event.show()
 
+    # The message is delivered in the first ranging round,
+    # which frees the data buffer.
+    await host.expect_control(
+        uci.SessionDataTransferStatusNtf(
+            session_token=session_handle,
+            uci_sequence_number=seq_num,
+            status=uci.DataTransferNtfStatusCode.UCI_DATA_TRANSFER_STATUS_OK,
+            tx_count=1,
+        )
+    )
+
+    await host.expect_control(
+        uci.SessionDataCreditNtf(
+            session_token=session_handle,
+            credit_availability=uci.CreditAvailability.CREDIT_AVAILABLE,
+        )
+    )
+
     event = await host.expect_control(uci.ShortMacTwoWaySessionInfoNtf, timeout=2.0)
     event.show()
 
     # STOP SESSION
-    host.send_control(uci.SessionStopCmd(session_id=0))
+    host.send_control(uci.SessionStopCmd(session_id=session_handle))
 
     await host.expect_control(uci.SessionStopRsp(status=uci.Status.OK))
 
     await host.expect_control(
         uci.SessionStatusNtf(
-            session_token=0,
+            session_token=session_handle,
             session_state=uci.SessionState.SESSION_STATE_IDLE,
             reason_code=0,
         )
@@ -147,7 +175,7 @@ async def controller(host: Host, peer: Host, file: Path):
     )
 
     # DEINIT
-    host.send_control(uci.SessionDeinitCmd(session_token=0))
+    host.send_control(uci.SessionDeinitCmd(session_token=session_handle))
 
     await host.expect_control(uci.SessionDeinitRsp(status=uci.Status.OK))
 
@@ -162,11 +190,13 @@ async def controlee(host: Host, peer: Host, file: Path):
         )
     )
 
-    await host.expect_control(uci.SessionInitRsp(status=uci.Status.OK))
+    rsp = await host.expect_control(uci.SessionInitRsp_V2)
+    assert rsp.status == uci.Status.OK
+    session_handle = rsp.session_handle
 
     await host.expect_control(
         uci.SessionStatusNtf(
-            session_token=0,
+            session_token=session_handle,
             session_state=uci.SessionState.SESSION_STATE_INIT,
             reason_code=0,
         )
@@ -177,7 +207,7 @@ async def controlee(host: Host, peer: Host, file: Path):
 
     host.send_control(
         uci.SessionSetAppConfigCmd(
-            session_token=0,
+            session_token=session_handle,
             tlvs=[
                 uci.AppConfigTlv(
                     cfg_id=uci.AppConfigTlvType.DEVICE_ROLE,
@@ -225,19 +255,21 @@ async def controlee(host: Host, peer: Host, file: Path):
 
     await host.expect_control(
         uci.SessionStatusNtf(
-            session_token=0,
+            session_token=session_handle,
             session_state=uci.SessionState.SESSION_STATE_IDLE,
             reason_code=0,
         )
     )
 
-    host.send_control(uci.SessionStartCmd(session_id=0))
+    max_data_size = await query_max_data_size(host, session_handle)
+
+    host.send_control(uci.SessionStartCmd(session_id=session_handle))
 
     await host.expect_control(uci.SessionStartRsp(status=uci.Status.OK))
 
     await host.expect_control(
         uci.SessionStatusNtf(
-            session_token=0,
+            session_token=session_handle,
             session_state=uci.SessionState.SESSION_STATE_ACTIVE,
             reason_code=0,
         )
@@ -248,29 +280,34 @@ async def controlee(host: Host, peer: Host, file: Path):
     )
 
     with file.open("rb") as f:
-        application_data = list(bytearray(f.read()))
-        event = await host.expect_data(
-            uci.DataMessageRcv(
-                session_handle=0,
-                status=uci.Status.OK,
-                source_address=int.from_bytes(peer.mac_address, "little"),
-                data_sequence_number=0x01,
-                application_data=application_data,
-            ),
-            timeout=2.0,
-        )
+        application_data = list(bytearray(f.read()[:max_data_size]))
+
+    # The application data message is segmented in data packets,
+    # all the segments carry the fields of the message.
+    received_data = []
+    while True:
+        event = await host.expect_data(uci.DataMessageRcv, timeout=2.0)
         event.show()
+        assert event.session_handle == session_handle
+        assert event.status == uci.Status.OK
+        assert event.source_address == int.from_bytes(peer.mac_address, "little")
+        assert event.data_sequence_number == 0
+        received_data += event.application_data
+        if event.pbf == uci.PacketBoundaryFlag.COMPLETE:
+            break
+        assert len(event.application_data) > 0
+    assert received_data == application_data
 
     event = await host.expect_control(uci.ShortMacTwoWaySessionInfoNtf, timeout=2.0)
     event.show()
 
-    host.send_control(uci.SessionStopCmd(session_id=0))
+    host.send_control(uci.SessionStopCmd(session_id=session_handle))
 
     await host.expect_control(uci.SessionStopRsp(status=uci.Status.OK))
 
     await host.expect_control(
         uci.SessionStatusNtf(
-            session_token=0,
+            session_token=session_handle,
             session_state=uci.SessionState.SESSION_STATE_IDLE,
             reason_code=0,
         )
@@ -280,73 +317,65 @@ async def controlee(host: Host, peer: Host, file: Path):
         uci.CoreDeviceStatusNtf(device_state=uci.DeviceState.DEVICE_STATE_READY)
     )
 
-    host.send_control(uci.SessionDeinitCmd(session_token=0))
+    host.send_control(uci.SessionDeinitCmd(session_token=session_handle))
 
     await host.expect_control(uci.SessionDeinitRsp(status=uci.Status.OK))
 
 
+async def query_max_data_size(host: Host, session_handle: int) -> int:
+    host.send_control(
+        uci.SessionQueryMaxDataSizeInRangingCmd(session_token=session_handle)
+    )
+
+    event = await host.expect_control(uci.SessionQueryMaxDataSizeInRangingRsp)
+    assert event.session_token == session_handle
+    assert event.max_data_size > 0
+    return event.max_data_size
+
+
 async def data_transfer(
-    host: Host, dst_mac_address: bytes, file: Path, session_id: int
+    host: Host,
+    dst_mac_address: bytes,
+    file: Path,
+    session_handle: int,
+    max_data_size: int,
+    seq_num: int,
 ):
-    try:
-        with open(file, "rb") as f:
-            b = f.read()
-            seq_num = 0
-
-            if len(b) > MAX_DATA_PACKET_PAYLOAD_SIZE:
-                for i in range(0, len(b), MAX_DATA_PACKET_PAYLOAD_SIZE):
-                    chunk = b[i : i + MAX_DATA_PACKET_PAYLOAD_SIZE]
-
-                    if i + MAX_DATA_PACKET_PAYLOAD_SIZE >= len(b):
-                        host.send_data(
-                            uci.DataMessageSnd(
-                                session_handle=int(session_id),
-                                destination_address=int.from_bytes(dst_mac_address),
-                                data_sequence_number=seq_num,
-                                application_data=chunk,
-                            )
-                        )
-                    else:
-                        host.send_data(
-                            uci.DataMessageSnd(
-                                session_handle=int(session_id),
-                                pbf=uci.PacketBoundaryFlag.NOT_COMPLETE,
-                                destination_address=int.from_bytes(dst_mac_address),
-                                data_sequence_number=seq_num,
-                                application_data=chunk,
-                            )
-                        )
-
-                    seq_num += 1
-                    if seq_num >= 65535:
-                        seq_num = 0
-
-                    event = await host.expect_control(
-                        uci.SessionDataCreditNtf(
-                            session_token=int(session_id),
-                            credit_availability=uci.CreditAvailability.CREDIT_AVAILABLE,
-                        )
-                    )
-                    event.show()
-            else:
-                host.send_data(
-                    uci.DataMessageSnd(
-                        session_handle=int(session_id),
-                        destination_address=int.from_bytes(dst_mac_address),
-                        data_sequence_number=seq_num,
-                        application_data=b,
-                    )
-                )
-                event = await host.expect_control(
-                    uci.SessionDataCreditNtf(
-                        session_token=int(session_id),
-                        credit_availability=uci.CreditAvailability.CREDIT_AVAILABLE,
-                    )
+    with open(file, "rb") as f:
+        # The data sent in one ranging round is limited to
+        # the maximum data size of the session.
+        b = f.read()[:max_data_size]
+
+        # The application data message is segmented in data packets,
+        # all the segments carry the sequence number of the message.
+        for i in range(0, len(b), MAX_DATA_PACKET_PAYLOAD_SIZE):
+            chunk = b[i : i + MAX_DATA_PACKET_PAYLOAD_SIZE]
+            complete = i + MAX_DATA_PACKET_PAYLOAD_SIZE >= len(b)
+
+            host.send_data(
+                uci.DataMessageSnd(
+                    session_handle=int(session_handle),
+                    pbf=(
+                        uci.PacketBoundaryFlag.COMPLETE
+                        if complete
+                        else uci.PacketBoundaryFlag.NOT_COMPLETE
+                    ),
+                    destination_address=int.from_bytes(dst_mac_address, "little"),
+                    data_sequence_number=seq_num,
+                    application_data=chunk,
                 )
-                event.show()
-
-    except Exception as e:
-        print(e)
+            )
+
+            # The credit is withdrawn when the data buffered for
+            # the next ranging round reaches the maximum data size.
+            event = await host.expect_control(uci.SessionDataCreditNtf)
+            event.show()
+            assert event.session_token == session_handle
+            assert event.credit_availability == (
+                uci.CreditAvailability.CREDIT_NOT_AVAILABLE
+                if i + len(chunk) >= max_data_size
+                else uci.CreditAvailability.CREDIT_AVAILABLE
+            )
 
 
 async def run(address: str, uci_port: int, file: Path):
@@ -32,11 +32,13 @@ async def controller(host: Host, peer: Host):
         )
     )
 
-    await host.expect_control(uci.SessionInitRsp(status=uci.Status.OK))
+    rsp = await host.expect_control(uci.SessionInitRsp_V2)
+    assert rsp.status == uci.Status.OK
+    session_handle = rsp.session_handle
 
     await host.expect_control(
         uci.SessionStatusNtf(
-            session_token=0,
+            session_token=session_handle,
             session_state=uci.SessionState.SESSION_STATE_INIT,
             reason_code=0,
         )
@@ -47,7 +49,7 @@ async def controller(host: Host, peer: Host):
 
     host.send_control(
         uci.SessionSetAppConfigCmd(
-            session_token=0,
+            session_token=session_handle,
             tlvs=[
                 uci.AppConfigTlv(
                     cfg_id=uci.AppConfigTlvType.DEVICE_ROLE,
@@ -95,19 +97,19 @@ async def controller(host: Host, peer: Host):
 
     await host.expect_control(
         uci.SessionStatusNtf(
-            session_token=0,
+            session_token=session_handle,
             session_state=uci.SessionState.SESSION_STATE_IDLE,
             reason_code=0,
         )
     )
 
-    host.send_control(uci.SessionStartCmd(session_id=0))
+    host.send_control(uci.SessionStartCmd(session_id=session_handle))
 
     await host.expect_control(uci.SessionStartRsp(status=uci.Status.OK))
 
     await host.expect_control(
         uci.SessionStatusNtf(
-            session_token=0,
+            session_token=session_handle,
             session_state=uci.SessionState.SESSION_STATE_ACTIVE,
             reason_code=0,
         )
@@ -121,13 +123,13 @@ async def controller(host: Host, peer: Host):
         event = await host.expect_control(uci.ShortMacTwoWaySessionInfoNtf, timeout=2.0)
         event.show()
 
-    host.send_control(uci.SessionStopCmd(session_id=0))
+    host.send_control(uci.SessionStopCmd(session_id=session_handle))
 
     await host.expect_control(uci.SessionStopRsp(status=uci.Status.OK))
 
     await host.expect_control(
         uci.SessionStatusNtf(
-            session_token=0,
+            session_token=session_handle,
             session_state=uci.SessionState.SESSION_STATE_IDLE,
             reason_code=0,
         )
@@ -137,7 +139,7 @@ async def controller(host: Host, peer: Host):
         uci.CoreDeviceStatusNtf(device_state=uci.DeviceState.DEVICE_STATE_READY)
     )
 
-    host.send_control(uci.SessionDeinitCmd(session_token=0))
+    host.send_control(uci.SessionDeinitCmd(session_token=session_handle))
 
     await host.expect_control(uci.SessionDeinitRsp(status=uci.Status.OK))
 
@@ -151,11 +153,13 @@ async def controlee(host: Host, peer: Host):
         )
     )
 
-    await host.expect_control(uci.SessionInitRsp(status=uci.Status.OK))
+    rsp = await host.expect_control(uci.SessionInitRsp_V2)
+    assert rsp.status == uci.Status.OK
+    session_handle = rsp.session_handle
 
     await host.expect_control(
         uci.SessionStatusNtf(
-            session_token=0,
+            session_token=session_handle,
             session_state=uci.SessionState.SESSION_STATE_INIT,
             reason_code=0,
         )
@@ -166,7 +170,7 @@ async def controlee(host: Host, peer: Host):
 
     host.send_control(
         uci.SessionSetAppConfigCmd(
-            session_token=0,
+            session_token=session_handle,
             tlvs=[
                 uci.AppConfigTlv(
                     cfg_id=uci.AppConfigTlvType.DEVICE_ROLE,
@@ -214,19 +218,19 @@ async def controlee(host: Host, peer: Host):
 
     await host.expect_control(
         uci.SessionStatusNtf(
-            session_token=0,
+            session_token=session_handle,
             session_state=uci.SessionState.SESSION_STATE_IDLE,
             reason_code=0,
         )
     )
 
-    host.send_control(uci.SessionStartCmd(session_id=0))
+    host.send_control(uci.SessionStartCmd(session_id=session_handle))
 
     await host.expect_control(uci.SessionStartRsp(status=uci.Status.OK))
 
     await host.expect_control(
         uci.SessionStatusNtf(
-            session_token=0,
+            session_token=session_handle,
             session_state=uci.SessionState.SESSION_STATE_ACTIVE,
             reason_code=0,
         )
@@ -240,13 +244,13 @@ async def controlee(host: Host, peer: Host):
         event = await host.expect_control(uci.ShortMacTwoWaySessionInfoNtf, timeout=2.0)
         event.show()
 
-    host.send_control(uci.SessionStopCmd(session_id=0))
+    host.send_control(uci.SessionStopCmd(session_id=session_handle))
 
     await host.expect_control(uci.SessionStopRsp(status=uci.Status.OK))
 
     await host.expect_control(
         uci.SessionStatusNtf(
-            session_token=0,
+            session_token=session_handle,
             session_state=uci.SessionState.SESSION_STATE_IDLE,
             reason_code=0,
         )
@@ -256,7 +260,7 @@ async def controlee(host: Host, peer: Host):
         uci.CoreDeviceStatusNtf(device_state=uci.DeviceState.DEVICE_STATE_READY)
     )
 
-    host.send_control(uci.SessionDeinitCmd(session_token=0))
+    host.send_control(uci.SessionDeinitCmd(session_token=session_handle))
 
     await host.expect_control(uci.SessionDeinitRsp(status=uci.Status.OK))
 